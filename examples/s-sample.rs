@@ -1,6 +1,5 @@
 use std::{fmt::Debug, fs::File, io::Read};
 
-use comrak::nodes::NodeKV;
 use comrak::{
     format_html, format_slide, format_slide_js,
     nodes::{AstNode, NodeCode, NodeValue},
@@ -30,6 +29,7 @@ fn comrakOpt() -> ComrakOptions {
             superscript: true,
             footnotes: true,
             description_lists: true,
+            image_attributes: true,
             ..ComrakExtensionOptions::default()
         },
         render: ComrakRenderOptions {
@@ -70,29 +70,9 @@ fn parseSMD() {
             NodeValue::Heading(ref mut head) => {
                 // println!("Head level:{}", head.level);
             }
-            NodeValue::SlideMetaDataBlock(ref mut smd) => {
-                let kv_literal = String::from_utf8_lossy(&smd.literal);
-
-                // smd.metadatas = Vec::new();
-                let lines = kv_literal.lines();
-                for line in lines {
-                    // let kv = NodeValue::KV;
-                    if let Some((k, v)) = line.split_once(':') {
-                        if k.len() <= 0 {
-                            println!("break:: {:?}", line);
-                            break;
-                        }
-
-                        // println!("xxxx {:?}:{:?}", k, v);
-                        let nodekv = NodeKV {
-                            key: k.as_bytes().to_vec(),
-                            value: v.as_bytes().to_vec(),
-                        };
-                        // let kv = NodeValue::KV(nodekv);
-                        smd.metadatas.push(nodekv);
-                    } else {
-                        // println!("{:?}", line);
-                    }
+            NodeValue::SlideMetaDataBlock(ref smd) => {
+                for kv in &smd.metadatas {
+                    println!("{} = {}", String::from_utf8_lossy(&kv.key), kv.value);
                 }
             }
             NodeValue::CodeBlock(ref mut codeblock) => {
@@ -106,7 +86,7 @@ fn parseSMD() {
         }
     });
 
-    dump_node(root);
+    dump_node(root, &mut std::io::stdout());
 
     let mut html = vec![];
     format_slide(root, &opts, &mut html).unwrap();
@@ -135,56 +115,7 @@ fn parseSlide2Input(input: &str) -> String {
 
     let root = parse_document(&arena, md_content, &ComrakOptions::default());
 
-    fn iter_nodes<'a, F>(node: &'a AstNode<'a>, f: &F)
-    where
-        F: Fn(&'a AstNode<'a>),
-    {
-        f(node);
-        for c in node.children() {
-            iter_nodes(c, f);
-        }
-    }
-
-    iter_nodes(root, &|node| {
-        match node.data.borrow_mut().value {
-            NodeValue::SlideMetaDataBlock(ref mut smd) => {
-                let kv_literal = String::from_utf8_lossy(&smd.literal);
-
-                // smd.metadatas = Vec::new();
-                let lines = kv_literal.lines();
-                for line in lines {
-                    // let kv = NodeValue::KV;
-                    if let Some((k, v)) = line.split_once(':') {
-                        if k.len() <= 0 {
-                            // println!("break:: {:?}", line);
-                            break;
-                        }
-
-                        // println!("xxxx {:?}:{:?}", k, v);
-                        let nodekv = NodeKV {
-                            key: k.as_bytes().to_vec(),
-                            value: v.as_bytes().to_vec(),
-                        };
-                        // println!("vvvv {:?}", &nodekv);
-                        // let kv = NodeValue::KV(nodekv);
-                        smd.metadatas.push(nodekv);
-                    } else {
-                        // println!("{:?}", line);
-                    }
-                }
-            }
-            NodeValue::CodeBlock(ref mut codeblock) => {
-                // println!(
-                //     "{:?} {:?}",
-                //     String::from_utf8_lossy(&codeblock.info),
-                //     String::from_utf8_lossy(&codeblock.literal)
-                // )
-            }
-            _ => (),
-        }
-    });
-
-    // dump_node(root);
+    // dump_node(root, &mut std::io::stdout());
     let mut html = vec![];
     let format_slide_result = format_slide(root, &ComrakOptions::default(), &mut html);
     // let mut jsonDom = SlideHtmlDom::new();
@@ -62,7 +62,7 @@ fn large() {
          </ol>\n"
     );
 
-    dump_node(root);
+    dump_node(root, &mut std::io::stdout());
 
     println!("{:?}", &html_str);
 }
@@ -0,0 +1,590 @@
+//! LaTeX Beamer output: a deck written as a Beamer document, with a frame per slide.
+
+use export::{self, footnote_blocks, Footnotes, LinkTarget};
+use nodes::{AstNode, ListType, NodeCode, NodeValue, TableAlignment};
+use parser::ComrakOptions;
+use slides::{build_deck, slide_anchor, AgendaEntry, AspectRatio, Deck, Slide, SplitReason};
+use std::io::{self, Write};
+use std::ptr;
+
+/// Formats an AST as a LaTeX Beamer document, with one frame per slide of the deck.
+///
+/// Each slide's first heading becomes its `\frametitle`, lists become `itemize`, `enumerate`
+/// and `description` environments, and code blocks become `lstlisting` environments, or
+/// escaped `Verbatim` ones without an info string or with a line which would end the listing
+/// or frame, in `fragile` frames.  Fragments are revealed with
+/// overlay specifications: `\item<2->` for list items, and an `uncoverenv` for other blocks.
+/// Images from files within `options.render.base_dir` become `\includegraphics`, and others,
+/// or those whose paths have characters special to TeX, are replaced by their alt text.
+/// Speaker notes become `\note{}`s, and a slide with more than one region lays them out in
+/// `columns`.
+///
+/// Frames are labelled with the slide's anchor, such as `slide-2`, and links to those anchors
+/// become `\hyperlink`s.  When the deck's metadata has a title, it is set with its author and
+/// date, and shown on a title page before the first slide.
+///
+/// ```
+/// # use comrak::{format_beamer, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Costs & benefits\n\n- 100% *done*\n", &options);
+/// let mut latex = vec![];
+/// format_beamer(root, &options, &mut latex).unwrap();
+/// let latex = String::from_utf8(latex).unwrap();
+///
+/// assert!(latex.contains("\\begin{frame}[label=slide-1]\n\\frametitle{Costs \\& benefits}\n"));
+/// assert!(latex.contains("\\item 100\\% \\emph{done}\n"));
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let mut f = BeamerFormatter {
+        options,
+        output: String::new(),
+        footnotes: Footnotes::new(root),
+        slides: deck.slides.len() + deck.cover.iter().len(),
+    };
+
+    f.preamble(&deck);
+    for (ix, slide) in deck.cover.iter().chain(deck.slides.iter()).enumerate() {
+        f.frame(slide, ix + 1, &deck.agenda);
+    }
+    f.output += "\\end{document}\n";
+    output.write_all(f.output.as_bytes())
+}
+
+/// Escape text for LaTeX.  Brackets are braced so that text at the start of an `\item` is not
+/// taken as its label.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped += "\\textasciitilde{}",
+            '^' => escaped += "\\textasciicircum{}",
+            '<' => escaped += "\\textless{}",
+            '>' => escaped += "\\textgreater{}",
+            '|' => escaped += "\\textbar{}",
+            '[' => escaped += "{[}",
+            ']' => escaped += "{]}",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape a URL for `\href`.
+fn escape_url(url: &str) -> String {
+    url.replace('\\', "%5C")
+        .replace('{', "%7B")
+        .replace('}', "%7D")
+        .replace('%', "\\%")
+        .replace('#', "\\#")
+}
+
+/// Escape a code block's text for a `Verbatim` environment with `\`, `{` and `}` as command
+/// characters, so that no line of it can end the environment.
+fn escape_verbatim(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The `aspectratio` class option for the ratios Beamer supports.
+fn aspect_ratio_option(ratio: AspectRatio) -> Option<&'static str> {
+    match (ratio.width, ratio.height) {
+        (16, 9) => Some("169"),
+        (16, 10) => Some("1610"),
+        (14, 9) => Some("149"),
+        (5, 4) => Some("54"),
+        (4, 3) => Some("43"),
+        (3, 2) => Some("32"),
+        _ => None,
+    }
+}
+
+/// The `listings` name of a language, for the common languages it knows.
+fn listings_language(info: &str) -> Option<&'static str> {
+    Some(match info.to_lowercase().as_str() {
+        "c" => "C",
+        "c++" | "cpp" => "C++",
+        "java" => "Java",
+        "python" | "py" => "Python",
+        "ruby" | "rb" => "Ruby",
+        "sh" | "bash" | "shell" => "bash",
+        "sql" => "SQL",
+        "haskell" | "hs" => "Haskell",
+        "html" => "HTML",
+        "xml" => "XML",
+        "tex" | "latex" => "TeX",
+        "perl" => "Perl",
+        "php" => "PHP",
+        "r" => "R",
+        "matlab" => "Matlab",
+        "fortran" => "Fortran",
+        _ => return None,
+    })
+}
+
+struct BeamerFormatter<'a, 'o> {
+    options: &'o ComrakOptions,
+    output: String,
+
+    /// Footnote definitions by name, written as `\footnote`s where they are referenced.
+    footnotes: Footnotes<'a>,
+
+    slides: usize,
+}
+
+impl<'a, 'o> BeamerFormatter<'a, 'o> {
+    fn cr(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    fn blankline(&mut self) {
+        self.cr();
+        if !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn preamble(&mut self, deck: &Deck) {
+        let metadata = &deck.metadata;
+        match aspect_ratio_option(metadata.aspect_ratio.unwrap_or_default()) {
+            Some(ratio) => {
+                self.output += &format!("\\documentclass[aspectratio={}]{{beamer}}\n", ratio)
+            }
+            None => self.output += "\\documentclass{beamer}\n",
+        }
+        self.output += "\\usepackage[utf8]{inputenc}\n\
+                        \\usepackage[T1]{fontenc}\n\
+                        \\usepackage{amssymb}\n\
+                        \\usepackage{listings}\n\
+                        \\usepackage{fancyvrb}\n\
+                        \\usepackage[normalem]{ulem}\n\
+                        \\lstset{basicstyle=\\ttfamily\\small, breaklines=true}\n";
+        if let Some(ref language) = metadata.language {
+            self.output += &format!("\\hypersetup{{pdflang={}}}\n", escape(language));
+        }
+        if let Some(ref title) = metadata.title {
+            self.output += &format!("\\title{{{}}}\n", escape(title));
+            if let Some(ref author) = metadata.author {
+                self.output += &format!("\\author{{{}}}\n", escape(author));
+            }
+            if let Some(ref date) = metadata.date {
+                self.output += &format!("\\date{{{}}}\n", escape(date));
+            }
+        }
+        self.output += "\n\\begin{document}\n\n";
+        if metadata.title.is_some() {
+            self.output += "\\begin{frame}[plain]\n\\titlepage\n\\end{frame}\n\n";
+        }
+    }
+
+    fn frame(&mut self, slide: &Slide<'a>, number: usize, agenda: &[AgendaEntry]) {
+        let title_node = slide
+            .nodes
+            .iter()
+            .cloned()
+            .find(|n| matches!(n.data.borrow().value, NodeValue::Heading(..)));
+        let fragile = slide.regions.iter().any(|region| {
+            region.nodes.iter().any(|node| {
+                node.descendants()
+                    .any(|n| matches!(n.data.borrow().value, NodeValue::CodeBlock(..)))
+            })
+        });
+
+        self.output += &format!(
+            "\\begin{{frame}}[{}label={}]\n",
+            if fragile { "fragile, " } else { "" },
+            slide_anchor(number)
+        );
+        match title_node {
+            Some(heading) => {
+                self.output += "\\frametitle{";
+                for child in heading.children() {
+                    self.format(child);
+                }
+                self.output += "}\n";
+            }
+            None if slide.split == SplitReason::Generated => {
+                self.output += &format!("\\frametitle{{{}}}\n", escape(&slide.title));
+            }
+            None => (),
+        }
+
+        let columns = slide.regions.len() > 1;
+        if columns {
+            self.output += "\\begin{columns}[T]\n";
+        }
+        let width = 0.96 / slide.regions.len() as f64;
+        for (ix, region) in slide.regions.iter().enumerate() {
+            if columns {
+                self.output += &format!("\\begin{{column}}{{{:.2}\\textwidth}}\n", width);
+            }
+            for &node in &region.nodes {
+                if !title_node.is_some_and(|t| ptr::eq(t, node)) {
+                    self.format(node);
+                }
+            }
+            if slide.agenda && ix == slide.regions.len() - 1 {
+                self.agenda(agenda, slide.section);
+            }
+            if columns {
+                self.cr();
+                self.output += "\\end{column}\n";
+            }
+        }
+        if columns {
+            self.output += "\\end{columns}\n";
+        }
+
+        for note in &slide.notes {
+            let paragraphs = note
+                .split("\n\n")
+                .map(|p| escape(p.trim()))
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<_>>();
+            self.cr();
+            self.output += &format!("\\note{{{}}}\n", paragraphs.join("\n\\par "));
+        }
+        self.cr();
+        self.output += "\\end{frame}\n\n";
+    }
+
+    /// The deck's agenda as nested `itemize` lists, with the current section in bold.
+    fn agenda(&mut self, agenda: &[AgendaEntry], current: Option<usize>) {
+        let base = agenda.iter().map(|e| e.level).min().unwrap_or(1);
+        let mut depth = 0;
+        self.cr();
+        for (ix, entry) in agenda.iter().enumerate() {
+            let level = (entry.level - base + 1).min(depth + 1);
+            while depth < level {
+                self.output += "\\begin{itemize}\n";
+                depth += 1;
+            }
+            while depth > level {
+                self.output += "\\end{itemize}\n";
+                depth -= 1;
+            }
+            let link = format!(
+                "\\hyperlink{{{}}}{{{}}}",
+                entry.anchor,
+                escape(&entry.title)
+            );
+            if current == Some(ix) {
+                self.output += &format!("\\item \\textbf{{{}}}\n", link);
+            } else {
+                self.output += &format!("\\item {}\n", link);
+            }
+        }
+        for _ in 0..depth {
+            self.output += "\\end{itemize}\n";
+        }
+    }
+
+    fn format(&mut self, node: &'a AstNode<'a>) {
+        export::walk(node, |node, entering| self.format_node(node, entering));
+    }
+
+    /// The overlay specification revealing a fragment: a slide's first build step is its
+    /// second overlay.
+    fn overlay(&self, node: &'a AstNode<'a>) -> Option<String> {
+        node.data
+            .borrow()
+            .fragment
+            .map(|index| format!("<{}->", index + 2))
+    }
+
+    /// Whether a block is revealed by wrapping it in an `uncoverenv`.  List items take their
+    /// overlay specification directly instead.
+    fn uncovered(&self, node: &'a AstNode<'a>) -> Option<String> {
+        match node.data.borrow().value {
+            NodeValue::Item(..) => return None,
+            ref value if !value.block() => return None,
+            _ => (),
+        }
+        self.overlay(node)
+    }
+
+    /// The `\href` or `\hyperlink` opening a link to `url`, if it can be followed.
+    fn link(&self, url: &[u8]) -> Option<String> {
+        Some(match export::link_target(url, self.options, self.slides)? {
+            LinkTarget::Slide(anchor) => format!("\\hyperlink{{{}}}{{", anchor),
+            LinkTarget::Url(url) => format!("\\href{{{}}}{{", escape_url(&url)),
+        })
+    }
+
+    /// A footnote's paragraphs, as the argument of `\footnote`.  The definition is set aside
+    /// while it is written, so that a footnote referring to itself is not expanded again.
+    fn footnote(&mut self, name: &[u8]) {
+        let definition = match self.footnotes.take(name) {
+            Some(definition) => definition,
+            None => return,
+        };
+        self.output += "\\footnote{";
+        for (ix, nodes) in footnote_blocks(definition).into_iter().enumerate() {
+            if ix > 0 {
+                self.output += "\\par ";
+            }
+            for node in nodes {
+                self.format(node);
+            }
+        }
+        self.output = self.output.trim_end().to_string();
+        self.output += "}";
+        self.footnotes.restore(name, definition);
+    }
+
+    fn format_node(&mut self, node: &'a AstNode<'a>, entering: bool) -> bool {
+        if let Some(overlay) = self.uncovered(node) {
+            if entering {
+                self.cr();
+                self.output += &format!("\\begin{{uncoverenv}}{}\n", overlay);
+            }
+        }
+        let descend = self.format_value(node, entering);
+        if self.uncovered(node).is_some() && !entering {
+            self.cr();
+            self.output += "\\end{uncoverenv}\n";
+        }
+        descend
+    }
+
+    fn format_value(&mut self, node: &'a AstNode<'a>, entering: bool) -> bool {
+        match node.data.borrow().value {
+            NodeValue::Document => (),
+            NodeValue::FrontMatter(_)
+            | NodeValue::SlideMetaDataBlock(_)
+            | NodeValue::KV(_)
+            | NodeValue::HtmlBlock(_)
+            | NodeValue::HtmlInline(_)
+            | NodeValue::Effect(_)
+            | NodeValue::FootnoteDefinition(_) => return false,
+            NodeValue::BlockQuote => {
+                self.cr();
+                if entering {
+                    self.output += "\\begin{quote}\n";
+                } else {
+                    self.output += "\\end{quote}\n";
+                }
+            }
+            NodeValue::List(ref nl) => {
+                let environment = match nl.list_type {
+                    ListType::Bullet => "itemize",
+                    ListType::Ordered => "enumerate",
+                };
+                self.cr();
+                if entering {
+                    self.output += &format!("\\begin{{{}}}\n", environment);
+                    if nl.list_type == ListType::Ordered && nl.start != 1 {
+                        let depth = node
+                            .ancestors()
+                            .filter(|n| match n.data.borrow().value {
+                                NodeValue::List(ref nl) => nl.list_type == ListType::Ordered,
+                                _ => false,
+                            })
+                            .count();
+                        let counter = ["i", "ii", "iii", "iv"][(depth - 1).min(3)];
+                        self.output += &format!(
+                            "\\setcounter{{enum{}}}{{{}}}\n",
+                            counter,
+                            nl.start.saturating_sub(1)
+                        );
+                    }
+                } else {
+                    self.output += &format!("\\end{{{}}}\n", environment);
+                }
+            }
+            NodeValue::Item(..) => {
+                if entering {
+                    self.cr();
+                    self.output += "\\item";
+                    if let Some(overlay) = self.overlay(node) {
+                        self.output += &overlay;
+                    }
+                    self.output.push(' ');
+                }
+            }
+            NodeValue::DescriptionList => {
+                self.cr();
+                if entering {
+                    self.output += "\\begin{description}\n";
+                } else {
+                    self.output += "\\end{description}\n";
+                }
+            }
+            NodeValue::DescriptionItem(..) => (),
+            NodeValue::DescriptionTerm => {
+                if entering {
+                    self.cr();
+                    self.output += "\\item[";
+                } else {
+                    self.output = self.output.trim_end().to_string();
+                    self.output += "] ";
+                }
+            }
+            NodeValue::DescriptionDetails => (),
+            NodeValue::Heading(..) => {
+                if entering {
+                    self.blankline();
+                    self.output += "\\textbf{";
+                } else {
+                    self.output += "}\n\n";
+                }
+            }
+            NodeValue::CodeBlock(ref ncb) => {
+                if entering {
+                    let info = String::from_utf8_lossy(&ncb.info);
+                    let info = info.split_whitespace().next().unwrap_or("");
+                    let literal = String::from_utf8_lossy(&ncb.literal);
+                    // Beamer ends a fragile frame at any line starting with `\end{frame}`, so
+                    // such code is escaped in a `Verbatim` like code which would end a listing.
+                    let ends_frame = literal
+                        .lines()
+                        .any(|line| line.trim_start().starts_with("\\end{frame}"));
+                    let listing =
+                        !info.is_empty() && !ends_frame && !literal.contains("\\end{lstlisting}");
+                    let environment = if listing { "lstlisting" } else { "Verbatim" };
+                    self.cr();
+                    self.output += &format!("\\begin{{{}}}", environment);
+                    if listing {
+                        if let Some(language) = listings_language(info) {
+                            self.output += &format!("[language={}]", language);
+                        }
+                        self.output.push('\n');
+                        self.output += &literal;
+                    } else {
+                        self.output += "[commandchars=\\\\\\{\\}]\n";
+                        self.output += &escape_verbatim(&literal);
+                    }
+                    self.cr();
+                    self.output += &format!("\\end{{{}}}\n", environment);
+                }
+            }
+            NodeValue::ThematicBreak => {
+                if entering {
+                    self.blankline();
+                    self.output += "\\noindent\\rule{\\linewidth}{0.4pt}\n\n";
+                }
+            }
+            NodeValue::Paragraph => {
+                if !entering {
+                    if export::tight(node) {
+                        self.cr();
+                    } else {
+                        self.blankline();
+                    }
+                }
+            }
+            NodeValue::Text(ref literal) => {
+                if entering {
+                    self.output += &escape(&String::from_utf8_lossy(literal));
+                }
+            }
+            NodeValue::LineBreak => {
+                if entering {
+                    self.output += "\\\\\n";
+                }
+            }
+            NodeValue::SoftBreak => {
+                if entering {
+                    self.output.push('\n');
+                }
+            }
+            NodeValue::Code(NodeCode { ref literal, .. }) => {
+                if entering {
+                    self.output +=
+                        &format!("\\texttt{{{}}}", escape(&String::from_utf8_lossy(literal)));
+                }
+            }
+            NodeValue::Strong => self.output += if entering { "\\textbf{" } else { "}" },
+            NodeValue::Emph => self.output += if entering { "\\emph{" } else { "}" },
+            NodeValue::Strikethrough => self.output += if entering { "\\sout{" } else { "}" },
+            NodeValue::Superscript => {
+                self.output += if entering { "\\textsuperscript{" } else { "}" }
+            }
+            NodeValue::Link(ref nl) => match self.link(&nl.url) {
+                Some(ref command) if entering => self.output += command,
+                Some(_) => self.output += "}",
+                None => (),
+            },
+            NodeValue::Image(ref nl) => {
+                let path = export::image_path(&nl.url, self.options)
+                    .filter(|path| !path.contains(|c| "{}%\\#".contains(c)));
+                if let Some(path) = path {
+                    if entering {
+                        self.output += &format!(
+                            "\\includegraphics[width=\\linewidth, height=0.7\\textheight, \
+                             keepaspectratio]{{{}}}",
+                            path
+                        );
+                    }
+                    return false;
+                }
+            }
+            NodeValue::Table(ref alignments) => {
+                self.cr();
+                if entering {
+                    let columns = alignments
+                        .iter()
+                        .map(|alignment| match *alignment {
+                            TableAlignment::Center => 'c',
+                            TableAlignment::Right => 'r',
+                            TableAlignment::None | TableAlignment::Left => 'l',
+                        })
+                        .collect::<String>();
+                    self.output += &format!("\\begin{{tabular}}{{{}}}\n", columns);
+                } else {
+                    self.output += "\\end{tabular}\n\n";
+                }
+            }
+            NodeValue::TableRow(header) => {
+                if !entering {
+                    self.output += " \\\\\n";
+                    if header {
+                        self.output += "\\hline\n";
+                    }
+                }
+            }
+            NodeValue::TableCell => {
+                if entering && node.previous_sibling().is_some() {
+                    self.output += " & ";
+                }
+            }
+            NodeValue::FootnoteReference(ref name) => {
+                if entering {
+                    self.footnote(name);
+                }
+            }
+            NodeValue::TaskItem(checked) => {
+                if entering {
+                    self.output += if checked {
+                        "$\\boxtimes$ "
+                    } else {
+                        "$\\square$ "
+                    };
+                }
+            }
+        }
+        true
+    }
+}
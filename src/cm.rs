@@ -5,14 +5,12 @@ use nodes::{
     AstNode, EffectAttr, ListDelimType, ListType, NodeCodeBlock, NodeHeading, NodeHtmlBlock,
     NodeLink, NodeSlideMetaDataBlock, NodeValue,
 };
-use parser::ComrakOptions;
+use parser::{effect, ComrakOptions};
 use scanners;
 use std;
 use std::cmp::max;
 use std::io::{self, Write};
 
-use crate::nodes::NodeKV;
-
 /// Formats an AST as CommonMark, modified by the given options.
 pub fn format_document<'a>(
     root: &'a AstNode<'a>,
@@ -309,13 +307,11 @@ impl<'a, 'o> CommonMarkFormatter<'a, 'o> {
             NodeValue::DescriptionDetails => self.format_description_details(entering),
             NodeValue::Heading(ref nch) => self.format_heading(nch, entering),
             NodeValue::SlideMetaDataBlock(ref smd) => {
-                self.format_slide_meta_data_block(node, smd, entering)
-            }
-            NodeValue::Effect(ref effect) => {
-                // self.format_slide_meta_data_block(node, smd, entering)
-                println!("format_node for Effect");
+                // The block's `KV` children are written as part of its literal.
+                self.format_slide_meta_data_block(smd, entering);
+                return false;
             }
-            NodeValue::KV(ref kv) => self.format_kv(node, kv, entering),
+            NodeValue::KV(..) => (),
             NodeValue::CodeBlock(ref ncb) => self.format_code_block(node, ncb, entering),
             NodeValue::HtmlBlock(ref nhb) => self.format_html_block(nhb, entering),
             NodeValue::ThematicBreak => self.format_thematic_break(entering),
@@ -331,8 +327,8 @@ impl<'a, 'o> CommonMarkFormatter<'a, 'o> {
             NodeValue::Strikethrough => self.format_strikethrough(),
             NodeValue::Superscript => self.format_superscript(),
             NodeValue::Link(ref nl) => return self.format_link(node, nl, entering),
-            NodeValue::Image(ref nl) => self.format_image(nl, allow_wrap, entering),
-            NodeValue::Effect(ref _effect_attr) => self.format_effect(),
+            NodeValue::Image(ref nl) => self.format_image(node, nl, allow_wrap, entering),
+            NodeValue::Effect(ref effect) => self.format_effect(effect, entering),
             NodeValue::Table(..) => self.format_table(entering),
             NodeValue::TableRow(..) => self.format_table_row(entering),
             NodeValue::TableCell => self.format_table_cell(node, entering),
@@ -446,15 +442,20 @@ impl<'a, 'o> CommonMarkFormatter<'a, 'o> {
         }
     }
 
-    fn format_kv(&mut self, node: &'a AstNode<'a>, kv: &NodeKV, entering: bool) {}
-
-    fn format_slide_meta_data_block(
-        &mut self,
-        node: &'a AstNode<'a>,
-        smd: &NodeSlideMetaDataBlock,
-        entering: bool,
-    ) {
+    fn format_slide_meta_data_block(&mut self, smd: &NodeSlideMetaDataBlock, entering: bool) {
+        if entering {
+            // A metadata block opens straight under the heading it follows.
+            self.need_cr = 1;
+            let fence = vec![smd.fence_char; max(3, smd.fence_length)];
+            self.write_all(&fence).unwrap();
+            self.cr();
+            self.write_all(&smd.literal).unwrap();
+            self.cr();
+            self.write_all(&fence).unwrap();
+            self.blankline();
+        }
     }
+
     fn format_code_block(&mut self, node: &'a AstNode<'a>, ncb: &NodeCodeBlock, entering: bool) {
         if entering {
             let first_in_list_item = node.previous_sibling().is_none()
@@ -647,14 +648,32 @@ impl<'a, 'o> CommonMarkFormatter<'a, 'o> {
         return true;
     }
 
-    fn format_effect(&mut self) {
-        println!("format_effect");
+    fn format_effect(&mut self, effect: &EffectAttr, entering: bool) {
+        if entering {
+            write!(self, "::effect[").unwrap();
+            self.write_all(&effect.literal).unwrap();
+            if effect.error.as_deref() != Some(effect::UNTERMINATED) {
+                write!(self, "]").unwrap();
+            }
+        }
     }
 
-    fn format_image(&mut self, nl: &NodeLink, allow_wrap: bool, entering: bool) {
+    fn format_image(
+        &mut self,
+        node: &'a AstNode<'a>,
+        nl: &NodeLink,
+        allow_wrap: bool,
+        entering: bool,
+    ) {
         if entering {
             write!(self, "![").unwrap();
         } else {
+            if !nl.attributes.is_empty() {
+                if node.first_child().is_some() {
+                    write!(self, "|").unwrap();
+                }
+                write!(self, "{}", nl.attributes).unwrap();
+            }
             write!(self, "](").unwrap();
             self.output(&nl.url, false, Escaping::URL);
             if !nl.title.is_empty() {
@@ -0,0 +1,194 @@
+//! Problems found in a document while parsing it.
+//!
+//! The parser recovers from everything it reports here: a malformed metadata line is skipped,
+//! an unclosed metadata fence runs to the end of its container, and so on.  Diagnostics say
+//! where that happened and why, for editors and command-line tools to show to the author.
+
+use serde::Serialize;
+use std::fmt;
+use std::slice;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// The document parsed as written, but probably not as intended.
+    Warning,
+
+    /// Part of the document could not be parsed as written.
+    Error,
+}
+
+impl Severity {
+    /// The severity's name, such as `warning`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What kind of problem a diagnostic reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    /// A `---` metadata fence with no closing fence.
+    UnclosedMetadataFence,
+
+    /// A metadata line whose key is empty, such as `: value`.
+    EmptyMetadataKey,
+
+    /// A metadata line which is not a `key: value` entry, or is indented under no entry.
+    InvalidMetadataLine,
+
+    /// A metadata key given more than once in the same block; the last value is used.
+    DuplicateMetadataKey,
+
+    /// An `::effect[...]` directive whose arguments could not be parsed.
+    MalformedEffect,
+
+    /// An `::effect[...]` directive naming an effect which is not known.
+    UnknownEffect,
+}
+
+impl DiagnosticCode {
+    /// The code's name, such as `unclosed-metadata-fence`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::UnclosedMetadataFence => "unclosed-metadata-fence",
+            DiagnosticCode::EmptyMetadataKey => "empty-metadata-key",
+            DiagnosticCode::InvalidMetadataLine => "invalid-metadata-line",
+            DiagnosticCode::DuplicateMetadataKey => "duplicate-metadata-key",
+            DiagnosticCode::MalformedEffect => "malformed-effect",
+            DiagnosticCode::UnknownEffect => "unknown-effect",
+        }
+    }
+
+    /// The severity of diagnostics with this code.
+    pub fn severity(self) -> Severity {
+        match self {
+            DiagnosticCode::UnclosedMetadataFence | DiagnosticCode::MalformedEffect => {
+                Severity::Error
+            }
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single problem, at a position in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// What kind of problem it is.
+    pub code: DiagnosticCode,
+
+    /// A description of the problem, for people.
+    pub message: String,
+
+    /// The line of the problem, counting from 1.
+    pub line: u32,
+
+    /// The column of the problem, in bytes counting from 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats as `3:1: error: message [code]`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} [{}]",
+            self.line, self.column, self.severity, self.message, self.code
+        )
+    }
+}
+
+/// The diagnostics of a document, in source order.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Create an empty collection of diagnostics.
+    pub fn new() -> Self {
+        Diagnostics::default()
+    }
+
+    /// Add a diagnostic with the code's severity.
+    pub fn push(&mut self, code: DiagnosticCode, message: String, line: u32, column: usize) {
+        self.diagnostics.push(Diagnostic {
+            severity: code.severity(),
+            code,
+            message,
+            line,
+            column,
+        });
+    }
+
+    /// Add all of `other`'s diagnostics.
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    /// Put the diagnostics in source order.  Diagnostics at the same position keep their
+    /// relative order.
+    pub fn sort(&mut self) {
+        self.diagnostics.sort_by_key(|d| (d.line, d.column));
+    }
+
+    /// Iterate over the diagnostics.
+    pub fn iter(&self) -> slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// Iterate over the diagnostics with `Severity::Error`.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.iter().filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Iterate over the diagnostics with `Severity::Warning`.
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.iter().filter(|d| d.severity == Severity::Warning)
+    }
+
+    /// Whether any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// The number of diagnostics.
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Whether there are no diagnostics.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl<'d> IntoIterator for &'d Diagnostics {
+    type Item = &'d Diagnostic;
+    type IntoIter = slice::Iter<'d, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,132 @@
+//! Shared support for the exporters that write a deck as the markup of another typesetting
+//! system, such as Beamer and Typst: traversal, links, local images and footnotes.
+
+use nodes::{AstNode, NodeValue};
+use package::image_file;
+use parser::ComrakOptions;
+use scanners;
+use std::collections::HashMap;
+
+/// Traverse the tree under `node` iteratively using a work stack, as the HTML formatter does,
+/// calling `f` on entering and on leaving each node.  A node's children are skipped when `f`
+/// returns `false` on entering it.
+pub fn walk<'a, F>(node: &'a AstNode<'a>, mut f: F)
+where
+    F: FnMut(&'a AstNode<'a>, bool) -> bool,
+{
+    enum Phase {
+        Pre,
+        Post,
+    }
+    let mut stack = vec![(node, Phase::Pre)];
+
+    while let Some((node, phase)) = stack.pop() {
+        match phase {
+            Phase::Pre => {
+                stack.push((node, Phase::Post));
+                if f(node, true) {
+                    for ch in node.reverse_children() {
+                        stack.push((ch, Phase::Pre));
+                    }
+                }
+            }
+            Phase::Post => {
+                f(node, false);
+            }
+        }
+    }
+}
+
+/// Whether a paragraph is in an item of a tight list, and so ends its line rather than a
+/// paragraph.
+pub fn tight<'a>(node: &'a AstNode<'a>) -> bool {
+    let list = node.parent().and_then(|item| item.parent());
+    match list.map(|list| list.data.borrow().value.clone()) {
+        Some(NodeValue::List(nl)) => nl.tight,
+        _ => false,
+    }
+}
+
+/// Where a link goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// A slide of the deck, by its anchor, such as `slide-2`.
+    Slide(String),
+
+    /// Anywhere else.
+    Url(String),
+}
+
+/// Where a link to `url` goes, if it can be followed in a deck of `slides` slides.  Unsafe
+/// URLs are dropped unless `options.render.unsafe_` is set, as are links to anchors other than
+/// slides.
+pub fn link_target(url: &[u8], options: &ComrakOptions, slides: usize) -> Option<LinkTarget> {
+    if !options.render.unsafe_ && scanners::dangerous_url(url).is_some() {
+        return None;
+    }
+    let url = String::from_utf8_lossy(url);
+    match url.strip_prefix('#') {
+        Some(anchor) => {
+            let number = anchor.strip_prefix("slide-")?.parse::<usize>().ok()?;
+            if number >= 1 && number <= slides {
+                Some(LinkTarget::Slide(anchor.to_string()))
+            } else {
+                None
+            }
+        }
+        None => Some(LinkTarget::Url(url.into_owned())),
+    }
+}
+
+/// The path an image should be read from, with `/` separators, if its URL refers to a file
+/// within `options.render.base_dir`, as found by `package::image_file`.
+pub fn image_path(url: &[u8], options: &ComrakOptions) -> Option<String> {
+    let path = image_file(
+        &String::from_utf8_lossy(url),
+        options.render.base_dir.as_deref(),
+    )?;
+    Some(path.to_string_lossy().replace('\\', "/"))
+}
+
+/// A document's footnote definitions by name, for exporters which write a footnote's content
+/// where it is referenced.
+#[derive(Debug)]
+pub struct Footnotes<'a> {
+    definitions: HashMap<Vec<u8>, &'a AstNode<'a>>,
+}
+
+impl<'a> Footnotes<'a> {
+    /// Collect the footnote definitions under `root`.
+    pub fn new(root: &'a AstNode<'a>) -> Self {
+        let mut definitions = HashMap::new();
+        for node in root.descendants() {
+            if let NodeValue::FootnoteDefinition(ref name) = node.data.borrow().value {
+                definitions.insert(name.clone(), node);
+            }
+        }
+        Footnotes { definitions }
+    }
+
+    /// Set aside the definition of a footnote while its content is written, so that a footnote
+    /// referring to itself is not expanded again.
+    pub fn take(&mut self, name: &[u8]) -> Option<&'a AstNode<'a>> {
+        self.definitions.remove(name)
+    }
+
+    /// Put back a definition set aside by `take`.
+    pub fn restore(&mut self, name: &[u8], definition: &'a AstNode<'a>) {
+        self.definitions.insert(name.to_vec(), definition);
+    }
+}
+
+/// The nodes to write for each block of a footnote's definition: a paragraph's inlines, or
+/// else the block itself.
+pub fn footnote_blocks<'a>(definition: &'a AstNode<'a>) -> Vec<Vec<&'a AstNode<'a>>> {
+    definition
+        .children()
+        .map(|block| match block.data.borrow().value {
+            NodeValue::Paragraph => block.children().collect(),
+            _ => vec![block],
+        })
+        .collect()
+}
@@ -1,8 +1,9 @@
 use ctype::isspace;
-use nodes::{AstNode, ListType, NodeCode, NodeValue, TableAlignment};
+use nodes::{AstNode, EffectAttr, ImageAttributes, ListType, NodeCode, NodeValue, TableAlignment};
 use parser::ComrakOptions;
 use regex::Regex;
 use scanners;
+use slides::anchor_prefix;
 use std::borrow::Cow;
 use std::cell::Cell;
 use std::collections::HashSet;
@@ -15,8 +16,6 @@ pub fn format_document<'a>(
     options: &ComrakOptions,
     output: &mut dyn Write,
 ) -> io::Result<()> {
-    println!("format_document");
-
     let mut writer = WriteWithLast {
         output,
         last_was_lf: Cell::new(true),
@@ -29,6 +28,33 @@ pub fn format_document<'a>(
     Ok(())
 }
 
+/// Formats a run of sibling nodes, such as the blocks making up one slide, as HTML.  When
+/// `links` is given, each link is followed by its number as a `<sup class="link-ref">`, and its
+/// URL appended to `links`.
+pub fn format_nodes<'a>(
+    nodes: &[&'a AstNode<'a>],
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+    links: Option<&mut Vec<String>>,
+) -> io::Result<()> {
+    let mut writer = WriteWithLast {
+        output,
+        last_was_lf: Cell::new(true),
+    };
+    let mut f = HtmlFormatter::new(options, &mut writer);
+    f.links = links.as_ref().map(|links| links.to_vec());
+    for node in nodes {
+        f.format(node, false)?;
+    }
+    if f.footnote_ix > 0 {
+        f.output.write_all(b"</ol>\n</section>\n")?;
+    }
+    if let (Some(links), Some(noted)) = (links, f.links.take()) {
+        *links = noted;
+    }
+    Ok(())
+}
+
 pub struct WriteWithLast<'w> {
     output: &'w mut dyn Write,
     pub last_was_lf: Cell<bool>,
@@ -125,6 +151,7 @@ struct HtmlFormatter<'o> {
     footnote_ix: u32,
     written_footnote_ix: u32,
     last_is_effect: bool,
+    links: Option<Vec<String>>,
 }
 
 #[rustfmt::skip]
@@ -236,6 +263,51 @@ fn dangerous_url(input: &[u8]) -> bool {
     scanners::dangerous_url(input).is_some()
 }
 
+/// The HTML attributes for an image's hints, each preceded by a space.  The values are
+/// validated when parsed, so need no escaping.
+pub(crate) fn image_attributes(attributes: &ImageAttributes) -> String {
+    let mut s = String::new();
+    if let Some(width) = attributes.width {
+        s += &format!(" width=\"{}\"", width);
+    }
+    if let Some(height) = attributes.height {
+        s += &format!(" height=\"{}\"", height);
+    }
+    if let Some(layout) = attributes.layout {
+        s += &format!(" class=\"layout-{}\"", layout.as_str());
+    }
+    if !attributes.filters.is_empty() {
+        let filters: Vec<String> = attributes.filters.iter().map(|f| f.to_string()).collect();
+        s += &format!(" style=\"filter: {}\"", filters.join(" "));
+    }
+    s
+}
+
+/// The `<effect>` tag of an effect directive, followed by a newline.  Parameter keys are
+/// validated when parsed, so need no escaping.
+pub(crate) fn effect_tag(effect: &EffectAttr) -> String {
+    let mut s = format!("<effect name=\"{}\"", escape_text(&effect.name));
+    if let Some(ref target) = effect.target {
+        s += &format!(" target=\"{}\"", escape_text(target));
+    }
+    for (i, (key, value)) in effect.params.iter().enumerate() {
+        if key.is_empty() {
+            s += &format!(" data-arg-{}=\"{}\"", i, escape_text(value));
+        } else {
+            s += &format!(" data-{}=\"{}\"", key, escape_text(value));
+        }
+    }
+    s + ">\n"
+}
+
+/// Escape text for use in HTML content or a quoted attribute value.
+pub(crate) fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 impl<'o> HtmlFormatter<'o> {
     fn new(options: &'o ComrakOptions, output: &'o mut WriteWithLast<'o>) -> Self {
         HtmlFormatter {
@@ -245,6 +317,7 @@ impl<'o> HtmlFormatter<'o> {
             footnote_ix: 0,
             written_footnote_ix: 0,
             last_is_effect: false,
+            links: None,
         }
     }
 
@@ -371,6 +444,16 @@ impl<'o> HtmlFormatter<'o> {
         Ok(())
     }
 
+    fn fragment<'a>(&mut self, node: &'a AstNode<'a>) -> io::Result<()> {
+        if let Some(index) = node.data.borrow().fragment {
+            if self.options.render.reveal {
+                self.output.write_all(b" class=\"fragment\"")?;
+            }
+            write!(self.output, " data-fragment-index=\"{}\"", index)?;
+        }
+        Ok(())
+    }
+
     fn collect_text<'a>(&self, node: &'a AstNode<'a>, output: &mut Vec<u8>) {
         match node.data.borrow().value {
             NodeValue::Text(ref literal) | NodeValue::Code(NodeCode { ref literal, .. }) => {
@@ -392,7 +475,9 @@ impl<'o> HtmlFormatter<'o> {
             NodeValue::BlockQuote => {
                 if entering {
                     self.cr()?;
-                    self.output.write_all(b"<blockquote>\n")?;
+                    self.output.write_all(b"<blockquote")?;
+                    self.fragment(node)?;
+                    self.output.write_all(b">\n")?;
                 } else {
                     self.cr()?;
                     self.output.write_all(b"</blockquote>\n")?;
@@ -402,12 +487,14 @@ impl<'o> HtmlFormatter<'o> {
                 if entering {
                     self.cr()?;
                     if nl.list_type == ListType::Bullet {
-                        self.output.write_all(b"<ul>\n")?;
+                        self.output.write_all(b"<ul")?;
                     } else if nl.start == 1 {
-                        self.output.write_all(b"<ol>\n")?;
+                        self.output.write_all(b"<ol")?;
                     } else {
-                        writeln!(self.output, "<ol start=\"{}\">", nl.start)?;
+                        write!(self.output, "<ol start=\"{}\"", nl.start)?;
                     }
+                    self.fragment(node)?;
+                    self.output.write_all(b">\n")?;
                 } else if nl.list_type == ListType::Bullet {
                     self.output.write_all(b"</ul>\n")?;
                 } else {
@@ -417,7 +504,9 @@ impl<'o> HtmlFormatter<'o> {
             NodeValue::Item(..) => {
                 if entering {
                     self.cr()?;
-                    self.output.write_all(b"<li>")?;
+                    self.output.write_all(b"<li")?;
+                    self.fragment(node)?;
+                    self.output.write_all(b">")?;
                 } else {
                     self.output.write_all(b"</li>\n")?;
                 }
@@ -425,7 +514,9 @@ impl<'o> HtmlFormatter<'o> {
             NodeValue::DescriptionList => {
                 if entering {
                     self.cr()?;
-                    self.output.write_all(b"<dl>")?;
+                    self.output.write_all(b"<dl")?;
+                    self.fragment(node)?;
+                    self.output.write_all(b">")?;
                 } else {
                     self.output.write_all(b"</dl>\n")?;
                 }
@@ -453,7 +544,9 @@ impl<'o> HtmlFormatter<'o> {
                     }
 
                     self.cr()?;
-                    write!(self.output, "<h{}>", nch.level)?;
+                    write!(self.output, "<h{}", nch.level)?;
+                    self.fragment(node)?;
+                    self.output.write_all(b">")?;
 
                     if let Some(ref prefix) = self.options.extension.header_ids {
                         let mut text_content = Vec::with_capacity(20);
@@ -475,23 +568,21 @@ impl<'o> HtmlFormatter<'o> {
             }
             NodeValue::SlideMetaDataBlock(ref smd) => {}
             NodeValue::Effect(ref effect) => {
-                if entering {
+                if entering && effect.error.is_none() {
                     self.last_is_effect = true;
                     self.cr()?;
-                    let effcontent = String::from_utf8_lossy(&effect.literal);
-                    writeln!(self.output, "<effect {}>", effcontent)?;
-                    // self.output.write_all(b"\n</effect>\n");
-                } else {
-                    // self.output.write_all(b"</effect>\n")?;
+                    self.output.write_all(effect_tag(effect).as_bytes())?;
                 }
             }
             NodeValue::KV(ref _kv) => {}
             NodeValue::CodeBlock(ref ncb) => {
                 if entering {
                     self.cr()?;
+                    self.output.write_all(b"<pre")?;
+                    self.fragment(node)?;
 
                     if ncb.info.is_empty() {
-                        self.output.write_all(b"<pre><code>")?;
+                        self.output.write_all(b"><code>")?;
                     } else {
                         let mut first_tag = 0;
                         while first_tag < ncb.info.len() && !isspace(ncb.info[first_tag]) {
@@ -499,11 +590,11 @@ impl<'o> HtmlFormatter<'o> {
                         }
 
                         if self.options.render.github_pre_lang {
-                            self.output.write_all(b"<pre lang=\"")?;
+                            self.output.write_all(b" lang=\"")?;
                             self.escape(&ncb.info[..first_tag])?;
                             self.output.write_all(b"\"><code>")?;
                         } else {
-                            self.output.write_all(b"<pre><code class=\"language-")?;
+                            self.output.write_all(b"><code class=\"language-")?;
                             self.escape(&ncb.info[..first_tag])?;
                             self.output.write_all(b"\">")?;
                         }
@@ -530,7 +621,9 @@ impl<'o> HtmlFormatter<'o> {
             NodeValue::ThematicBreak => {
                 if entering {
                     self.cr()?;
-                    self.output.write_all(b"<hr />\n")?;
+                    self.output.write_all(b"<hr")?;
+                    self.fragment(node)?;
+                    self.output.write_all(b" />\n")?;
                 }
             }
             NodeValue::Paragraph => {
@@ -557,7 +650,9 @@ impl<'o> HtmlFormatter<'o> {
                 if !tight {
                     if entering {
                         self.cr()?;
-                        self.output.write_all(b"<p>")?;
+                        self.output.write_all(b"<p")?;
+                        self.fragment(node)?;
+                        self.output.write_all(b">")?;
                     } else {
                         if matches!(
                             node.parent().unwrap().data.borrow().value,
@@ -643,7 +738,14 @@ impl<'o> HtmlFormatter<'o> {
                 if entering {
                     self.output.write_all(b"<a href=\"")?;
                     if self.options.render.unsafe_ || !dangerous_url(&nl.url) {
-                        self.escape_href(&nl.url)?;
+                        match nl.url.strip_prefix(b"#") {
+                            Some(anchor) if anchor.starts_with(b"slide-") => {
+                                self.output
+                                    .write_all(anchor_prefix(self.options).as_bytes())?;
+                                self.escape_href(anchor)?;
+                            }
+                            _ => self.escape_href(&nl.url)?,
+                        }
                     }
                     if !nl.title.is_empty() {
                         self.output.write_all(b"\" title=\"")?;
@@ -652,6 +754,13 @@ impl<'o> HtmlFormatter<'o> {
                     self.output.write_all(b"\">")?;
                 } else {
                     self.output.write_all(b"</a>")?;
+                    let safe = self.options.render.unsafe_ || !dangerous_url(&nl.url);
+                    if let Some(ref mut links) = self.links {
+                        if safe && !nl.url.is_empty() && !nl.url.starts_with(b"#") {
+                            links.push(String::from_utf8_lossy(&nl.url).into_owned());
+                            write!(self.output, "<sup class=\"link-ref\">{}</sup>", links.len())?;
+                        }
+                    }
                 }
             }
             NodeValue::Image(ref nl) => {
@@ -667,13 +776,18 @@ impl<'o> HtmlFormatter<'o> {
                         self.output.write_all(b"\" title=\"")?;
                         self.escape(&nl.title)?;
                     }
-                    self.output.write_all(b"\" />")?;
+                    self.output.write_all(b"\"")?;
+                    self.output
+                        .write_all(image_attributes(&nl.attributes).as_bytes())?;
+                    self.output.write_all(b" />")?;
                 }
             }
             NodeValue::Table(..) => {
                 if entering {
                     self.cr()?;
-                    self.output.write_all(b"<table>\n")?;
+                    self.output.write_all(b"<table")?;
+                    self.fragment(node)?;
+                    self.output.write_all(b">\n")?;
                 } else {
                     if !node
                         .last_child()
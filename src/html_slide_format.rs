@@ -1,63 +1,83 @@
 //! html_slide_format
 use ctype::isspace;
+use html::{effect_tag, image_attributes};
 use nodes::{AstNode, ListType, NodeCode, NodeValue, TableAlignment};
-use parser::ComrakOptions;
+use parser::{ComrakOptions, SlideContentMode};
 use regex::Regex;
 use scanners;
+use slides::{
+    build_deck, is_slide_only, AgendaEntry, DeckMetadata, Layout, Metadata, Slide, SourceRange,
+    SplitReason, Transitions,
+};
 
 use std::borrow::Cow;
 use std::cell::Cell;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::io::{self, Write};
+use std::mem;
 use std::str::{self, FromStr};
 
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use serde_json::{Result, Value};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 pub struct SlideHtmlDom {
     front: SlideSectionHtmlDom,
     content: Vec<SlideSectionHtmlDom>,
 
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    agenda: Vec<AgendaEntry>,
     title : String,
-
-    #[serde(skip)]
-    format_level: u8, // 0:cover  -1 footer 标记format时 当前在那一页
+    deck: DeckMetadata,
 
     #[serde(skip)]
     format_content: String, // 当前页面的内容
-
-    #[serde(skip)]
-    format_meta: HashMap<String, String>,
-
-    #[serde(skip)]
-    format_notes: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 pub struct SlideSectionHtmlDom {
-    meta: HashMap<String, String>,
-    content: String,
-    notes: String,
+    meta: Metadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+    notes: Vec<String>,
     title : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layout: Option<Layout>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    regions: Vec<SlideRegionHtmlDom>,
+    #[serde(flatten)]
+    transitions: Transitions,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    agenda: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<usize>,
+    split: SplitReason,
+    steps: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<SourceRange>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlideRegionHtmlDom {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
 }
 
 impl SlideHtmlDom {
     pub fn new() -> Self {
         SlideHtmlDom {
-            front: SlideSectionHtmlDom {
-                meta: HashMap::new(),
-                content: String::new(),
-                notes: String::new(),
-                title: String::new(),
-            },
+            front: SlideSectionHtmlDom::new(),
 
             content: Vec::new(),
-            format_level: 0,
+            agenda: Vec::new(),
             format_content: String::new(),
-            format_meta: HashMap::new(),
-            format_notes: String::new(),
             title: String::new(),
+            deck: DeckMetadata::default(),
         }
     }
 }
@@ -65,10 +85,19 @@ impl SlideHtmlDom {
 impl SlideSectionHtmlDom {
     pub fn new() -> Self {
         SlideSectionHtmlDom {
-            meta: HashMap::new(),
-            content: String::new(),
-            notes: String::new(),
+            meta: Metadata::new(),
+            content: None,
+            html: None,
+            notes: Vec::new(),
             title: String::new(),
+            layout: None,
+            regions: Vec::new(),
+            transitions: Transitions::default(),
+            agenda: false,
+            section: None,
+            split: SplitReason::Start,
+            steps: 0,
+            source: None,
         }
     }
 }
@@ -78,33 +107,11 @@ pub fn format_document_slide_js<'a>(
     root: &'a AstNode<'a>,
     options: &ComrakOptions,
     output: &mut dyn Write,
-    jsonDom: &mut SlideHtmlDom,
+    json_dom: &mut SlideHtmlDom,
 ) -> io::Result<()> {
-    // println!("format_document_slide");
+    format_deck(root, options, json_dom)?;
 
-    let mut writer = WriteWithLast {
-        output,
-        last_was_lf: Cell::new(true),
-    };
-
-    let mut html: Vec<u8> = vec![];
-    let mut tmp_out = &mut html;
-
-    let mut tmp_writer = WriteWithLast {
-        output: tmp_out,
-        last_was_lf: Cell::new(true),
-    };
-
-    let mut f = HtmlSlideFormatter::new(options, &mut tmp_writer);
-    f.format(root, jsonDom, false)?;
-    f.setupSlideDomContent(root, jsonDom);
-
-    // if f.footnote_ix > 0 {
-    //     f.output.write_all(b"</ol>\n</section>\n")?;
-    // }
-
-    let serialized = serde_json::to_string(&jsonDom).unwrap();
-    // println!("serialized = {}", serialized);
+    let serialized = serde_json::to_string(&json_dom).unwrap();
     output.write_all(serialized.as_bytes())?;
     Ok(())
 }
@@ -115,34 +122,48 @@ pub fn format_document_slide<'a>(
     options: &ComrakOptions,
     output: &mut dyn Write,
 ) -> io::Result<()> {
-    // println!("format_document_slide");
-
-    let mut writer = WriteWithLast {
-        output,
-        last_was_lf: Cell::new(true),
-    };
-
-    let mut html: Vec<u8> = vec![];
-    let mut tmp_out = &mut html;
+    let mut json_dom = SlideHtmlDom::new();
+    format_document_slide_js(root, options, output, &mut json_dom)
+}
 
-    let mut tmp_writer = WriteWithLast {
-        output: tmp_out,
-        last_was_lf: Cell::new(true),
+/// Splits the document per `options.split` and fills `json_dom` with one section per slide.
+fn format_deck<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    json_dom: &mut SlideHtmlDom,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    json_dom.title = match (&deck.metadata.title, &deck.cover) {
+        (Some(title), _) => title.clone(),
+        (None, Some(cover)) => cover.title.clone(),
+        (None, None) => String::new(),
     };
+    json_dom.deck = deck.metadata.clone();
+    json_dom.agenda = deck.agenda.clone();
+
+    for (ix, slide) in deck.cover.iter().chain(deck.slides.iter()).enumerate() {
+        let mut html: Vec<u8> = vec![];
+        let mut writer = WriteWithLast {
+            output: &mut html,
+            last_was_lf: Cell::new(true),
+        };
 
-    let mut jsonDom = SlideHtmlDom::new();
-
-    let mut f = HtmlSlideFormatter::new(options, &mut tmp_writer);
-    f.format(root, &mut jsonDom, false)?;
-    f.setupSlideDomContent(root, &mut jsonDom);
-
-    // if f.footnote_ix > 0 {
-    //     f.output.write_all(b"</ol>\n</section>\n")?;
-    // }
+        let mut f = HtmlSlideFormatter::new(options, &mut writer);
+        let mut regions = vec![];
+        if slide.has_regions() {
+            for region in &slide.regions {
+                for node in &region.nodes {
+                    f.format(node, json_dom, false)?;
+                }
+                regions.push(mem::take(&mut json_dom.format_content));
+            }
+        }
+        for node in slide.nodes.iter().filter(|n| !is_slide_only(n)) {
+            f.format(node, json_dom, false)?;
+        }
+        f.setupSlideDomContent(slide, &regions, ix == 0, json_dom);
+    }
 
-    let serialized = serde_json::to_string(&jsonDom).unwrap();
-    // println!("serialized = {}", serialized);
-    output.write_all(serialized.as_bytes())?;
     Ok(())
 }
 
@@ -490,37 +511,55 @@ impl<'o> HtmlSlideFormatter<'o> {
         }
     }
 
-    fn setupSlideDomContent<'a>(&mut self, node: &'a AstNode<'a>, jsonDom: &mut SlideHtmlDom) {
-        if jsonDom.format_level == 0 {
-            return;
+    fn setupSlideDomContent(
+        &mut self,
+        slide: &Slide,
+        regions: &[String],
+        cover: bool,
+        json_dom: &mut SlideHtmlDom,
+    ) {
+        let mut section_dom = SlideSectionHtmlDom::new();
+        section_dom.meta = slide.metadata.clone();
+        section_dom.notes = slide.notes.clone();
+        section_dom.title = slide.title.clone();
+        section_dom.layout = slide.layout;
+        section_dom.transitions = slide.transitions;
+        section_dom.agenda = slide.agenda;
+        section_dom.section = slide.section;
+        section_dom.split = slide.split;
+        section_dom.steps = slide.steps;
+        section_dom.source = slide.source;
+
+        let mode = self.options.render.slide_content;
+        if mode != SlideContentMode::Html {
+            section_dom.content = Some(json_dom.format_content.clone());
+        }
+        if mode != SlideContentMode::Markdown {
+            section_dom.html = Some(slide.html.clone());
+        }
+        for (region, content) in slide.regions.iter().zip(regions) {
+            section_dom.regions.push(SlideRegionHtmlDom {
+                name: region.name.clone(),
+                content: if mode != SlideContentMode::Html {
+                    Some(content.clone())
+                } else {
+                    None
+                },
+                html: if mode != SlideContentMode::Markdown {
+                    Some(region.html.clone())
+                } else {
+                    None
+                },
+            });
         }
 
-        if jsonDom.format_level == 1 {
-
-
-            jsonDom.front.content = jsonDom.format_content.clone();
-            jsonDom.front.meta = jsonDom.format_meta.clone();
-            jsonDom.front.notes = jsonDom.format_notes.clone();
-            jsonDom.front.title = jsonDom.title.clone();
-        } else if jsonDom.format_level > 1 {
-            let mut sectionDom = SlideSectionHtmlDom::new();
-            sectionDom.content = jsonDom.format_content.clone();
-            sectionDom.meta = jsonDom.format_meta.clone();
-            sectionDom.notes = jsonDom.format_notes.clone();
-            sectionDom.title = jsonDom.title.clone();
-            if sectionDom.notes.is_empty() == false {
-                sectionDom.content = format!(
-                    "{}\n_1001110001000Notes_1001110001000_: {}",
-                    sectionDom.content, sectionDom.notes
-                );
-            }
-
-            jsonDom.content.push(sectionDom);
+        if cover {
+            json_dom.front = section_dom;
+        } else {
+            json_dom.content.push(section_dom);
         }
 
-        jsonDom.format_meta.clear();
-        jsonDom.format_content.clear();
-        jsonDom.format_notes.clear();
+        json_dom.format_content.clear();
     }
 
     fn format_node<'a>(
@@ -591,48 +630,27 @@ impl<'o> HtmlSlideFormatter<'o> {
                     self.output.write_all(b"</dd>\n")?;
                 }
             }
-            NodeValue::SlideMetaDataBlock(ref smd) => {
-                if entering {
-                    let mut meta: HashMap<String, String> = HashMap::new();
-
-                    for kv in &smd.metadatas {
-                        meta.insert(
-                            String::from_utf8_lossy(&kv.key).to_string(),
-                            String::from_utf8_lossy(&kv.value).to_string(),
-                        );
-                    }
-
-                    jsonDom.format_meta = meta.clone();
-                }
-            }
+            NodeValue::SlideMetaDataBlock(..) => (),
             NodeValue::Effect(ref effect) => {
-                if entering {
+                if entering && effect.error.is_none() {
                     self.last_is_effect = true;
                     self.cr()?;
-                    let effcontent = String::from_utf8_lossy(&effect.literal);
-                    writeln!(self.output, "<effect {}>", effcontent)?;
-                    // self.output.write_all(b"\n</effect>\n");
-                } else {
-                    // self.output.write_all(b"</effect>\n")?;
+                    self.output.write_all(effect_tag(effect).as_bytes())?;
                 }
             }
             NodeValue::KV(ref _kv) => {}
             NodeValue::Heading(ref nch) => {
                 if entering {
-                    self.setupSlideDomContent(node, jsonDom);
-
-                    if nch.level == 1 {
-                        jsonDom.format_content = String::new();
-                        jsonDom.format_content = format!("{}", "# ");
-                    } else if nch.level == 2 {
-                        jsonDom.format_content = format!("{}", "## ");
-                    } else if nch.level == 3 {
-                        jsonDom.format_content = format!("{}", "### ");
-                    } else if nch.level == 4 {
-                        jsonDom.format_content = format!("{}", "#### ");
+                    // A heading within a slide follows the blocks before it after a blank line.
+                    if !jsonDom.format_content.trim().is_empty() {
+                        let kept = jsonDom.format_content.trim_end().len();
+                        jsonDom.format_content.truncate(kept);
+                        jsonDom.format_content.push_str("\n\n");
+                    } else {
+                        jsonDom.format_content.clear();
                     }
-
-                    jsonDom.format_level += 1;
+                    jsonDom.format_content += &"#".repeat(nch.level as usize);
+                    jsonDom.format_content.push(' ');
 
                     if self.last_is_effect {
                         self.last_is_effect = false;
@@ -664,7 +682,6 @@ impl<'o> HtmlSlideFormatter<'o> {
             NodeValue::CodeBlock(ref ncb) => {
                 if entering {
                     self.cr()?;
-                    let mut is_note = false;
                     if ncb.info.is_empty() {
                         self.output.write_all(b"<pre><code>")?;
                     } else {
@@ -685,27 +702,20 @@ impl<'o> HtmlSlideFormatter<'o> {
 
                         let language = String::from_utf8_lossy(&ncb.info[..first_tag]);
 
-                        println!("language：{}", language);
-                        if &language == "note" {
-                            is_note = true;
-                            jsonDom.format_notes =
-                                format!("{}", String::from_utf8_lossy(&ncb.literal));
-                        } else {
-                            jsonDom.format_content =
-                                format!("{}\n{}{}", jsonDom.format_content, "```".to_string(), language);
-                        }
-                    }
-
-                    if !is_note {
                         jsonDom.format_content = format!(
-                            "{}\n{}",
+                            "{}\n{}{}",
                             jsonDom.format_content,
-                            String::from_utf8_lossy(&ncb.literal)
+                            "```".to_string(),
+                            language
                         );
-
-                        println!("String::from_utf8_lossy(&ncb.literal):{}", String::from_utf8_lossy(&ncb.literal));
                     }
 
+                    jsonDom.format_content = format!(
+                        "{}\n{}",
+                        jsonDom.format_content,
+                        String::from_utf8_lossy(&ncb.literal)
+                    );
+
                     self.escape(&ncb.literal)?;
                     self.output.write_all(b"</code></pre>\n")?;
                 } else {
@@ -750,11 +760,6 @@ impl<'o> HtmlSlideFormatter<'o> {
                                 );
                             }
                             NodeValue::Heading(..) => {
-                                // 为了title 才这里给header赋值
-                                jsonDom.title =  format!(
-                                    "{}",
-                                    String::from_utf8_lossy(literal));
-
                                 jsonDom.format_content = format!(
                                     "{}{}",
                                     jsonDom.format_content,
@@ -993,7 +998,10 @@ impl<'o> HtmlSlideFormatter<'o> {
                         self.output.write_all(b"\" title=\"")?;
                         self.escape(&nl.title)?;
                     }
-                    self.output.write_all(b"\" />")?;
+                    self.output.write_all(b"\"")?;
+                    self.output
+                        .write_all(image_attributes(&nl.attributes).as_bytes())?;
+                    self.output.write_all(b" />")?;
                     let mut need_close = false;
                     if self.options.render.unsafe_ || !dangerous_url(&nl.url) {
                         // jsonDom.format_content = format!(
@@ -1037,7 +1045,11 @@ impl<'o> HtmlSlideFormatter<'o> {
                     }
 
                     if need_close {
-                        jsonDom.format_content = format!("{}>\n\n", jsonDom.format_content);
+                        jsonDom.format_content = format!(
+                            "{}{}>\n\n",
+                            jsonDom.format_content,
+                            image_attributes(&nl.attributes)
+                        );
                     }
 
                     // if !nl.title.is_empty() {
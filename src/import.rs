@@ -0,0 +1,649 @@
+//! Import of decks written for other slide tools.
+//!
+//! A deck written in one of the supported dialects is translated into this crate's own slide
+//! syntax and then parsed as usual, so the result is an ordinary document.
+//! - Slides are separated by `<!-- slide -->` markers.
+//! - Each slide's directives or attributes become a metadata block.
+//! - Speaker notes become ```` ```note ```` fences or `<!-- notes: ... -->` comments.
+//! - Incremental content follows `<!-- fragment -->` or `<!-- fragment: rest -->` markers.
+//!
+//! Split and format the document with the options returned by `Dialect::options`.
+//!
+//! ```
+//! use comrak::import::{parse_dialect, Dialect};
+//! use comrak::{build_deck, Arena, ComrakOptions};
+//!
+//! let options = Dialect::Marp.options(&ComrakOptions::default());
+//! let arena = Arena::new();
+//! let root = parse_dialect(
+//!     &arena,
+//!     "# Hello\n\n<!-- _class: lead -->\n\n---\n\n# World\n\n<!-- Wave. -->\n",
+//!     Dialect::Marp,
+//!     &options,
+//! );
+//! let deck = build_deck(root, &options);
+//!
+//! assert_eq!(deck.cover.unwrap().metadata.get_str("class"), Some("lead"));
+//! assert_eq!(deck.slides[0].title, "World");
+//! assert_eq!(deck.slides[0].notes, vec!["Wave."]);
+//! ```
+
+use arena_tree::Node;
+use nodes::{Ast, AstNode, NodeSlideMetaDataBlock, NodeValue};
+use parser::{metadata, parse_document, ComrakOptions, SlideSplitOptions};
+use regex::Regex;
+use slides::is_slide_marker;
+use std::cell::RefCell;
+use typed_arena::Arena;
+
+/// Marp's global directives, which apply to the whole deck.
+const MARP_GLOBAL: &[&str] = &[
+    "theme",
+    "style",
+    "headingDivider",
+    "size",
+    "math",
+    "title",
+    "author",
+    "description",
+    "image",
+    "keywords",
+    "url",
+    "lang",
+    "marp",
+];
+
+/// Marp's local directives, which apply to the slide setting them and every slide after it, or
+/// only to that slide when prefixed with `_`.
+const MARP_LOCAL: &[&str] = &[
+    "paginate",
+    "header",
+    "footer",
+    "class",
+    "backgroundColor",
+    "backgroundImage",
+    "backgroundPosition",
+    "backgroundRepeat",
+    "backgroundSize",
+    "color",
+    "transition",
+];
+
+/// The Markdown dialect of another slide tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// [Marp](https://marp.app/).  `---` separates slides.  An HTML comment holds either
+    /// directives, such as `<!-- paginate: true -->` or `<!-- _class: lead -->`, or speaker
+    /// notes.  Global directives go in the front matter, camel-cased names are written with
+    /// hyphens (`backgroundColor` becomes `background-color`), and `headingDivider` starts a
+    /// slide at each heading of its level or a higher rank.
+    Marp,
+
+    /// [Remark](https://remarkjs.com/).  `---` separates slides and `--` reveals the rest of a
+    /// slide in one step, after any earlier `--`.  `key: value` properties at the start of a
+    /// slide, such as `class: center, middle`, become its metadata, and `???` starts its
+    /// speaker notes.
+    Remark,
+
+    /// The Markdown of [reveal.js](https://revealjs.com/).  `---` and `--` both separate
+    /// slides, and a slide after `--` has `vertical: true` metadata, stacking it under the
+    /// slide before it in `format_reveal`'s output.  `Note:` starts a slide's speaker notes,
+    /// and the attributes of a `<!-- .slide: ... -->` comment become its metadata, without any
+    /// `data-` prefix.
+    Reveal,
+}
+
+impl Dialect {
+    /// Parse a dialect name: `marp`, `remark` or `reveal`.  Returns `None` for an unknown name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "marp" => Some(Dialect::Marp),
+            "remark" => Some(Dialect::Remark),
+            "reveal" => Some(Dialect::Reveal),
+            _ => None,
+        }
+    }
+
+    /// `options`, adjusted for decks imported from the dialect.  Slides are split at
+    /// `<!-- slide -->` markers only, since none of the dialects starts a slide at a heading.
+    /// Marp and reveal.js decks may open with front matter delimited by `---`, which is read
+    /// unless `options` already sets a delimiter.
+    pub fn options(self, options: &ComrakOptions) -> ComrakOptions {
+        let mut options = options.clone();
+        options.split = SlideSplitOptions {
+            heading_level: 0,
+            thematic_break: false,
+            marker: true,
+        };
+        if self != Dialect::Remark && options.extension.front_matter_delimiter.is_none() {
+            options.extension.front_matter_delimiter = Some("---".to_string());
+        }
+        options
+    }
+}
+
+/// Parse a deck written in `dialect` into an AST.  `options` should come from
+/// `Dialect::options`, and the same options used to split and format the document.
+///
+/// See the documentation of the `import` module for an example.
+pub fn parse_dialect<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    buffer: &str,
+    dialect: Dialect,
+    options: &ComrakOptions,
+) -> &'a AstNode<'a> {
+    let mut translator = Translator::new(dialect);
+    let delimiter = options.extension.front_matter_delimiter.as_deref();
+    let markdown = translator.translate(buffer, delimiter);
+    let root = parse_document(arena, &markdown, options);
+    insert_metadata(arena, root, &translator.metadata);
+    root
+}
+
+type Entries = Vec<(String, String)>;
+
+/// The translation of a deck into this crate's syntax, line by line.
+struct Translator {
+    dialect: Dialect,
+
+    /// The translated document, after any front matter.
+    output: String,
+
+    /// The front matter entries, as `key: value` lines.
+    front_matter: Vec<String>,
+
+    /// The metadata entries of each slide, in order.
+    metadata: Vec<Entries>,
+
+    /// The Marp local directives set so far, which carry over to each new slide.
+    inherited: Entries,
+
+    /// Marp's `headingDivider`: headings of this level or a higher rank start a slide.  Being
+    /// a global directive, it applies to the whole deck wherever it is set.
+    heading_divider: u32,
+
+    /// The open code fence: its character and length.
+    fence: Option<(char, usize)>,
+
+    /// The HTML comment read so far, if one spans several lines.
+    comment: Option<String>,
+
+    /// The speaker notes running to the end of the current slide, once started.
+    notes: Option<String>,
+
+    /// Whether the current slide has any content yet.
+    content: bool,
+}
+
+impl Translator {
+    fn new(dialect: Dialect) -> Self {
+        Translator {
+            dialect,
+            output: String::new(),
+            front_matter: vec![],
+            metadata: vec![vec![]],
+            inherited: vec![],
+            heading_divider: 0,
+            fence: None,
+            comment: None,
+            notes: None,
+            content: false,
+        }
+    }
+
+    fn translate(&mut self, buffer: &str, delimiter: Option<&str>) -> String {
+        if self.dialect == Dialect::Marp {
+            self.heading_divider = heading_divider(buffer);
+        }
+
+        let mut lines = buffer.lines().peekable();
+
+        let mut front_matter = false;
+        if let Some(delimiter) = delimiter {
+            if lines.peek().map(|l| l.trim_end()) == Some(delimiter) {
+                front_matter = true;
+                lines.next();
+                for line in &mut lines {
+                    if line.trim_end() == delimiter {
+                        break;
+                    }
+                    self.front_matter_line(line);
+                }
+            }
+        }
+
+        for line in lines {
+            self.line(line);
+        }
+        if let Some(comment) = self.comment.take() {
+            self.output += &comment;
+        }
+        self.end_slide();
+
+        match delimiter {
+            Some(delimiter) if front_matter || !self.front_matter.is_empty() => format!(
+                "{}\n{}{}\n\n{}",
+                delimiter,
+                self.front_matter.concat(),
+                delimiter,
+                self.output
+            ),
+            _ => self.output.clone(),
+        }
+    }
+
+    fn front_matter_line(&mut self, line: &str) {
+        if self.dialect == Dialect::Marp && !line.starts_with(char::is_whitespace) {
+            if let Some((key, value)) = split_entry(line) {
+                self.global(key, value);
+                return;
+            }
+        }
+        self.front_matter.push(format!("{}\n", line));
+    }
+
+    fn line(&mut self, line: &str) {
+        if let Some((c, length)) = self.fence {
+            if closes_fence(line, c, length) {
+                self.fence = None;
+            }
+            self.push(line);
+            return;
+        }
+
+        if let Some(mut comment) = self.comment.take() {
+            comment += line;
+            comment.push('\n');
+            if line.contains("-->") {
+                self.html_comment(&comment);
+            } else {
+                self.comment = Some(comment);
+            }
+            return;
+        }
+
+        let trimmed = line.trim_end();
+        if trimmed == "---" {
+            self.start_slide();
+            return;
+        }
+        if trimmed == "--" && self.dialect == Dialect::Reveal {
+            self.start_slide();
+            self.set("vertical".to_string(), "true".to_string());
+            return;
+        }
+
+        if let Some(fence) = opens_fence(line) {
+            self.fence = Some(fence);
+            self.push(line);
+            return;
+        }
+
+        if self.notes.is_some() {
+            self.push(line);
+            return;
+        }
+
+        if line.starts_with("<!--") {
+            let comment = format!("{}\n", line);
+            if line.contains("-->") {
+                self.html_comment(&comment);
+            } else {
+                self.comment = Some(comment);
+            }
+            return;
+        }
+
+        match self.dialect {
+            Dialect::Marp => {
+                let divides = heading_level(line).is_some_and(|l| l <= self.heading_divider);
+                if divides && self.content {
+                    self.start_slide();
+                }
+            }
+            Dialect::Remark => {
+                if trimmed == "--" {
+                    self.block("<!-- fragment: rest -->");
+                    return;
+                }
+                if trimmed == "???" {
+                    self.notes = Some(String::new());
+                    return;
+                }
+                if !self.content {
+                    if let Some((key, value)) = split_entry(line) {
+                        self.remark_property(key, value);
+                        return;
+                    }
+                }
+            }
+            Dialect::Reveal => {
+                lazy_static! {
+                    static ref NOTE: Regex = Regex::new(r"(?i)^\s*notes?:").unwrap();
+                }
+                if let Some(m) = NOTE.find(line) {
+                    self.notes = Some(String::new());
+                    self.push(line[m.end()..].trim_start());
+                    return;
+                }
+            }
+        }
+
+        self.push(line);
+    }
+
+    /// Handle an HTML comment starting a line, with each of its lines ending in a newline.
+    fn html_comment(&mut self, comment: &str) {
+        let inner = comment
+            .trim_end()
+            .strip_prefix("<!--")
+            .and_then(|c| c.strip_suffix("-->"))
+            .map(str::trim);
+        let inner = match inner {
+            Some(inner) if !inner.contains("-->") => inner,
+            _ => {
+                // Text follows the comment, so it is not a block of its own.
+                self.output += comment;
+                self.content = true;
+                return;
+            }
+        };
+
+        match self.dialect {
+            Dialect::Marp => match marp_directives(inner) {
+                Some(directives) => {
+                    for (key, value) in directives {
+                        self.marp_directive(key, value);
+                    }
+                }
+                None if inner.is_empty() => (),
+                None => self.block(&format!("<!-- notes: {} -->", inner)),
+            },
+            _ if inner == "slide" => self.start_slide(),
+            Dialect::Reveal if inner.starts_with(".slide:") => {
+                for (key, value) in reveal_attributes(&inner[".slide:".len()..]) {
+                    self.set(key, value);
+                }
+            }
+            _ => self.output += comment,
+        }
+    }
+
+    fn marp_directive(&mut self, key: &str, value: &str) {
+        match key.strip_prefix('_') {
+            Some(name) if MARP_LOCAL.contains(&name) => self.set(marp_key(name), value.to_string()),
+            _ if MARP_LOCAL.contains(&key) => {
+                let key = marp_key(key);
+                self.inherited.retain(|(k, _)| *k != key);
+                self.inherited.push((key.clone(), value.to_string()));
+                self.set(key, value.to_string());
+            }
+            _ => self.global(key.trim_start_matches('_'), value),
+        }
+    }
+
+    /// Add a Marp global directive to the front matter.
+    fn global(&mut self, key: &str, value: &str) {
+        self.front_matter
+            .push(format!("{}: {}\n", marp_key(key), value));
+    }
+
+    fn remark_property(&mut self, key: &str, value: &str) {
+        let value = if key == "class" && value.contains(',') {
+            let classes = value.split(',').map(str::trim).collect::<Vec<_>>();
+            format!("[{}]", classes.join(", "))
+        } else {
+            value.to_string()
+        };
+        self.set(key.to_string(), value);
+    }
+
+    /// Set a metadata entry of the current slide.
+    fn set(&mut self, key: String, value: String) {
+        self.metadata.last_mut().unwrap().push((key, value));
+    }
+
+    /// Copy a line to the current slide's notes, if started, or else to the slide.
+    fn push(&mut self, line: &str) {
+        let target = self.notes.as_mut().unwrap_or(&mut self.output);
+        *target += line;
+        target.push('\n');
+        if !line.trim().is_empty() && self.notes.is_none() {
+            self.content = true;
+        }
+    }
+
+    /// Write an HTML block of its own.
+    fn block(&mut self, html: &str) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+        self.output += html;
+        self.output += "\n\n";
+    }
+
+    fn start_slide(&mut self) {
+        self.end_slide();
+        self.block("<!-- slide -->");
+        self.metadata.push(self.inherited.clone());
+        self.content = false;
+    }
+
+    fn end_slide(&mut self) {
+        self.fence = None;
+        if let Some(notes) = self.notes.take() {
+            let notes = notes.trim();
+            if !notes.is_empty() {
+                let longest = notes
+                    .lines()
+                    .map(|l| l.trim_start().len() - l.trim_start().trim_start_matches('`').len())
+                    .max()
+                    .unwrap_or(0);
+                let fence = "`".repeat(longest.max(2) + 1);
+                self.block(&format!("{}note\n{}\n{}", fence, notes, fence));
+            }
+        }
+    }
+}
+
+/// Split a `key: value` line, where the key is a plain identifier.
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    lazy_static! {
+        static ref ENTRY: Regex =
+            Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_-]*)\s*:\s*(.*?)\s*$").unwrap();
+    }
+    let captures = ENTRY.captures(line)?;
+    Some((captures.get(1)?.as_str(), captures.get(2)?.as_str()))
+}
+
+/// The directives of a Marp comment, or `None` if it holds anything else.
+fn marp_directives(comment: &str) -> Option<Vec<(&str, &str)>> {
+    let mut directives = vec![];
+    for line in comment.lines().filter(|l| !l.trim().is_empty()) {
+        let (key, value) = split_entry(line)?;
+        let name = key.strip_prefix('_').unwrap_or(key);
+        if !MARP_GLOBAL.contains(&name) && !MARP_LOCAL.contains(&name) {
+            return None;
+        }
+        directives.push((key, value));
+    }
+    if directives.is_empty() {
+        None
+    } else {
+        Some(directives)
+    }
+}
+
+/// The level set by Marp's `headingDivider` directive, in the front matter or a comment
+/// outside code blocks, or 0 if it is not set.
+fn heading_divider(buffer: &str) -> u32 {
+    lazy_static! {
+        static ref HEADING_DIVIDER: Regex =
+            Regex::new(r"^\s*(?:<!--\s*)?headingDivider\s*:\s*([1-6])\s*(?:-->)?\s*$").unwrap();
+    }
+    let mut fence = None;
+    for line in buffer.lines() {
+        match fence {
+            Some((c, length)) => {
+                if closes_fence(line, c, length) {
+                    fence = None;
+                }
+            }
+            None => {
+                if let Some(captures) = HEADING_DIVIDER.captures(line) {
+                    return captures[1].parse().unwrap();
+                }
+                fence = opens_fence(line);
+            }
+        }
+    }
+    0
+}
+
+/// The metadata key of a Marp directive: `size` is the aspect ratio, `lang` the language, and
+/// camel-cased names are written with hyphens.
+fn marp_key(name: &str) -> String {
+    match name {
+        "size" => "aspect-ratio".to_string(),
+        "lang" => "language".to_string(),
+        _ => {
+            let mut key = String::new();
+            for c in name.chars() {
+                if c.is_ascii_uppercase() {
+                    key.push('-');
+                }
+                key.push(c.to_ascii_lowercase());
+            }
+            key
+        }
+    }
+}
+
+/// The attributes of a reveal.js `.slide:` comment, as metadata entries.
+fn reveal_attributes(text: &str) -> Entries {
+    lazy_static! {
+        static ref ATTRIBUTE: Regex = Regex::new(
+            r#"([A-Za-z_:][-A-Za-z0-9_:.]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#
+        )
+        .unwrap();
+    }
+    ATTRIBUTE
+        .captures_iter(text)
+        .map(|captures| {
+            let name = &captures[1];
+            let key = name.strip_prefix("data-").unwrap_or(name).to_string();
+            let value = match (2..5).find_map(|i| captures.get(i)) {
+                Some(value) => quote(value.as_str()),
+                None => "true".to_string(),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// A metadata value holding `s` as a string.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The level of an ATX heading.
+fn heading_level(line: &str) -> Option<u32> {
+    let line = line.trim_start_matches(' ');
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        Some(level as u32)
+    } else {
+        None
+    }
+}
+
+/// The character and length of a code fence opened by `line`.
+fn opens_fence(line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let line = &line[indent..];
+    let c = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let length = line.len() - line.trim_start_matches(c).len();
+    let info = &line[length..];
+    if indent < 4 && length >= 3 && !(c == '`' && info.contains('`')) {
+        Some((c, length))
+    } else {
+        None
+    }
+}
+
+fn closes_fence(line: &str, c: char, length: usize) -> bool {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let line = &line[indent..];
+    let fence = line.len() - line.trim_start_matches(c).len();
+    indent < 4 && fence >= length && line[fence..].trim().is_empty()
+}
+
+/// Insert a metadata block into each slide setting any metadata: after the slide's heading if
+/// it opens with one, as a block written in this crate's syntax would be, or else first.
+fn insert_metadata<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, metadata: &[Entries]) {
+    // The marker starting each slide, if any, and its first node.
+    let mut slides = vec![(None, None)];
+    for node in root.children() {
+        match node.data.borrow().value {
+            NodeValue::FrontMatter(..) => continue,
+            NodeValue::HtmlBlock(ref nhb) if is_slide_marker(&nhb.literal) => {
+                slides.push((Some(node), None));
+                continue;
+            }
+            _ => (),
+        }
+        let slide = slides.last_mut().unwrap();
+        if slide.1.is_none() {
+            slide.1 = Some(node);
+        }
+    }
+
+    for ((marker, first), entries) in slides.into_iter().zip(metadata) {
+        if entries.is_empty() {
+            continue;
+        }
+        let block = metadata_block(arena, entries);
+        match first {
+            Some(first) if matches!(first.data.borrow().value, NodeValue::Heading(..)) => {
+                first.insert_after(block)
+            }
+            Some(first) => first.insert_before(block),
+            None => match marker {
+                Some(marker) => marker.insert_after(block),
+                None => root.append(block),
+            },
+        }
+    }
+}
+
+fn metadata_block<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    entries: &[(String, String)],
+) -> &'a AstNode<'a> {
+    let node = |value| {
+        let mut ast = Ast::new(value);
+        ast.open = false;
+        arena.alloc(Node::new(RefCell::new(ast)))
+    };
+
+    let literal = entries
+        .iter()
+        .map(|(key, value)| format!("{}: {}\n", key, value))
+        .collect::<String>()
+        .into_bytes();
+    let metadatas = metadata::parse(&literal);
+    let block = node(NodeValue::SlideMetaDataBlock(NodeSlideMetaDataBlock {
+        fenced: true,
+        fence_char: b'-',
+        fence_length: 3,
+        fence_offset: 0,
+        info: vec![],
+        literal,
+        metadatas: metadatas.clone(),
+    }));
+    for kv in metadatas {
+        block.append(node(NodeValue::KV(kv)));
+    }
+    block
+}
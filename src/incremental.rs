@@ -0,0 +1,413 @@
+//! Keeping a deck up to date as its source is edited, re-parsing only the slides an edit
+//! touches.
+//!
+//! ```
+//! use comrak::incremental::{LiveDeck, TextEdit};
+//! use comrak::{Arena, ComrakOptions};
+//!
+//! let arena = Arena::new();
+//! let input = "# Talk\n\n## One\n\nHello.\n\n## Two\n\nMore.\n";
+//! let mut live = LiveDeck::new(&arena, input, &ComrakOptions::default());
+//!
+//! let start = input.find("Hello").unwrap();
+//! let update = live.edit(&TextEdit::new(start, start + 5, "Goodbye"));
+//!
+//! assert!(!update.full);
+//! assert_eq!(update.changed, [2]);
+//! assert_eq!(live.deck().slide(2).unwrap().html, "<h2>One</h2>\n<p>Goodbye.</p>\n");
+//! assert_eq!(live.source(), input.replace("Hello", "Goodbye"));
+//! ```
+
+use nodes::{AstNode, NodeValue};
+use parser::{line_starts, parse_document_part, ComrakOptions, Reference};
+use slides::{agenda, build_deck, split_slides, Deck, SplitReason};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use typed_arena::Arena;
+
+/// A change to a document's source: the bytes `start..end` replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The byte offset of the first byte replaced.
+    pub start: usize,
+
+    /// The byte offset just past the last byte replaced; `start` for an insertion.
+    pub end: usize,
+
+    /// The text replacing the bytes.
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Create an edit replacing the bytes `start..end` with `text`.
+    pub fn new(start: usize, end: usize, text: &str) -> Self {
+        TextEdit {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+}
+
+/// How a `LiveDeck` changed after an edit.  Slides are numbered counting the cover as 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckUpdate {
+    /// Whether the whole document was parsed again.
+    pub full: bool,
+
+    /// The numbers of the previous deck's slides which were replaced.
+    pub removed: Range<usize>,
+
+    /// The numbers of the slides which replaced them.  The slides after them are as they were,
+    /// but renumbered if the number of slides changed.
+    pub inserted: Range<usize>,
+
+    /// The numbers of the slides which differ from the previous deck: the inserted slides,
+    /// and any others whose agenda or section changed.
+    pub changed: Vec<usize>,
+}
+
+/// A parsed document and its deck, which can be edited without parsing the whole document
+/// again.
+///
+/// An edit re-parses the slides it touches, along with the slide before when it touches the
+/// first line of a slide, and checks that the slide after still starts where it did.  The
+/// whole document is parsed again instead when that check fails, and when the edit:
+///
+/// * touches the front matter, makes the re-parsed slides start with front matter, or follows
+///   front matter which is not closed;
+/// * adds, removes or changes a link reference definition;
+/// * touches a footnote reference or definition, as footnotes are numbered across the whole
+///   document.
+///
+/// The nodes of re-parsed slides are allocated in the arena, which grows with each edit.
+pub struct LiveDeck<'a> {
+    arena: &'a Arena<AstNode<'a>>,
+    options: ComrakOptions,
+    source: String,
+    root: &'a AstNode<'a>,
+    references: HashMap<Vec<u8>, Reference>,
+    deck: Deck<'a>,
+}
+
+impl<'a> fmt::Debug for LiveDeck<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LiveDeck")
+            .field("source", &self.source)
+            .field("deck", &self.deck)
+            .finish()
+    }
+}
+
+impl<'a> LiveDeck<'a> {
+    /// Parse a document and split it into a deck.
+    pub fn new(arena: &'a Arena<AstNode<'a>>, source: &str, options: &ComrakOptions) -> Self {
+        let (root, references) = parse_document_part(arena, source, options, None);
+        LiveDeck {
+            arena,
+            options: options.clone(),
+            source: source.to_string(),
+            root,
+            references,
+            deck: build_deck(root, options),
+        }
+    }
+
+    /// The document's source, with every edit applied.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The document's AST.  Its nodes' positions are kept up to date with the source.
+    pub fn root(&self) -> &'a AstNode<'a> {
+        self.root
+    }
+
+    /// The document's deck.
+    pub fn deck(&self) -> &Deck<'a> {
+        &self.deck
+    }
+
+    /// Apply an edit to the source, and update the AST and the deck.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edit.start` or `edit.end` is out of bounds or not on a `char` boundary, or
+    /// if `edit.start` is greater than `edit.end`.
+    pub fn edit(&mut self, edit: &TextEdit) -> DeckUpdate {
+        let mut source = self.source.clone();
+        source.replace_range(edit.start..edit.end, &edit.text);
+
+        match self.edit_slides(edit, &source) {
+            Some(update) => {
+                self.source = source;
+                update
+            }
+            None => self.reparse(source),
+        }
+    }
+
+    /// Parse the whole of `source` again.
+    fn reparse(&mut self, source: String) -> DeckUpdate {
+        let removed = self.slide_count();
+        let (root, references) = parse_document_part(self.arena, &source, &self.options, None);
+        self.root = root;
+        self.references = references;
+        self.deck = build_deck(root, &self.options);
+        self.source = source;
+
+        let inserted = self.slide_count();
+        DeckUpdate {
+            full: true,
+            removed: 1..removed + 1,
+            inserted: 1..inserted + 1,
+            changed: (1..inserted + 1).collect(),
+        }
+    }
+
+    fn slide_count(&self) -> usize {
+        self.deck.cover.iter().count() + self.deck.slides.len()
+    }
+
+    /// Re-parse only the slides `edit` touches, giving `source` after the edit, or `None` if
+    /// the whole document must be parsed again.
+    fn edit_slides(&mut self, edit: &TextEdit, source: &str) -> Option<DeckUpdate> {
+        let options = &self.options;
+        let slides = self
+            .deck
+            .cover
+            .iter()
+            .chain(self.deck.slides.iter())
+            .collect::<Vec<_>>();
+        // The slides written in the source, with their numbers and the line their split ends on.
+        let written = slides
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, slide)| {
+                let range = slide.source?;
+                let split_end = slide
+                    .nodes
+                    .first()
+                    .map(|n| n.data.borrow())
+                    .filter(|ast| ast.start_line == range.start_line)
+                    .map_or(range.start_line, |ast| ast.end_line);
+                Some((ix + 1, range, split_end))
+            })
+            .collect::<Vec<_>>();
+        if written.is_empty() || source.is_empty() || edit.start < written[0].1.start_byte {
+            return None;
+        }
+
+        let containing = |byte: usize| {
+            written
+                .iter()
+                .position(|&(_, range, _)| byte < range.end_byte)
+                .unwrap_or(written.len() - 1)
+        };
+        let old_line_starts = self.root.data.borrow().line_offsets.clone();
+        let mut first = containing(edit.start);
+        let last = containing(edit.end);
+        // Editing the block which starts a slide, such as a setext heading, can join the slide
+        // to the one before.  An edit at the end of a document without a final newline is on
+        // its last line.
+        let split_end = old_line_starts[(written[first].2 as usize).min(old_line_starts.len() - 1)];
+        let on_split =
+            edit.start < split_end || (edit.start == split_end && split_end == self.source.len());
+        if first > 0 && on_split {
+            first -= 1;
+        }
+        // The slide after the edited ones, to check that it still starts where it did.
+        let next = written.get(last + 1).map(|&(_, range, _)| range);
+
+        let start = written[first].1;
+        let end_byte = next.map_or(self.source.len(), |range| range.end_byte);
+        let byte_delta = edit.text.len() as isize - (edit.end - edit.start) as isize;
+        let old_chunk = &self.source[start.start_byte..end_byte];
+        let new_chunk = &source[start.start_byte..(end_byte as isize + byte_delta) as usize];
+
+        if let Some(ref delimiter) = options.extension.front_matter_delimiter {
+            // An unclosed front matter block can be closed by a delimiter further down.
+            let unclosed = self.source.starts_with(delimiter.as_str())
+                && !self
+                    .root
+                    .children()
+                    .any(|n| matches!(n.data.borrow().value, NodeValue::FrontMatter(..)));
+            if unclosed || new_chunk.starts_with(delimiter.as_str()) {
+                return None;
+            }
+        }
+        if options.extension.footnotes && (old_chunk.contains("[^") || new_chunk.contains("[^")) {
+            return None;
+        }
+
+        let (chunk, defined) = parse_document_part(
+            self.arena,
+            new_chunk,
+            options,
+            Some(self.references.clone()),
+        );
+        if old_chunk.contains("]:") || new_chunk.contains("]:") {
+            let arena = Arena::new();
+            let (_, previously) =
+                parse_document_part(&arena, old_chunk, options, Some(self.references.clone()));
+            if defined != previously {
+                return None;
+            }
+        }
+
+        // Move the chunk's nodes to their lines in the document.
+        let line_starts = line_starts(source.as_bytes());
+        let line_delta = line_starts.len() as i64 - old_line_starts.len() as i64;
+        for node in chunk.children() {
+            shift_lines(node, i64::from(start.start_line) - 1);
+        }
+        let (end_line, end_column) = document_end(source, &line_starts);
+        let entries = self.deck.metadata.entries.clone();
+        let mut inserted = split_slides(
+            chunk.children(),
+            options,
+            &entries,
+            start.start_line,
+            end_line,
+            &line_starts,
+        );
+
+        // Keep the chunk's slides up to the one which starts where the next slide did.
+        let boundary = next.map(|range| (i64::from(range.start_line) + line_delta) as u32);
+        if let Some(boundary) = boundary {
+            let kept = inserted
+                .iter()
+                .position(|slide| slide.source.map(|s| s.start_line) == Some(boundary))?;
+            inserted.truncate(kept);
+        }
+        if inserted.is_empty() && first == 0 {
+            return None;
+        }
+
+        // Replace the edited slides' nodes in the document, and move the nodes after them.
+        let old_end_line = written[last].1.end_line;
+        let mut before = None;
+        for node in self.root.children().collect::<Vec<_>>() {
+            let line = node.data.borrow().start_line;
+            if matches!(node.data.borrow().value, NodeValue::FrontMatter(..))
+                || line < start.start_line
+            {
+                continue;
+            }
+            if line <= old_end_line {
+                node.detach();
+            } else {
+                before = before.or(Some(node));
+                shift_lines(node, line_delta);
+            }
+        }
+        let moved = chunk
+            .children()
+            .take_while(|n| match boundary {
+                Some(b) => n.data.borrow().start_line < b,
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        for node in moved {
+            match before {
+                Some(before) => before.insert_before(node),
+                None => self.root.append(node),
+            }
+        }
+        {
+            let mut ast = self.root.data.borrow_mut();
+            ast.line_offsets = line_starts;
+            ast.end_line = end_line;
+            ast.end_column = end_column;
+        }
+
+        // Replace the edited slides in the deck, and move the slides after them.
+        let mut all = self.deck.cover.take().into_iter().collect::<Vec<_>>();
+        all.append(&mut self.deck.slides);
+        let generated = all
+            .iter()
+            .position(|slide| slide.split == SplitReason::Generated)
+            .map(|ix| all.remove(ix));
+        let count = inserted.len();
+        let mut slides = all;
+        let tail = slides.split_off(last + 1);
+        slides.truncate(first);
+        slides.append(&mut inserted);
+        for mut slide in tail {
+            if let Some(ref mut range) = slide.source {
+                range.start_line = (i64::from(range.start_line) + line_delta) as u32;
+                range.end_line = (i64::from(range.end_line) + line_delta) as u32;
+                range.start_byte = (range.start_byte as isize + byte_delta) as usize;
+                range.end_byte = (range.end_byte as isize + byte_delta) as usize;
+            }
+            slides.push(slide);
+        }
+        // The agenda slide goes after the cover, so is replaced along with the cover.
+        let mut removed = written[first].0..written[last].0 + 1;
+        let inserted = match generated {
+            Some(generated) => {
+                slides.insert(1, generated);
+                if first == 0 {
+                    removed.end = removed.end.max(3);
+                    1..count + 2
+                } else {
+                    first + 2..first + count + 2
+                }
+            }
+            None => first + 1..first + count + 1,
+        };
+
+        let agenda = agenda(&slides, &entries);
+        let agenda_changed = agenda != self.deck.agenda;
+        let mut changed = vec![];
+        for (ix, slide) in slides.iter_mut().enumerate() {
+            let number = ix + 1;
+            if inserted.contains(&number) || (agenda_changed && slide.agenda) {
+                slide.finish(ix, &agenda, &entries, options);
+                changed.push(number);
+            } else {
+                let section = agenda.iter().rposition(|e| e.slide <= number);
+                if section != slide.section {
+                    slide.section = section;
+                    changed.push(number);
+                }
+            }
+        }
+
+        let mut slides = slides.into_iter();
+        self.deck.cover = slides.next();
+        self.deck.slides = slides.collect();
+        self.deck.agenda = agenda;
+        Some(DeckUpdate {
+            full: false,
+            removed,
+            inserted,
+            changed,
+        })
+    }
+}
+
+/// Move a node and its descendants `delta` lines down the document.
+fn shift_lines<'a>(node: &'a AstNode<'a>, delta: i64) {
+    for node in node.descendants() {
+        let mut ast = node.data.borrow_mut();
+        if ast.start_line > 0 {
+            ast.start_line = (i64::from(ast.start_line) + delta) as u32;
+        }
+        if ast.end_line > 0 {
+            ast.end_line = (i64::from(ast.end_line) + delta) as u32;
+        }
+    }
+}
+
+/// The line and column of the end of a document, as the parser records them.
+fn document_end(source: &str, line_starts: &[usize]) -> (u32, usize) {
+    let lines = line_starts.len() - 1;
+    let last = &source.as_bytes()[line_starts[lines - 1]..];
+    let length = last
+        .iter()
+        .position(|&c| c == b'\n' || c == b'\r')
+        .unwrap_or(last.len());
+    (lines as u32, length)
+}
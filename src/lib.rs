@@ -97,33 +97,51 @@ use serde::{Deserialize, Serialize};
 extern crate serde_json;
 
 pub mod arena_tree;
+mod beamer;
 mod cm;
 mod ctype;
+pub mod diagnostics;
 mod entity;
+mod export;
 mod html;
 mod html_slide_format;
+pub mod import;
+pub mod incremental;
 pub mod nodes;
+mod odp;
+mod package;
 mod parser;
+mod pptx;
 mod scanners;
+pub mod slides;
+mod standalone;
 mod strings;
 #[cfg(test)]
 mod tests;
+pub mod themes;
+mod typst;
 
+pub use beamer::format_document as format_beamer;
 pub use cm::format_document as format_commonmark;
 pub use html::format_document as format_html;
 pub use html::Anchorizer;
 pub use html_slide_format::format_document_slide as format_slide;
 pub use html_slide_format::format_document_slide_js as format_slide_js;
+pub use odp::format_document as format_odp;
 pub use parser::{
-    dump_node, parse_document, parse_document_with_broken_link_callback, ComrakExtensionOptions,
-    ComrakOptions, ComrakParseOptions, ComrakRenderOptions,
+    dump_node, parse_document, parse_document_with_broken_link_callback,
+    parse_document_with_diagnostics, ComrakExtensionOptions, ComrakOptions, ComrakParseOptions,
+    ComrakRenderOptions, SlideContentMode, SlidePrintOptions, SlideSplitOptions,
 };
+pub use pptx::format_document as format_pptx;
+pub use slides::{build_deck, parse_deck};
+pub use standalone::format_document as format_slide_standalone;
+pub use standalone::print::format_document as format_slide_print;
+pub use standalone::reveal::format_document as format_reveal;
 pub use typed_arena::Arena;
+pub use typst::format_document as format_typst;
 
 use crate::html_slide_format::SlideHtmlDom;
-use crate::nodes::AstNode;
-use crate::nodes::NodeKV;
-use crate::nodes::NodeValue;
 
 /// Render Markdown to HTML.
 ///
@@ -147,52 +165,11 @@ pub fn markdown_to_html(md: &str, options: &ComrakOptions) -> String {
 pub fn markdown_to_html_wasm_bindgen_js(input: &str) -> JsValue {
     let mut jsonDom = SlideHtmlDom::new();
 
-    let md_content = input;
-
     // The returned nodes are created in the supplied Arena, and are bound by its lifetime.
     let arena = Arena::new();
 
-    let root = parse_document(&arena, md_content, &ComrakOptions::default());
-
-    fn iter_nodes<'a, F>(node: &'a AstNode<'a>, f: &F)
-    where
-        F: Fn(&'a AstNode<'a>),
-    {
-        f(node);
-        for c in node.children() {
-            iter_nodes(c, f);
-        }
-    }
-
-    iter_nodes(root, &|node| {
-        match node.data.borrow_mut().value {
-            NodeValue::SlideMetaDataBlock(ref mut smd) => {
-                let kv_literal = String::from_utf8_lossy(&smd.literal);
-
-                // smd.metadatas = Vec::new();
-                let lines = kv_literal.lines();
-                for line in lines {
-                    // let kv = NodeValue::KV;
-                    if let Some((k, v)) = line.split_once(':') {
-                        if k.len() <= 0 {
-                            break;
-                        }
-
-                        let nodekv = NodeKV {
-                            key: k.as_bytes().to_vec(),
-                            value: v.as_bytes().to_vec(),
-                        };
-                        smd.metadatas.push(nodekv);
-                    } else {
-                    }
-                }
-            }
-            NodeValue::CodeBlock(ref mut codeblock) => {}
-            _ => (),
-        }
-    });
+    let root = parse_document(&arena, input, &ComrakOptions::default());
 
-    // dump_node(root);
     let mut html = vec![];
     let format_slide_result =
         format_slide_js(root, &ComrakOptions::default(), &mut html, &mut jsonDom);
@@ -208,59 +185,15 @@ pub fn markdown_to_html_wasm_bindgen_js(input: &str) -> JsValue {
 ///
 #[wasm_bindgen]
 pub fn markdown_to_html_wasm_bindgen(input: &str) -> String {
-    let md_content = input;
-    let mut result = String::new();
-
     // The returned nodes are created in the supplied Arena, and are bound by its lifetime.
     let arena = Arena::new();
 
-    let root = parse_document(&arena, md_content, &ComrakOptions::default());
-
-    fn iter_nodes<'a, F>(node: &'a AstNode<'a>, f: &F)
-    where
-        F: Fn(&'a AstNode<'a>),
-    {
-        f(node);
-        for c in node.children() {
-            iter_nodes(c, f);
-        }
-    }
-
-    iter_nodes(root, &|node| {
-        match node.data.borrow_mut().value {
-            NodeValue::SlideMetaDataBlock(ref mut smd) => {
-                let kv_literal = String::from_utf8_lossy(&smd.literal);
-
-                // smd.metadatas = Vec::new();
-                let lines = kv_literal.lines();
-                for line in lines {
-                    // let kv = NodeValue::KV;
-                    if let Some((k, v)) = line.split_once(':') {
-                        if k.len() <= 0 {
-                            break;
-                        }
-
-                        let nodekv = NodeKV {
-                            key: k.as_bytes().to_vec(),
-                            value: v.as_bytes().to_vec(),
-                        };
-                        smd.metadatas.push(nodekv);
-                    } else {
-                    }
-                }
-            }
-            NodeValue::CodeBlock(ref mut codeblock) => {}
-            _ => (),
-        }
-    });
+    let root = parse_document(&arena, input, &ComrakOptions::default());
 
-    // dump_node(root);
     let mut html = vec![];
     let format_slide_result = format_slide(root, &ComrakOptions::default(), &mut html);
 
-    let str = String::from_utf8_lossy(&html);
-    result = str.to_string();
-    result
+    String::from_utf8_lossy(&html).to_string()
 }
 
 // #[wasm_bindgen(js_namespace = console)]
@@ -9,8 +9,10 @@ extern crate shell_words;
 #[cfg(not(windows))]
 extern crate xdg;
 
+use comrak::import::Dialect;
 use comrak::{
     Arena, ComrakExtensionOptions, ComrakOptions, ComrakParseOptions, ComrakRenderOptions,
+    SlideContentMode, SlidePrintOptions, SlideSplitOptions,
 };
 
 use std::boxed::Box;
@@ -19,6 +21,7 @@ use std::env;
 use std::error::Error;
 use std::fs;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process;
 
 const EXIT_SUCCESS: i32 = 0;
@@ -80,6 +83,15 @@ if the file does not exist.\
                 .value_name("INFO")
                 .takes_value(true),
         )
+        .arg(
+            clap::Arg::with_name("effect")
+                .long("effect")
+                .takes_value(true)
+                .number_of_values(1)
+                .multiple(true)
+                .value_name("NAME")
+                .help("Treat NAME as a known effect in ::effect[...] directives"),
+        )
         .arg(
             clap::Arg::with_name("unsafe")
                 .long("unsafe")
@@ -106,6 +118,7 @@ if the file does not exist.\
                     "superscript",
                     "footnotes",
                     "description-lists",
+                    "image-attributes",
                 ])
                 .value_name("EXTENSION")
                 .help("Specify an extension name to use"),
@@ -115,11 +128,60 @@ if the file does not exist.\
                 .short("t")
                 .long("to")
                 .takes_value(true)
-                .possible_values(&["html", "commonmark"])
+                .possible_values(&[
+                    "html",
+                    "commonmark",
+                    "slides-html",
+                    "slides-print",
+                    "pptx",
+                    "odp",
+                    "beamer",
+                    "typst",
+                    "reveal",
+                ])
                 .default_value("html")
                 .value_name("FORMAT")
                 .help("Specify output format"),
         )
+        .arg(
+            clap::Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .possible_values(&["marp", "remark", "reveal"])
+                .value_name("DIALECT")
+                .help("Import a deck written in the Markdown dialect of another slide tool"),
+        )
+        .arg(
+            clap::Arg::with_name("theme")
+                .long("theme")
+                .takes_value(true)
+                .value_name("THEME")
+                .help("Use the named theme for slides output, overriding the deck's theme"),
+        )
+        .arg(
+            clap::Arg::with_name("theme-dir")
+                .long("theme-dir")
+                .takes_value(true)
+                .number_of_values(1)
+                .multiple(true)
+                .value_name("DIR")
+                .help("Search DIR for themes which are not built in"),
+        )
+        .arg(
+            clap::Arg::with_name("print-fragments")
+                .long("print-fragments")
+                .help("Print a page for every build step of a slide in slides-print output"),
+        )
+        .arg(
+            clap::Arg::with_name("print-notes")
+                .long("print-notes")
+                .help("Print speaker notes under each slide in slides-print output"),
+        )
+        .arg(
+            clap::Arg::with_name("link-footnotes")
+                .long("link-footnotes")
+                .help("List link URLs as footnotes on each slide in slides-print output"),
+        )
         .arg(
             clap::Arg::with_name("output")
                 .short("o")
@@ -192,12 +254,16 @@ if the file does not exist.\
             front_matter_delimiter: matches
                 .value_of("front-matter-delimiter")
                 .map(|s| s.to_string()),
+            image_attributes: exts.remove("image-attributes"),
         },
         parse: ComrakParseOptions {
             smart: matches.is_present("smart"),
             default_info_string: matches
                 .value_of("default-info-string")
                 .map(|e| e.to_owned()),
+            effects: matches
+                .values_of("effect")
+                .map_or(vec![], |values| values.map(|e| e.to_owned()).collect()),
         },
         render: ComrakRenderOptions {
             hardbreaks: matches.is_present("hardbreaks"),
@@ -209,6 +275,26 @@ if the file does not exist.\
                 .unwrap_or(0),
             unsafe_: matches.is_present("unsafe"),
             escape: matches.is_present("escape"),
+            slide_content: SlideContentMode::default(),
+            theme: matches.value_of("theme").map(|s| s.to_string()),
+            theme_dirs: matches
+                .values_of("theme-dir")
+                .map_or(vec![], |dirs| dirs.map(PathBuf::from).collect()),
+            // Images are read from the directory of the input, or the current directory.
+            base_dir: Some(
+                matches
+                    .values_of("file")
+                    .and_then(|mut files| files.next())
+                    .and_then(|file| Path::new(file).parent())
+                    .map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+            ),
+            reveal: false,
+        },
+        split: SlideSplitOptions::default(),
+        print: SlidePrintOptions {
+            fragments: matches.is_present("print-fragments"),
+            notes: matches.is_present("print-notes"),
+            link_footnotes: matches.is_present("link-footnotes"),
         },
     };
 
@@ -238,12 +324,36 @@ if the file does not exist.\
         }
     };
 
+    let dialect = matches.value_of("from").and_then(Dialect::parse);
+    let options = match dialect {
+        Some(dialect) => dialect.options(&options),
+        None => options,
+    };
+
     let arena = Arena::new();
-    let root = comrak::parse_document(&arena, &String::from_utf8(s)?, &options);
+    let input = String::from_utf8(s)?;
+    let root = match dialect {
+        Some(dialect) => comrak::import::parse_dialect(&arena, &input, dialect, &options),
+        None => {
+            let (root, diagnostics) =
+                comrak::parse_document_with_diagnostics(&arena, &input, &options);
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            root
+        }
+    };
 
     let formatter = match matches.value_of("format") {
         Some("html") => comrak::format_html,
         Some("commonmark") => comrak::format_commonmark,
+        Some("slides-html") => comrak::format_slide_standalone,
+        Some("slides-print") => comrak::format_slide_print,
+        Some("pptx") => comrak::format_pptx,
+        Some("odp") => comrak::format_odp,
+        Some("beamer") => comrak::format_beamer,
+        Some("typst") => comrak::format_typst,
+        Some("reveal") => comrak::format_reveal,
         _ => panic!("unknown format"),
     };
 
@@ -2,6 +2,7 @@
 
 use arena_tree::Node;
 use std::cell::RefCell;
+use std::fmt;
 
 /// The core AST node enum.
 #[derive(Debug, Clone, PartialEq)]
@@ -65,7 +66,8 @@ pub enum NodeValue {
     /// **Block**. Details of slide metadata.
     SlideMetaDataBlock(NodeSlideMetaDataBlock),
 
-    /// KV  auth: levene
+    /// One `key: value` entry of a slide metadata block.  Produced as the children of
+    /// `SlideMetaDataBlock`, in source order.
     KV(NodeKV),
 
     /// **Block**. A code block; may be [fenced](https://github.github.com/gfm/#fenced-code-blocks)
@@ -183,18 +185,109 @@ pub struct NodeCode {
     pub literal: Vec<u8>,
 }
 
-/// The details of a link's destination, or an image's source.
+/// A single entry of slide metadata.
 #[derive(Debug, Clone, PartialEq)]
 pub struct NodeKV {
-    /// key
+    /// The key, with surrounding whitespace and quotes removed.
     pub key: Vec<u8>,
 
-    /// value
-    pub value: Vec<u8>,
+    /// The typed value.
+    pub value: MetaValue,
 }
 
-/// The details of a link's destination, or an image's source.
+/// A typed slide metadata value.
 #[derive(Debug, Clone, PartialEq)]
+pub enum MetaValue {
+    /// A plain or quoted string.
+    String(String),
+
+    /// A number, such as `3` or `-1.5e3`.
+    Number(f64),
+
+    /// `true` or `false`.
+    Bool(bool),
+
+    /// A list, written `[a, b]` or as indented `- item` lines.
+    List(Vec<MetaValue>),
+
+    /// A map, written `{a: 1, b: 2}` or as indented `key: value` lines.  Entries keep their
+    /// source order.
+    Map(Vec<(String, MetaValue)>),
+}
+
+impl MetaValue {
+    /// The value as a string slice, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            MetaValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value as a number, if it is one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            MetaValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The value as a boolean, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            MetaValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// The items of a list value.
+    pub fn as_list(&self) -> Option<&[MetaValue]> {
+        match *self {
+            MetaValue::List(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The entries of a map value.
+    pub fn as_map(&self) -> Option<&[(String, MetaValue)]> {
+        match *self {
+            MetaValue::Map(ref entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MetaValue {
+    /// Formats the value as plain text: lists and maps are joined with `, `.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MetaValue::String(ref s) => f.write_str(s),
+            MetaValue::Number(n) => write!(f, "{}", n),
+            MetaValue::Bool(b) => write!(f, "{}", b),
+            MetaValue::List(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            MetaValue::Map(ref entries) => {
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The details of a link's destination, or an image's source.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct NodeLink {
     /// The URL for the link destination or image source.
     pub url: Vec<u8>,
@@ -204,6 +297,98 @@ pub struct NodeLink {
     /// Note this field is used for the `title` attribute by the HTML formatter even for images;
     /// `alt` text is supplied in the image inline text.
     pub title: Vec<u8>,
+
+    /// Sizing and layout hints of an image, given in its alt text.  Only filled with the
+    /// `image_attributes` extension; always empty for links.
+    pub attributes: ImageAttributes,
+}
+
+/// Sizing and layout hints of an image, written in its alt text as comma-separated `key:value`
+/// pairs, such as `width:200,height:300,layout:center,filter:blur`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImageAttributes {
+    /// The `width` (or `w`) in pixels.
+    pub width: Option<u32>,
+
+    /// The `height` (or `h`) in pixels.
+    pub height: Option<u32>,
+
+    /// The `layout` of the image on its slide.
+    pub layout: Option<ImageLayout>,
+
+    /// The CSS filters to apply, one per `filter` key, in source order.
+    pub filters: Vec<ImageFilter>,
+}
+
+impl ImageAttributes {
+    /// Whether no hints were given.
+    pub fn is_empty(&self) -> bool {
+        *self == ImageAttributes::default()
+    }
+}
+
+impl fmt::Display for ImageAttributes {
+    /// Writes the hints in the alt text syntax.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut hints = vec![];
+        if let Some(width) = self.width {
+            hints.push(format!("width:{}", width));
+        }
+        if let Some(height) = self.height {
+            hints.push(format!("height:{}", height));
+        }
+        if let Some(layout) = self.layout {
+            hints.push(format!("layout:{}", layout.as_str()));
+        }
+        for filter in &self.filters {
+            hints.push(format!("filter:{}", filter));
+        }
+        write!(f, "{}", hints.join(","))
+    }
+}
+
+/// The placement of an image on its slide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageLayout {
+    /// Floated to the left.
+    Left,
+
+    /// Centred.
+    Center,
+
+    /// Floated to the right.
+    Right,
+
+    /// Filling the slide.
+    Fill,
+}
+
+impl ImageLayout {
+    /// The name of the layout, as written in the alt text.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageLayout::Left => "left",
+            ImageLayout::Center => "center",
+            ImageLayout::Right => "right",
+            ImageLayout::Fill => "fill",
+        }
+    }
+}
+
+/// A CSS filter function, such as `blur(10px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageFilter {
+    /// The name of the function, such as `blur`.
+    pub function: String,
+
+    /// The argument of the function, such as `10px`.
+    pub argument: String,
+}
+
+impl fmt::Display for ImageFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", self.function, self.argument)
+    }
 }
 
 /// The metadata of a list; the kind of list, the delimiter used and so on.
@@ -291,19 +476,39 @@ pub struct NodeSlideMetaDataBlock {
     /// any kind.
     pub literal: Vec<u8>,
 
-    /// metadata
+    /// The entries of the block, in source order.  The same entries are the block's `KV`
+    /// children.
     pub metadatas: Vec<NodeKV>,
 }
 
-/// The metadata and data of a code block (fenced or indented).
+/// The details of an `::effect[name: key=value, ...]` directive.
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct EffectAttr {
-    /// The literal contents of the code block.  As the contents are not interpreted as Markdown at
-    /// all, they are contained within this structure, rather than inserted into a child inline of
-    /// any kind.
+    /// The literal text between the brackets, as written.
     pub literal: Vec<u8>,
-    // /// metadata
-    // pub metadatas: Vec<NodeKV>,
+
+    /// The name of the effect.
+    pub name: String,
+
+    /// The parameters, in source order.  Positional parameters have an empty key.
+    pub params: Vec<(String, String)>,
+
+    /// The element the effect applies to, from the `target` parameter.
+    pub target: Option<String>,
+
+    /// Why the directive could not be parsed, if it is malformed.  Malformed effects are not
+    /// rendered.
+    pub error: Option<String>,
+}
+
+impl EffectAttr {
+    /// The value of the parameter with the given key.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
 }
 
 /// The metadata and data of a code block (fenced or indented).
@@ -429,10 +634,29 @@ pub struct Ast {
     /// The node value itself.
     pub value: NodeValue,
 
-    /// The line in the input document the node starts at.
+    /// The line in the input document the node starts at, counting from 1.  Nodes which do not
+    /// come from the input, such as those created with `Ast::new`, have no position: their
+    /// lines and columns are 0.
     pub start_line: u32,
 
+    /// The column the node starts at, in bytes counting from 1.
+    pub start_column: usize,
+
+    /// The line the node ends at.
+    pub end_line: u32,
+
+    /// The column of the node's last byte.  A node ending at the start of a line, such as an
+    /// empty list item, has an end column of 0.
+    pub end_column: usize,
+
+    /// The index of the fragment the node is revealed in, within its slide.  Assigned by
+    /// `slides::build_deck`; `None` if the node is shown with the slide.
+    pub fragment: Option<usize>,
+
     pub(crate) content: Vec<u8>,
+    /// The byte offset in the input of the start of each line of `content`.  For the document,
+    /// the offset of each line of the input, followed by the length of the input.
+    pub(crate) line_offsets: Vec<usize>,
     pub(crate) open: bool,
     pub(crate) last_line_blank: bool,
 }
@@ -443,7 +667,12 @@ impl Ast {
         Ast {
             value,
             content: vec![],
+            line_offsets: vec![],
             start_line: 0,
+            start_column: 0,
+            end_line: 0,
+            end_column: 0,
+            fragment: None,
             open: true,
             last_line_blank: false,
         }
@@ -494,9 +723,10 @@ pub(crate) fn can_contain_type<'a>(node: &'a AstNode<'a>, child: &NodeValue) ->
         | NodeValue::FootnoteDefinition(_)
         | NodeValue::DescriptionTerm
         | NodeValue::DescriptionDetails
-        | NodeValue::SlideMetaDataBlock(..)
         | NodeValue::Item(..) => child.block() && !matches!(*child, NodeValue::Item(..)),
 
+        NodeValue::SlideMetaDataBlock(..) => matches!(*child, NodeValue::KV(..)),
+
         NodeValue::List(..) => matches!(*child, NodeValue::Item(..)),
 
         NodeValue::DescriptionList => matches!(*child, NodeValue::DescriptionItem(_)),
@@ -0,0 +1,210 @@
+//! OpenDocument presentation output: a deck written as an ODF package, as LibreOffice Impress
+//! reads and writes.
+
+mod page;
+
+use self::page::{PageWriter, Styles};
+use nodes::AstNode;
+use package::content::SlideContent;
+use package::layout::{centimetres, notes_rects, Rect, NOTES_SIZE};
+use package::zip::ZipWriter;
+use package::{deck_theme, theme_color, theme_font, xml_escape, ImageFormat};
+use parser::ComrakOptions;
+use slides::{build_deck, Deck};
+use std::io::{self, Write};
+use themes::Theme;
+
+const STYLES_XML: &str = include_str!("styles.xml");
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// The namespaces of the package's XML documents.
+const NAMESPACES: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+                          xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+                          xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+                          xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+                          xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" \
+                          xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+                          xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                          xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+                          xmlns:meta=\"urn:oasis:names:tc:opendocument:xmlns:meta:1.0\" \
+                          xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" \
+                          xmlns:presentation=\"urn:oasis:names:tc:opendocument:xmlns:presentation:1.0\"";
+
+const MIME_TYPE: &str = "application/vnd.oasis.opendocument.presentation";
+
+/// Formats an AST as an OpenDocument presentation, with one page per slide of the deck.
+///
+/// The deck is split and laid out as by `format_pptx`: each slide's first heading is its title,
+/// lists, tables and images from files within `options.render.base_dir` go in its outline, and speaker notes go on its
+/// notes page.  Pages are named by the slide's anchor, such as `slide-2`, so that links to
+/// slides of the deck keep working.  Colours and fonts are taken from the deck's theme.
+///
+/// The package is written as a ZIP archive to `output`, which should expect binary data.
+///
+/// ```
+/// # use comrak::{format_odp, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Hello\n\n## World\n\n- one\n- two\n", &options);
+/// let mut odp = vec![];
+/// format_odp(root, &options, &mut odp).unwrap();
+///
+/// assert_eq!(&odp[30..38], b"mimetype");
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let theme = deck_theme(&deck, options)?;
+    let size = Rect::slide(deck.metadata.aspect_ratio.unwrap_or_default());
+    let notes = notes_rects(size);
+
+    let slides = deck
+        .cover
+        .iter()
+        .chain(deck.slides.iter())
+        .collect::<Vec<_>>();
+    let mut styles = Styles::default();
+    let mut media = vec![];
+    let mut pages = String::new();
+    for (ix, slide) in slides.iter().enumerate() {
+        let content = SlideContent::new(slide, &deck.agenda, options);
+        let mut writer = PageWriter::new(&mut styles, &mut media, slides.len());
+        pages += &writer.page(&content, slide.layout, ix + 1, size, notes);
+    }
+
+    let content = format!(
+        "{}<office:document-content {} office:version=\"1.2\">\n\
+         <office:automatic-styles>\n{}</office:automatic-styles>\n\
+         <office:body><office:presentation>\n{}</office:presentation></office:body>\n\
+         </office:document-content>\n",
+        XML_DECLARATION,
+        NAMESPACES,
+        styles.xml(),
+        pages
+    );
+
+    let mut zip = ZipWriter::new(output);
+    zip.add("mimetype", MIME_TYPE.as_bytes())?;
+    zip.add("content.xml", content.as_bytes())?;
+    zip.add("styles.xml", styles_xml(&deck, &theme, size).as_bytes())?;
+    zip.add("meta.xml", meta_xml(&deck).as_bytes())?;
+    for (name, data) in &media {
+        zip.add(&format!("Pictures/{}", name), data)?;
+    }
+    zip.add("META-INF/manifest.xml", manifest_xml(&media).as_bytes())?;
+    zip.finish()
+}
+
+fn styles_xml(deck: &Deck, theme: &Theme, size: Rect) -> String {
+    let font = |role, fallback: &str| {
+        let family = theme_font(theme, role).unwrap_or_else(|| fallback.to_string());
+        xml_escape(&format!("'{}'", family))
+    };
+    let (thumbnail, notes) = notes_rects(size);
+    let position = |rect: Rect| {
+        format!(
+            "svg:x=\"{}\" svg:y=\"{}\" svg:width=\"{}\" svg:height=\"{}\"",
+            centimetres(rect.x),
+            centimetres(rect.y),
+            centimetres(rect.width),
+            centimetres(rect.height)
+        )
+    };
+
+    // The language, such as `en-GB`, sets the language and country of the default style.
+    let mut language = String::new();
+    if let Some(ref tag) = deck.metadata.language {
+        let mut parts = tag.splitn(2, ['-', '_']);
+        if let Some(code) = parts.next() {
+            language += &format!(" fo:language=\"{}\"", xml_escape(code));
+        }
+        if let Some(country) = parts.next() {
+            language += &format!(" fo:country=\"{}\"", xml_escape(country));
+        }
+    }
+
+    STYLES_XML
+        .replace("{{namespaces}}", NAMESPACES)
+        .replace("{{body_font}}", &font("body", "Liberation Sans"))
+        .replace("{{heading_font}}", &font("heading", "Liberation Sans"))
+        .replace("{{code_font}}", &font("code", "Liberation Mono"))
+        .replace(
+            "{{foreground}}",
+            &format!("#{}", theme_color(theme, "foreground")),
+        )
+        .replace(
+            "{{heading}}",
+            &format!("#{}", theme_color(theme, "heading")),
+        )
+        .replace(
+            "{{background}}",
+            &format!("#{}", theme_color(theme, "background")),
+        )
+        .replace("{{language}}", &language)
+        .replace("{{page_width}}", &centimetres(size.width))
+        .replace("{{page_height}}", &centimetres(size.height))
+        .replace("{{notes_width}}", &centimetres(NOTES_SIZE.width))
+        .replace("{{notes_height}}", &centimetres(NOTES_SIZE.height))
+        .replace("{{thumbnail}}", &position(thumbnail))
+        .replace("{{notes}}", &position(notes))
+}
+
+fn meta_xml(deck: &Deck) -> String {
+    let mut meta = String::from("<meta:generator>comrak</meta:generator>");
+    let title = deck
+        .metadata
+        .title
+        .as_deref()
+        .or_else(|| deck.cover.as_ref().map(|c| c.title.as_str()))
+        .filter(|t| !t.is_empty());
+    if let Some(title) = title {
+        meta += &format!("<dc:title>{}</dc:title>", xml_escape(title));
+    }
+    if let Some(ref author) = deck.metadata.author {
+        meta += &format!(
+            "<meta:initial-creator>{}</meta:initial-creator><dc:creator>{}</dc:creator>",
+            xml_escape(author),
+            xml_escape(author)
+        );
+    }
+    if let Some(ref language) = deck.metadata.language {
+        meta += &format!("<dc:language>{}</dc:language>", xml_escape(language));
+    }
+    format!(
+        "{}<office:document-meta {} office:version=\"1.2\"><office:meta>{}</office:meta></office:document-meta>\n",
+        XML_DECLARATION, NAMESPACES, meta
+    )
+}
+
+fn manifest_xml(media: &[(String, Vec<u8>)]) -> String {
+    let mut entries = format!(
+        "<manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.2\" manifest:media-type=\"{}\"/>\n",
+        MIME_TYPE
+    );
+    for name in &["content.xml", "styles.xml", "meta.xml"] {
+        entries += &format!(
+            "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"text/xml\"/>\n",
+            name
+        );
+    }
+    for (name, _) in media {
+        let format = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Gif]
+            .iter()
+            .find(|format| name.ends_with(&format!(".{}", format.extension())))
+            .cloned()
+            .unwrap_or(ImageFormat::Png);
+        entries += &format!(
+            "<manifest:file-entry manifest:full-path=\"Pictures/{}\" manifest:media-type=\"{}\"/>\n",
+            name,
+            format.mime_type()
+        );
+    }
+    format!(
+        "{}<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\n{}</manifest:manifest>\n",
+        XML_DECLARATION, entries
+    )
+}
@@ -0,0 +1,429 @@
+//! The XML of presentation pages: a slide's content laid out as frames.
+
+use nodes::TableAlignment;
+use package::content::{
+    Block, Marker, Paragraph, ParagraphKind, Picture, Run, RunStyle, SlideContent, Table,
+};
+use package::layout::{
+    centimetres, picture_rect, region_rects, table_row_height, title_rect, Rect, RegionLayout,
+};
+use package::xml_escape;
+use slides::Layout;
+
+/// The automatic styles of `content.xml` which do not depend on the content.
+const FIXED_STYLES: &str = include_str!("content_styles.xml");
+
+/// The formatting of a run which its text style carries.  Links are `text:a` elements instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TextStyle {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    strike: bool,
+    superscript: bool,
+}
+
+impl TextStyle {
+    fn new(style: &RunStyle) -> Self {
+        TextStyle {
+            bold: style.bold,
+            italic: style.italic,
+            code: style.code,
+            strike: style.strike,
+            superscript: style.superscript,
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        !(self.bold || self.italic || self.code || self.strike || self.superscript)
+    }
+}
+
+/// The automatic styles pages refer to, collected as they are needed.
+#[derive(Default)]
+pub struct Styles {
+    /// The text styles, named `T1`, `T2` and so on.
+    text: Vec<TextStyle>,
+
+    /// The column widths and row heights of tables, whose styles are named `co1` and `ro1`,
+    /// `co2` and `ro2` and so on.
+    tables: Vec<(i64, i64)>,
+}
+
+impl Styles {
+    /// The name of the text style for `style`.
+    fn text(&mut self, style: TextStyle) -> String {
+        let ix = match self.text.iter().position(|&s| s == style) {
+            Some(ix) => ix,
+            None => {
+                self.text.push(style);
+                self.text.len() - 1
+            }
+        };
+        format!("T{}", ix + 1)
+    }
+
+    /// The automatic styles, as the content of `office:automatic-styles`.
+    pub fn xml(&self) -> String {
+        let mut xml = String::from(FIXED_STYLES);
+        for (ix, style) in self.text.iter().enumerate() {
+            let mut properties = String::new();
+            if style.bold {
+                properties += " fo:font-weight=\"bold\"";
+            }
+            if style.italic {
+                properties += " fo:font-style=\"italic\"";
+            }
+            if style.code {
+                properties += " style:font-name=\"Code\"";
+            }
+            if style.strike {
+                properties += " style:text-line-through-style=\"solid\"";
+            }
+            if style.superscript {
+                properties += " style:text-position=\"super 58%\"";
+            }
+            xml += &format!(
+                "<style:style style:name=\"T{}\" style:family=\"text\"><style:text-properties{}/></style:style>\n",
+                ix + 1,
+                properties
+            );
+        }
+        for (ix, (width, height)) in self.tables.iter().enumerate() {
+            xml += &format!(
+                "<style:style style:name=\"co{}\" style:family=\"table-column\"><style:table-column-properties style:column-width=\"{}\"/></style:style>\n\
+                 <style:style style:name=\"ro{}\" style:family=\"table-row\"><style:table-row-properties style:row-height=\"{}\"/></style:style>\n",
+                ix + 1,
+                centimetres(*width),
+                ix + 1,
+                centimetres(*height)
+            );
+        }
+        xml
+    }
+}
+
+/// Writes the XML of one page, collecting the styles and media it needs.
+pub struct PageWriter<'s> {
+    styles: &'s mut Styles,
+
+    /// The images of the whole presentation, by file name within `Pictures`.
+    media: &'s mut Vec<(String, Vec<u8>)>,
+
+    slides: usize,
+}
+
+impl<'s> PageWriter<'s> {
+    pub fn new(
+        styles: &'s mut Styles,
+        media: &'s mut Vec<(String, Vec<u8>)>,
+        slides: usize,
+    ) -> Self {
+        PageWriter {
+            styles,
+            media,
+            slides,
+        }
+    }
+
+    /// The `draw:page` of slide `number`, named by the slide's anchor so that `#slide-N`
+    /// links reach it.  Its notes page, if it has notes, shows them under `notes`.
+    pub fn page(
+        &mut self,
+        content: &SlideContent,
+        layout: Option<Layout>,
+        number: usize,
+        size: Rect,
+        (thumbnail, notes): (Rect, Rect),
+    ) -> String {
+        let mut xml = format!(
+            "<draw:page draw:name=\"slide-{}\" draw:master-page-name=\"Default\">\n",
+            number
+        );
+        if !content.title.is_empty() {
+            xml += &self.title(&content.title, layout, size);
+        }
+        let frames = region_rects(layout, size, content.regions.len());
+        for (blocks, frame) in content.regions.iter().zip(frames) {
+            xml += &self.region(blocks, frame);
+        }
+
+        if !content.notes.is_empty() {
+            let mut paragraphs = String::new();
+            for line in content
+                .notes
+                .iter()
+                .flat_map(|note| note.trim_end().lines())
+            {
+                paragraphs += &format!("<text:p>{}</text:p>", text(line, &mut true));
+            }
+            xml += &format!(
+                "<presentation:notes>\
+                 <draw:page-thumbnail presentation:class=\"page\" draw:page-number=\"{}\" {}/>\
+                 <draw:frame presentation:style-name=\"Default-notes\" presentation:class=\"notes\" {}>\
+                 <draw:text-box>{}</draw:text-box></draw:frame></presentation:notes>\n",
+                number,
+                position(thumbnail),
+                position(notes),
+                paragraphs
+            );
+        }
+        xml + "</draw:page>\n"
+    }
+
+    fn title(&mut self, title: &str, layout: Option<Layout>, size: Rect) -> String {
+        let style = match layout {
+            Some(Layout::Title) | Some(Layout::Section) => " text:style-name=\"Pcenter\"",
+            _ => "",
+        };
+        format!(
+            "<draw:frame presentation:style-name=\"Default-title\" presentation:class=\"title\" {}>\
+             <draw:text-box><text:p{}>{}</text:p></draw:text-box></draw:frame>\n",
+            position(title_rect(layout, size)),
+            style,
+            text(title, &mut true)
+        )
+    }
+
+    fn region(&mut self, blocks: &[Block], frame: Rect) -> String {
+        let region = RegionLayout::new(blocks, frame);
+        let mut xml = String::new();
+        if !region.paragraphs.is_empty() {
+            let mut body = String::new();
+            for paragraph in &region.paragraphs {
+                body += &self.paragraph(paragraph);
+            }
+            xml += &format!(
+                "<draw:frame presentation:style-name=\"Default-outline1\" presentation:class=\"outline\" {}>\
+                 <draw:text-box>{}</draw:text-box></draw:frame>\n",
+                position(region.text),
+                body
+            );
+        }
+        for (block, rect) in region.objects {
+            match block {
+                Block::Table(table) => xml += &self.table(table, rect),
+                Block::Picture(picture) => xml += &self.picture(picture, rect),
+                Block::Paragraph(_) => (),
+            }
+        }
+        xml
+    }
+
+    /// A paragraph, within a list for each level of nesting.  A list item's later paragraphs
+    /// are in list headers, which have no label.
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String {
+        let style = match paragraph.kind {
+            ParagraphKind::Heading(_) => "Pheading",
+            ParagraphKind::Code => "Pcode",
+            ParagraphKind::Quote if paragraph.level == 0 => "Pquote",
+            _ if paragraph.level <= 1 => "P1",
+            _ if paragraph.level == 2 => "P2",
+            _ => "P3",
+        };
+        let xml = format!(
+            "<text:p text:style-name=\"{}\">{}</text:p>",
+            style,
+            self.runs(&paragraph.runs, false)
+        );
+        if paragraph.level == 0 {
+            return xml;
+        }
+
+        let list_style = match paragraph.marker {
+            Marker::Number(_) => "LN",
+            _ => "LB",
+        };
+        let mut item = match paragraph.marker {
+            Marker::None => format!("<text:list-header>{}</text:list-header>", xml),
+            Marker::Bullet => format!("<text:list-item>{}</text:list-item>", xml),
+            Marker::Number(number) => format!(
+                "<text:list-item text:start-value=\"{}\">{}</text:list-item>",
+                number, xml
+            ),
+        };
+        for _ in 1..paragraph.level {
+            item = format!(
+                "<text:list-item><text:list>{}</text:list></text:list-item>",
+                item
+            );
+        }
+        format!(
+            "<text:list text:style-name=\"{}\">{}</text:list>",
+            list_style, item
+        )
+    }
+
+    /// The runs of a paragraph, all bold if `bold`.
+    fn runs(&mut self, runs: &[Run], bold: bool) -> String {
+        let mut xml = String::new();
+        let mut after_space = true;
+        for run in runs {
+            match run {
+                Run::Break => {
+                    xml += "<text:line-break/>";
+                    after_space = true;
+                }
+                Run::Text(content, style) => {
+                    let mut text_style = TextStyle::new(style);
+                    text_style.bold |= bold;
+                    let mut span = text(content, &mut after_space);
+                    if !text_style.is_plain() {
+                        span = format!(
+                            "<text:span text:style-name=\"{}\">{}</text:span>",
+                            self.styles.text(text_style),
+                            span
+                        );
+                    }
+                    match style.link.as_deref().and_then(|l| self.link_target(l)) {
+                        Some(target) => {
+                            xml += &format!(
+                                "<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>",
+                                xml_escape(target),
+                                span
+                            )
+                        }
+                        None => xml += &span,
+                    }
+                }
+            }
+        }
+        xml
+    }
+
+    /// Where a link goes: a URL, or `#slide-N` for a slide of the deck, which is the name of its
+    /// page.  Other links within the deck are dropped.
+    fn link_target<'l>(&self, link: &'l str) -> Option<&'l str> {
+        match link.strip_prefix('#') {
+            Some(anchor) => {
+                let number = anchor.strip_prefix("slide-")?.parse::<usize>().ok()?;
+                if number >= 1 && number <= self.slides {
+                    Some(link)
+                } else {
+                    None
+                }
+            }
+            None => Some(link),
+        }
+    }
+
+    fn table(&mut self, table: &Table, rect: Rect) -> String {
+        let columns = table
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain(Some(table.alignments.len()))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let width = rect.width / columns as i64;
+        let height = table_row_height(rect, table.rows.len());
+        self.styles.tables.push((width, height));
+        let number = self.styles.tables.len();
+
+        let mut rows = String::new();
+        for (ix, row) in table.rows.iter().enumerate() {
+            rows += &format!("<table:table-row table:style-name=\"ro{}\">", number);
+            for column in 0..columns {
+                let style = match table.alignments.get(column) {
+                    Some(TableAlignment::Center) => "Ptable-center",
+                    Some(TableAlignment::Right) => "Ptable-right",
+                    _ => "Ptable",
+                };
+                let runs = row.get(column).map_or(&[][..], Vec::as_slice);
+                rows += &format!(
+                    "<table:table-cell table:style-name=\"ce1\" office:value-type=\"string\">\
+                     <text:p text:style-name=\"{}\">{}</text:p></table:table-cell>",
+                    style,
+                    self.runs(runs, ix == 0)
+                );
+            }
+            rows += "</table:table-row>";
+        }
+
+        format!(
+            "<draw:frame draw:name=\"Table {}\" svg:x=\"{}\" svg:y=\"{}\" svg:width=\"{}\" svg:height=\"{}\">\
+             <table:table table:name=\"Table{}\">\
+             <table:table-column table:style-name=\"co{}\" table:number-columns-repeated=\"{}\"/>{}\
+             </table:table></draw:frame>\n",
+            number,
+            centimetres(rect.x),
+            centimetres(rect.y),
+            centimetres(width * columns as i64),
+            centimetres(height * table.rows.len() as i64),
+            number,
+            number,
+            columns,
+            rows
+        )
+    }
+
+    /// A picture, placed within `rect`.
+    fn picture(&mut self, picture: &Picture, rect: Rect) -> String {
+        let name = format!(
+            "image{}.{}",
+            self.media.len() + 1,
+            picture.image.format.extension()
+        );
+        let xml = format!(
+            "<draw:frame draw:style-name=\"Picture\" draw:name=\"{}\" {}>\
+             <draw:image xlink:href=\"Pictures/{}\" xlink:type=\"simple\" xlink:show=\"embed\" xlink:actuate=\"onLoad\"/>\
+             <svg:desc>{}</svg:desc></draw:frame>\n",
+            name,
+            position(picture_rect(picture, rect)),
+            name,
+            xml_escape(&picture.alt)
+        );
+        self.media.push((name, picture.image.data.clone()));
+        xml
+    }
+}
+
+/// The `svg:x`, `svg:y`, `svg:width` and `svg:height` attributes placing a frame at `rect`.
+fn position(rect: Rect) -> String {
+    format!(
+        "svg:x=\"{}\" svg:y=\"{}\" svg:width=\"{}\" svg:height=\"{}\"",
+        centimetres(rect.x),
+        centimetres(rect.y),
+        centimetres(rect.width),
+        centimetres(rect.height)
+    )
+}
+
+/// Text for a paragraph, escaped for XML, with tabs, line breaks, and the spaces XML would
+/// collapse written as elements.  `after_space` says whether the text follows a space or the
+/// start of the paragraph, and is updated for the text which follows.
+fn text(s: &str, after_space: &mut bool) -> String {
+    let mut xml = String::with_capacity(s.len());
+    let mut spaces = 0;
+    for c in s.chars() {
+        if c == ' ' {
+            if *after_space {
+                spaces += 1;
+            } else {
+                xml.push(' ');
+                *after_space = true;
+            }
+            continue;
+        }
+
+        match spaces {
+            0 => (),
+            1 => xml += "<text:s/>",
+            n => xml += &format!("<text:s text:c=\"{}\"/>", n),
+        }
+        spaces = 0;
+        match c {
+            '\t' => xml += "<text:tab/>",
+            '\n' => xml += "<text:line-break/>",
+            c => xml += &xml_escape(c.encode_utf8(&mut [0; 4])),
+        }
+        *after_space = c == '\n';
+    }
+    match spaces {
+        0 => (),
+        1 => xml += "<text:s/>",
+        n => xml += &format!("<text:s text:c=\"{}\"/>", n),
+    }
+    xml
+}
@@ -0,0 +1,401 @@
+//! A format-neutral view of a slide's content for the package exporters: paragraphs of styled
+//! runs, tables and images, grouped by region.
+
+use super::LocalImage;
+use nodes::{AstNode, ListType, NodeCode, NodeValue, TableAlignment};
+use parser::ComrakOptions;
+use scanners;
+use slides::{AgendaEntry, Slide};
+use std::ptr;
+
+/// A slide's title, content and speaker notes.
+pub struct SlideContent {
+    pub title: String,
+
+    /// The blocks of each of the slide's regions.  The heading the title was taken from is
+    /// left out.
+    pub regions: Vec<Vec<Block>>,
+
+    pub notes: Vec<String>,
+}
+
+pub enum Block {
+    Paragraph(Paragraph),
+    Table(Table),
+    Picture(Picture),
+}
+
+pub struct Paragraph {
+    pub runs: Vec<Run>,
+
+    /// The depth of list nesting, or 0 outside lists.
+    pub level: usize,
+
+    pub marker: Marker,
+    pub kind: ParagraphKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    None,
+    Bullet,
+
+    /// An ordered list item, with its number.
+    Number(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParagraphKind {
+    Body,
+    Heading(u32),
+
+    /// A line of a code block.
+    Code,
+
+    Quote,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Run {
+    Text(String, RunStyle),
+    Break,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub strike: bool,
+    pub superscript: bool,
+
+    /// The target of a link: a URL, or `#slide-N` for a slide of the deck.
+    pub link: Option<String>,
+}
+
+/// A table.  The first row is the header.
+pub struct Table {
+    pub alignments: Vec<TableAlignment>,
+    pub rows: Vec<Vec<Vec<Run>>>,
+}
+
+pub struct Picture {
+    pub image: LocalImage,
+    pub alt: String,
+
+    /// The width in pixels from the image's hints, if any.
+    pub width: Option<u32>,
+
+    /// The height in pixels from the image's hints, if any.
+    pub height: Option<u32>,
+}
+
+impl Picture {
+    /// The size to show the picture at in pixels: as hinted, or else its natural size, keeping
+    /// its aspect ratio when only one dimension is hinted.
+    pub fn size(&self) -> (u32, u32) {
+        let (w, h) = (u64::from(self.image.width), u64::from(self.image.height));
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, (u64::from(width) * h / w) as u32),
+            (None, Some(height)) => ((u64::from(height) * w / h) as u32, height),
+            (None, None) => (self.image.width, self.image.height),
+        }
+    }
+}
+
+impl SlideContent {
+    /// The content of `slide`.  Images are read from files within `options.render.base_dir`;
+    /// others are replaced by their alt text.
+    pub fn new<'a>(slide: &Slide<'a>, agenda: &[AgendaEntry], options: &ComrakOptions) -> Self {
+        let title_node = slide
+            .nodes
+            .iter()
+            .cloned()
+            .find(|n| matches!(n.data.borrow().value, NodeValue::Heading(..)));
+
+        let mut regions = slide
+            .regions
+            .iter()
+            .map(|region| {
+                let mut builder = Builder {
+                    options,
+                    blocks: vec![],
+                    marker: None,
+                };
+                for &node in &region.nodes {
+                    if !title_node.is_some_and(|t| ptr::eq(t, node)) {
+                        builder.block(node, 0, false);
+                    }
+                }
+                builder.blocks
+            })
+            .collect::<Vec<_>>();
+
+        if slide.agenda {
+            let blocks = regions.last_mut().unwrap();
+            for entry in agenda {
+                let style = RunStyle {
+                    bold: slide
+                        .section
+                        .is_some_and(|s| agenda[s].slide == entry.slide),
+                    link: Some(format!("#{}", entry.anchor)),
+                    ..RunStyle::default()
+                };
+                blocks.push(Block::Paragraph(Paragraph {
+                    runs: vec![Run::Text(entry.title.clone(), style)],
+                    level: entry.level as usize,
+                    marker: Marker::Bullet,
+                    kind: ParagraphKind::Body,
+                }));
+            }
+        }
+
+        SlideContent {
+            title: slide.title.clone(),
+            regions,
+            notes: slide.notes.clone(),
+        }
+    }
+}
+
+struct Builder<'o> {
+    options: &'o ComrakOptions,
+    blocks: Vec<Block>,
+
+    /// The list marker for the next paragraph, which is the first of a list item.
+    marker: Option<Marker>,
+}
+
+impl<'o> Builder<'o> {
+    fn block<'a>(&mut self, node: &'a AstNode<'a>, level: usize, quote: bool) {
+        match node.data.borrow().value {
+            NodeValue::Paragraph => {
+                let kind = if quote {
+                    ParagraphKind::Quote
+                } else {
+                    ParagraphKind::Body
+                };
+                self.paragraph(node, level, kind);
+            }
+            NodeValue::Heading(ref nh) => {
+                self.paragraph(node, level, ParagraphKind::Heading(nh.level))
+            }
+            NodeValue::List(ref nl) => {
+                for (ix, item) in node.children().enumerate() {
+                    self.marker = Some(match nl.list_type {
+                        ListType::Bullet => Marker::Bullet,
+                        ListType::Ordered => Marker::Number(nl.start + ix),
+                    });
+                    for child in item.children() {
+                        self.block(child, level + 1, quote);
+                    }
+                    self.marker = None;
+                }
+            }
+            NodeValue::CodeBlock(ref ncb) => {
+                let literal = String::from_utf8_lossy(&ncb.literal);
+                let style = RunStyle {
+                    code: true,
+                    ..RunStyle::default()
+                };
+                for line in literal.trim_end_matches('\n').split('\n') {
+                    let runs = vec![Run::Text(line.to_string(), style.clone())];
+                    self.push_paragraph(runs, level, ParagraphKind::Code);
+                }
+            }
+            NodeValue::BlockQuote => {
+                for child in node.children() {
+                    self.block(child, level, true);
+                }
+            }
+            NodeValue::Table(ref alignments) => {
+                let rows = node
+                    .children()
+                    .map(|row| {
+                        row.children()
+                            .map(|cell| self.inlines(cell, &RunStyle::default(), &mut vec![]))
+                            .collect()
+                    })
+                    .collect();
+                self.blocks.push(Block::Table(Table {
+                    alignments: alignments.clone(),
+                    rows,
+                }));
+            }
+            NodeValue::DescriptionList => {
+                for item in node.children() {
+                    for part in item.children() {
+                        match part.data.borrow().value {
+                            NodeValue::DescriptionTerm => {
+                                let style = RunStyle {
+                                    bold: true,
+                                    ..RunStyle::default()
+                                };
+                                for child in part.children() {
+                                    let mut pictures = vec![];
+                                    let runs = self.inlines(child, &style, &mut pictures);
+                                    self.push_paragraph(runs, level, ParagraphKind::Body);
+                                }
+                            }
+                            _ => {
+                                for child in part.children() {
+                                    self.block(child, level + 1, quote);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            NodeValue::FootnoteDefinition(_) => {
+                for child in node.children() {
+                    self.block(child, level, quote);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// A paragraph of inline content, followed by any pictures within it.
+    fn paragraph<'a>(&mut self, node: &'a AstNode<'a>, level: usize, kind: ParagraphKind) {
+        let style = RunStyle {
+            bold: matches!(kind, ParagraphKind::Heading(_)),
+            italic: kind == ParagraphKind::Quote,
+            ..RunStyle::default()
+        };
+        let mut pictures = vec![];
+        let mut runs = self.inlines(node, &style, &mut pictures);
+        if let Some(Run::Text(text, _)) = runs.first_mut() {
+            *text = text.trim_start().to_string();
+        }
+        if let Some(Run::Text(text, _)) = runs.last_mut() {
+            *text = text.trim_end().to_string();
+        }
+        let blank = runs.iter().all(|run| match run {
+            Run::Text(text, _) => text.trim().is_empty(),
+            Run::Break => true,
+        });
+        if !blank {
+            self.push_paragraph(runs, level, kind);
+        }
+        self.blocks.extend(pictures.into_iter().map(Block::Picture));
+    }
+
+    fn push_paragraph(&mut self, runs: Vec<Run>, level: usize, kind: ParagraphKind) {
+        self.blocks.push(Block::Paragraph(Paragraph {
+            runs,
+            level,
+            marker: self.marker.take().unwrap_or(Marker::None),
+            kind,
+        }));
+    }
+
+    /// The styled text within `node`.  Local images are collected into `pictures`; other
+    /// images are replaced by their alt text.
+    fn inlines<'a>(
+        &self,
+        node: &'a AstNode<'a>,
+        style: &RunStyle,
+        pictures: &mut Vec<Picture>,
+    ) -> Vec<Run> {
+        let mut runs = vec![];
+        for child in node.children() {
+            self.inline(child, style, &mut runs, pictures);
+        }
+        runs
+    }
+
+    fn inline<'a>(
+        &self,
+        node: &'a AstNode<'a>,
+        style: &RunStyle,
+        runs: &mut Vec<Run>,
+        pictures: &mut Vec<Picture>,
+    ) {
+        let mut style = style.clone();
+        match node.data.borrow().value {
+            NodeValue::Text(ref literal) => {
+                push_text(runs, &String::from_utf8_lossy(literal), &style)
+            }
+            NodeValue::Code(NodeCode { ref literal, .. }) => {
+                style.code = true;
+                push_text(runs, &String::from_utf8_lossy(literal), &style);
+            }
+            NodeValue::SoftBreak if self.options.render.hardbreaks => runs.push(Run::Break),
+            NodeValue::SoftBreak => push_text(runs, " ", &style),
+            NodeValue::LineBreak => runs.push(Run::Break),
+            NodeValue::TaskItem(checked) => push_text(
+                runs,
+                if checked { "\u{2611} " } else { "\u{2610} " },
+                &style,
+            ),
+            NodeValue::FootnoteReference(ref name) => {
+                style.superscript = true;
+                push_text(runs, &String::from_utf8_lossy(name), &style);
+            }
+            NodeValue::Image(ref nl) => {
+                let url = String::from_utf8_lossy(&nl.url);
+                let base_dir = self.options.render.base_dir.as_deref();
+                match LocalImage::load(&url, base_dir) {
+                    Some(image) => {
+                        let mut alt = vec![];
+                        for child in node.children() {
+                            self.inline(child, &style, &mut alt, &mut vec![]);
+                        }
+                        pictures.push(Picture {
+                            image,
+                            alt: plain_text(&alt),
+                            width: nl.attributes.width,
+                            height: nl.attributes.height,
+                        });
+                    }
+                    None => {
+                        for child in node.children() {
+                            self.inline(child, &style, runs, pictures);
+                        }
+                    }
+                }
+            }
+            NodeValue::HtmlInline(_) | NodeValue::Effect(_) => (),
+            ref value => {
+                match *value {
+                    NodeValue::Emph => style.italic = true,
+                    NodeValue::Strong => style.bold = true,
+                    NodeValue::Strikethrough => style.strike = true,
+                    NodeValue::Superscript => style.superscript = true,
+                    NodeValue::Link(ref nl)
+                        if self.options.render.unsafe_
+                            || scanners::dangerous_url(&nl.url).is_none() =>
+                    {
+                        style.link = Some(String::from_utf8_lossy(&nl.url).into_owned());
+                    }
+                    _ => (),
+                }
+                for child in node.children() {
+                    self.inline(child, &style, runs, pictures);
+                }
+            }
+        }
+    }
+}
+
+/// Append text, merging it into the last run if that has the same style.
+fn push_text(runs: &mut Vec<Run>, text: &str, style: &RunStyle) {
+    if let Some(Run::Text(last, last_style)) = runs.last_mut() {
+        if last_style == style {
+            last.push_str(text);
+            return;
+        }
+    }
+    runs.push(Run::Text(text.to_string(), style.clone()));
+}
+
+/// The text of runs, without their styles.
+pub fn plain_text(runs: &[Run]) -> String {
+    runs.iter()
+        .map(|run| match run {
+            Run::Text(text, _) => text.as_str(),
+            Run::Break => " ",
+        })
+        .collect()
+}
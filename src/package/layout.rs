@@ -0,0 +1,209 @@
+//! Where the parts of an exported slide go.  Lengths are in EMUs (English Metric Units, 914400
+//! to the inch), as Office Open XML measures them.
+
+use super::content::{Block, Paragraph, Picture};
+use slides::{AspectRatio, Layout};
+
+/// The width of a slide, as PowerPoint's widescreen slides.  The height follows from the deck's
+/// aspect ratio.
+pub const SLIDE_WIDTH: i64 = 12_192_000;
+
+/// The margin around a slide's content: half an inch.
+pub const MARGIN: i64 = 457_200;
+
+/// The number of EMUs to the inch.
+pub const EMU_PER_INCH: i64 = 914_400;
+
+/// The number of EMUs to the centimetre.
+const EMU_PER_CM: f64 = 360_000.0;
+
+/// The size of an image pixel, at 96 pixels to the inch.
+const EMU_PER_PIXEL: i64 = EMU_PER_INCH / 96;
+
+/// The size of a notes page: US letter, portrait.
+pub const NOTES_SIZE: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 6_858_000,
+    height: 9_144_000,
+};
+
+/// A rectangle on a slide or page.
+#[derive(Debug, Clone, Copy)]
+pub struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Rect {
+    /// The whole of a slide with the given aspect ratio.
+    pub fn slide(ratio: AspectRatio) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width: SLIDE_WIDTH,
+            height: SLIDE_WIDTH * i64::from(ratio.height) / i64::from(ratio.width),
+        }
+    }
+
+    /// Split the rectangle into `count` columns, `gap` apart.
+    pub fn columns(self, count: usize, gap: i64) -> Vec<Rect> {
+        let count = count.max(1) as i64;
+        let width = (self.width - gap * (count - 1)) / count;
+        (0..count)
+            .map(|ix| Rect {
+                x: self.x + ix * (width + gap),
+                width,
+                ..self
+            })
+            .collect()
+    }
+
+    /// Split the rectangle into `count` rows, `gap` apart.
+    pub fn rows(self, count: usize, gap: i64) -> Vec<Rect> {
+        let count = count.max(1) as i64;
+        let height = (self.height - gap * (count - 1)) / count;
+        (0..count)
+            .map(|ix| Rect {
+                y: self.y + ix * (height + gap),
+                height,
+                ..self
+            })
+            .collect()
+    }
+}
+
+/// A length in EMUs as centimetres, such as `2.54cm`, to at most three decimal places.
+pub fn centimetres(emu: i64) -> String {
+    let s = format!("{:.3}", emu as f64 / EMU_PER_CM);
+    format!("{}cm", s.trim_end_matches('0').trim_end_matches('.'))
+}
+
+/// Where the slide image and the notes go on a notes page for slides of `size`: the image as
+/// wide as the notes, above them.
+pub fn notes_rects(size: Rect) -> (Rect, Rect) {
+    let margin = NOTES_SIZE.width / 10;
+    let image = Rect {
+        x: margin,
+        y: margin,
+        width: NOTES_SIZE.width - 2 * margin,
+        height: (NOTES_SIZE.width - 2 * margin) * size.height / size.width,
+    };
+    let top = image.y + image.height + margin / 2;
+    let notes = Rect {
+        x: margin,
+        y: top,
+        width: image.width,
+        height: NOTES_SIZE.height - top - margin,
+    };
+    (image, notes)
+}
+
+/// Where a slide's title goes.  Title and section slides have it in the middle of the slide.
+pub fn title_rect(layout: Option<Layout>, size: Rect) -> Rect {
+    match layout {
+        Some(Layout::Title) | Some(Layout::Section) => Rect {
+            x: MARGIN,
+            y: size.height * 3 / 10,
+            width: size.width - 2 * MARGIN,
+            height: size.height / 5,
+        },
+        _ => Rect {
+            x: MARGIN,
+            y: MARGIN / 2,
+            width: size.width - 2 * MARGIN,
+            height: size.height * 3 / 20,
+        },
+    }
+}
+
+/// Where a slide's content goes, below its title.
+pub fn body_rect(layout: Option<Layout>, size: Rect) -> Rect {
+    let title = title_rect(layout, size);
+    let top = title.y + title.height + MARGIN / 4;
+    Rect {
+        x: MARGIN,
+        y: top,
+        width: size.width - 2 * MARGIN,
+        height: size.height - top - MARGIN / 2,
+    }
+}
+
+/// Where each of `count` regions goes: side by side, or one above the other for the quote
+/// layout.
+pub fn region_rects(layout: Option<Layout>, size: Rect, count: usize) -> Vec<Rect> {
+    let body = body_rect(layout, size);
+    if layout == Some(Layout::Quote) {
+        body.rows(count, MARGIN / 4)
+    } else {
+        body.columns(count, MARGIN / 2)
+    }
+}
+
+/// A region's blocks, placed within it: its paragraphs together, beside its tables and
+/// pictures, which are stacked one above the other.
+pub struct RegionLayout<'b> {
+    pub paragraphs: Vec<&'b Paragraph>,
+    pub text: Rect,
+    pub objects: Vec<(&'b Block, Rect)>,
+}
+
+impl<'b> RegionLayout<'b> {
+    pub fn new(blocks: &'b [Block], frame: Rect) -> Self {
+        let paragraphs = blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Paragraph(p) => Some(p),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let objects = blocks
+            .iter()
+            .filter(|block| !matches!(block, Block::Paragraph(_)))
+            .collect::<Vec<_>>();
+
+        let (text, objects_frame) = if paragraphs.is_empty() || objects.is_empty() {
+            (frame, frame)
+        } else {
+            let halves = frame.columns(2, MARGIN / 2);
+            (halves[0], halves[1])
+        };
+        let rows = objects_frame.rows(objects.len(), MARGIN / 4);
+
+        RegionLayout {
+            paragraphs,
+            text,
+            objects: objects.into_iter().zip(rows).collect(),
+        }
+    }
+}
+
+/// Where a picture goes within `rect`: at its size, shrunk to fit if need be, and centred
+/// across it.
+pub fn picture_rect(picture: &Picture, rect: Rect) -> Rect {
+    let (width, height) = picture.size();
+    let (width, height) = (
+        i64::from(width.max(1)) * EMU_PER_PIXEL,
+        i64::from(height.max(1)) * EMU_PER_PIXEL,
+    );
+    let scale = (rect.width as f64 / width as f64)
+        .min(rect.height as f64 / height as f64)
+        .min(1.0);
+    let (width, height) = (
+        (width as f64 * scale) as i64,
+        (height as f64 * scale) as i64,
+    );
+    Rect {
+        x: rect.x + (rect.width - width) / 2,
+        y: rect.y,
+        width,
+        height,
+    }
+}
+
+/// The height of each row of a table of `rows` rows within `rect`, at most half an inch.
+pub fn table_row_height(rect: Rect, rows: usize) -> i64 {
+    (rect.height / rows.max(1) as i64).min(MARGIN)
+}
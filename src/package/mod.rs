@@ -0,0 +1,250 @@
+//! Shared support for the exporters that write zipped XML packages, such as PPTX: the ZIP
+//! writer, XML escaping, local images, and a format-neutral view of slide content.
+
+pub mod content;
+pub mod layout;
+pub mod zip;
+
+use parser::ComrakOptions;
+use slides::Deck;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str;
+use themes::{Theme, DEFAULT_THEME};
+
+/// Escape text for XML content or a quoted attribute value, dropping the control characters
+/// XML cannot represent.
+pub fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The theme of a deck, selected as for standalone output: by `options.render.theme`, or else
+/// the deck's `theme` metadata.
+pub fn deck_theme(deck: &Deck, options: &ComrakOptions) -> io::Result<Theme> {
+    let name = options
+        .render
+        .theme
+        .as_deref()
+        .or(deck.metadata.theme.as_deref())
+        .unwrap_or(DEFAULT_THEME);
+    Theme::resolve(name, &options.render.theme_dirs)
+}
+
+/// The first font of a theme's stack for `role` which names a particular family, rather than a
+/// generic or system font.
+pub fn theme_font(theme: &Theme, role: &str) -> Option<String> {
+    let (_, stack) = theme.fonts.iter().find(|(r, _)| r == role)?;
+    stack
+        .split(',')
+        .map(|family| family.trim().trim_matches(|c| c == '"' || c == '\''))
+        .find(|family| {
+            !family.is_empty()
+                && !family.starts_with('-')
+                && !family.starts_with("ui-")
+                && ![
+                    "serif",
+                    "sans-serif",
+                    "monospace",
+                    "cursive",
+                    "fantasy",
+                    "system-ui",
+                    "BlinkMacSystemFont",
+                ]
+                .contains(family)
+        })
+        .map(str::to_string)
+}
+
+/// A theme colour as six hex digits, from its `#rgb` or `#rrggbb` CSS value.  Other values
+/// fall back to the default theme's colour.
+pub fn theme_color(theme: &Theme, name: &str) -> String {
+    let parse = |value: &str| {
+        let hex = value.trim().strip_prefix('#')?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match hex.len() {
+            3 => Some(hex.chars().flat_map(|c| vec![c, c]).collect::<String>()),
+            6 => Some(hex.to_string()),
+            _ => None,
+        }
+    };
+    theme
+        .variable(name)
+        .and_then(parse)
+        .or_else(|| {
+            Theme::builtin(DEFAULT_THEME)
+                .unwrap()
+                .variable(name)
+                .and_then(parse)
+        })
+        .unwrap_or_else(|| "000000".to_string())
+        .to_uppercase()
+}
+
+/// The formats of image which can be embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+}
+
+impl ImageFormat {
+    /// The usual file extension.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Gif => "gif",
+        }
+    }
+
+    /// The MIME type.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+}
+
+/// An image read from a local file.
+#[derive(Debug, Clone)]
+pub struct LocalImage {
+    pub data: Vec<u8>,
+    pub format: ImageFormat,
+
+    /// The width in pixels.
+    pub width: u32,
+
+    /// The height in pixels.
+    pub height: u32,
+}
+
+impl LocalImage {
+    /// Read the image at `url`, if it is a PNG, JPEG or GIF file within `base_dir`, as found by
+    /// `image_file`.
+    pub fn load(url: &str, base_dir: Option<&Path>) -> Option<LocalImage> {
+        LocalImage::from_data(fs::read(image_file(url, base_dir)?).ok()?)
+    }
+
+    /// Recognise an image from its contents.
+    pub fn from_data(data: Vec<u8>) -> Option<LocalImage> {
+        let (format, width, height) = image_size(&data)?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(LocalImage {
+            data,
+            format,
+            width,
+            height,
+        })
+    }
+}
+
+/// The file an image URL refers to, if it is a local file within `base_dir`.  Relative paths
+/// are resolved against `base_dir`; the file must exist, and its path must stay within
+/// `base_dir` once symbolic links and `..` components are resolved.  No file is allowed without
+/// a `base_dir`, so that Markdown from untrusted sources cannot read arbitrary files.
+///
+/// The path is returned as `base_dir` joined with the file's path within it.
+pub fn image_file(url: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    let dir = match base_dir? {
+        dir if dir.as_os_str().is_empty() => Path::new("."),
+        dir => dir,
+    };
+    let root = fs::canonicalize(dir).ok()?;
+    let file = fs::canonicalize(dir.join(local_path(url)?)).ok()?;
+    let within = file.strip_prefix(&root).ok()?;
+    if !file.is_file() {
+        return None;
+    }
+    Some(dir.join(within))
+}
+
+/// The path a URL refers to, if it refers to a local file: it has no scheme other than
+/// `file:`.  Percent-escapes are decoded.
+fn local_path(url: &str) -> Option<PathBuf> {
+    let url = url.strip_prefix("file://").unwrap_or(url);
+    let url = url.split(['?', '#']).next()?;
+    if url.is_empty() || url.contains("://") || url.starts_with("data:") {
+        return None;
+    }
+
+    let bytes = url.as_bytes();
+    let mut path = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                path.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                path.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(path).ok()?))
+}
+
+/// The format and pixel dimensions of PNG, JPEG and GIF data.
+fn image_size(data: &[u8]) -> Option<(ImageFormat, u32, u32)> {
+    let be16 = |at: usize| {
+        Some(u32::from(u16::from_be_bytes([
+            *data.get(at)?,
+            *data.get(at + 1)?,
+        ])))
+    };
+    let le16 = |at: usize| {
+        Some(u32::from(u16::from_le_bytes([
+            *data.get(at)?,
+            *data.get(at + 1)?,
+        ])))
+    };
+    let be32 = |at: usize| {
+        let bytes = data.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.get(12..16) == Some(&b"IHDR"[..]) {
+        return Some((ImageFormat::Png, be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some((ImageFormat::Gif, le16(6)?, le16(8)?));
+    }
+    if data.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments to the first start-of-frame marker, which holds the dimensions.
+        let mut at = 2;
+        while *data.get(at)? == 0xff {
+            let marker = *data.get(at + 1)?;
+            let is_frame = (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker);
+            if is_frame {
+                return Some((ImageFormat::Jpeg, be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
@@ -0,0 +1,147 @@
+//! A minimal ZIP archive writer.  Entries are stored uncompressed, which every reader of
+//! Office and OpenDocument packages accepts, and which OpenDocument requires of its `mimetype`.
+
+use std::io::{self, Write};
+
+lazy_static! {
+    static ref CRC32_TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 == 1 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+            *entry = c;
+        }
+        table
+    };
+}
+
+/// The CRC-32 checksum of `data`, as used by ZIP.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        CRC32_TABLE[((crc ^ u32::from(b)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The DOS date of 1980-01-01, the earliest a ZIP entry can carry.  Every entry has it, so
+/// that output is reproducible.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Flag bit 11: the entry name is UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a ZIP archive to an output stream, one whole entry at a time.
+pub struct ZipWriter<'w> {
+    output: &'w mut dyn Write,
+    offset: u32,
+    entries: Vec<Entry>,
+}
+
+impl<'w> ZipWriter<'w> {
+    pub fn new(output: &'w mut dyn Write) -> Self {
+        ZipWriter {
+            output,
+            offset: 0,
+            entries: vec![],
+        }
+    }
+
+    /// Add an entry named `name` holding `data`.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = to_u32(data.len())?;
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(data),
+            size,
+            offset: self.offset,
+        };
+
+        let mut header = vec![];
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&entry.crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&to_u16(name.len())?.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+
+        self.output.write_all(&header)?;
+        self.output.write_all(data)?;
+        self.offset = self
+            .offset
+            .checked_add(to_u32(header.len())?)
+            .and_then(|o| o.checked_add(size))
+            .ok_or_else(too_large)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory, completing the archive.
+    pub fn finish(self) -> io::Result<()> {
+        let mut directory = vec![];
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes());
+            directory.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&DOS_DATE.to_le_bytes());
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&to_u16(entry.name.len())?.to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = to_u16(self.entries.len())?;
+        let mut end = vec![];
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&to_u32(directory.len())?.to_le_bytes());
+        end.extend_from_slice(&self.offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+
+        self.output.write_all(&directory)?;
+        self.output.write_all(&end)
+    }
+}
+
+fn to_u16(n: usize) -> io::Result<u16> {
+    if n > usize::from(u16::MAX) {
+        return Err(too_large());
+    }
+    Ok(n as u16)
+}
+
+fn to_u32(n: usize) -> io::Result<u32> {
+    if n as u64 > u64::from(u32::MAX) {
+        return Err(too_large());
+    }
+    Ok(n as u32)
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "package too large for ZIP")
+}
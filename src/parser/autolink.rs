@@ -92,7 +92,13 @@ fn www_match<'a>(
     let mut url = b"http://".to_vec();
     url.extend_from_slice(&contents[i..link_end + i]);
 
-    let inl = make_inline(arena, NodeValue::Link(NodeLink { url, title: vec![] }));
+    let inl = make_inline(
+        arena,
+        NodeValue::Link(NodeLink {
+            url,
+            ..NodeLink::default()
+        }),
+    );
 
     inl.append(make_inline(
         arena,
@@ -236,7 +242,7 @@ fn url_match<'a>(
         arena,
         NodeValue::Link(NodeLink {
             url: url.clone(),
-            title: vec![],
+            ..NodeLink::default()
         }),
     );
 
@@ -316,7 +322,13 @@ fn email_match<'a>(
     let mut url = b"mailto:".to_vec();
     url.extend_from_slice(&contents[i - rewind..link_end + i]);
 
-    let inl = make_inline(arena, NodeValue::Link(NodeLink { url, title: vec![] }));
+    let inl = make_inline(
+        arena,
+        NodeValue::Link(NodeLink {
+            url,
+            ..NodeLink::default()
+        }),
+    );
 
     inl.append(make_inline(
         arena,
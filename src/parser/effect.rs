@@ -0,0 +1,258 @@
+//! Parsing of the arguments of `::effect[...]` directives.
+//!
+//! The grammar is:
+//!
+//! ```text
+//! directive = name [ ":" param { "," param } ]
+//! param     = [ key "=" ] value
+//! name, key = one or more of A-Z a-z 0-9 "-" "_" "."
+//! value     = quoted | bare
+//! ```
+//!
+//! Quoted values use double or single quotes; inside double quotes `\"` and `\\` are escapes,
+//! inside single quotes `''` stands for one quote.  Bare values run to the next unescaped comma
+//! and are trimmed; a backslash escapes the following character.  A parameter without a key is
+//! positional and is stored with an empty key.  The `target` parameter names the element the
+//! effect applies to and is stored apart from the other parameters.
+
+use nodes::EffectAttr;
+
+/// The names of the effects known without configuration: the slide transitions and the
+/// fragment styles of reveal.js.
+pub const EFFECTS: &[&str] = &[
+    "none",
+    "fade",
+    "slide",
+    "convex",
+    "concave",
+    "zoom",
+    "fade-in",
+    "fade-out",
+    "fade-up",
+    "fade-down",
+    "fade-left",
+    "fade-right",
+    "fade-in-then-out",
+    "fade-in-then-semi-out",
+    "semi-fade-out",
+    "current-visible",
+    "grow",
+    "shrink",
+    "strike",
+    "highlight-red",
+    "highlight-green",
+    "highlight-blue",
+    "highlight-current-red",
+    "highlight-current-green",
+    "highlight-current-blue",
+];
+
+/// The error of a directive whose line ends before its closing `]`.
+pub const UNTERMINATED: &str = "unterminated effect directive";
+
+/// Find the `]` closing a directive whose arguments start at the beginning of `input`,
+/// skipping quoted and escaped characters.  Returns `None` if the line ends first.
+pub fn scan(input: &[u8]) -> Option<usize> {
+    let mut quote = None;
+    // Quotes only open a quoted value at the start of a value.
+    let mut value_start = true;
+    let mut i = 0;
+
+    while i < input.len() {
+        let c = input[i];
+        if c == b'\n' || c == b'\r' {
+            return None;
+        }
+
+        match quote {
+            Some(b'\'') if c == b'\'' && input.get(i + 1) == Some(&b'\'') => i += 1,
+            Some(q) if c == q => quote = None,
+            Some(b'"') if c == b'\\' => i += 1,
+            Some(_) => (),
+            None => match c {
+                b'"' | b'\'' if value_start => quote = Some(c),
+                b'\\' => i += 1,
+                b']' => return Some(i),
+                _ => (),
+            },
+        }
+        if quote.is_none() && !c.is_ascii_whitespace() {
+            value_start = c == b':' || c == b',' || c == b'=';
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Parse the literal arguments of a directive.  On malformed input, `error` is set and the
+/// other fields are left empty.
+pub fn parse(literal: &[u8]) -> EffectAttr {
+    let mut effect = EffectAttr {
+        literal: literal.to_vec(),
+        ..EffectAttr::default()
+    };
+
+    match parse_directive(&String::from_utf8_lossy(literal)) {
+        Ok((name, params, target)) => {
+            effect.name = name;
+            effect.params = params;
+            effect.target = target;
+        }
+        Err(error) => effect.error = Some(error),
+    }
+
+    effect
+}
+
+/// An effect which could not be parsed, with the given error.
+pub fn malformed(literal: &[u8], error: &str) -> EffectAttr {
+    EffectAttr {
+        literal: literal.to_vec(),
+        error: Some(error.to_string()),
+        ..EffectAttr::default()
+    }
+}
+
+type Directive = (String, Vec<(String, String)>, Option<String>);
+
+fn parse_directive(text: &str) -> Result<Directive, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("empty effect directive".to_string());
+    }
+
+    let (name, rest) = match text.find(':') {
+        Some(colon) => (text[..colon].trim(), Some(&text[colon + 1..])),
+        None => (text, None),
+    };
+    if !is_identifier(name) {
+        return Err(format!("invalid effect name `{}`", name));
+    }
+
+    let mut params: Vec<(String, String)> = vec![];
+    let mut target = None;
+
+    for (key, value) in split_params(rest.unwrap_or(""))? {
+        if key == "target" {
+            if target.is_some() {
+                return Err("duplicate effect target".to_string());
+            }
+            target = Some(value);
+        } else if !key.is_empty() && params.iter().any(|(k, _)| *k == key) {
+            return Err(format!("duplicate effect parameter `{}`", key));
+        } else {
+            params.push((key, value));
+        }
+    }
+
+    Ok((name.to_string(), params, target))
+}
+
+fn split_params(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut params = vec![];
+    if text.trim().is_empty() {
+        return Ok(params);
+    }
+
+    let mut chars = text.chars().peekable();
+    loop {
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+
+        skip_spaces(&mut chars);
+        while let Some(&c) = chars.peek() {
+            match c {
+                ',' => break,
+                '"' | '\'' if value.trim().is_empty() && !quoted => {
+                    chars.next();
+                    value = parse_quoted(&mut chars, c)?;
+                    quoted = true;
+                    skip_spaces(&mut chars);
+                    match chars.peek() {
+                        None | Some(',') => (),
+                        Some(_) => return Err("unexpected text after quoted value".to_string()),
+                    }
+                }
+                '=' if key.is_none() && !quoted => {
+                    chars.next();
+                    let k = value.trim().to_string();
+                    if k.is_empty() {
+                        return Err("empty effect parameter key".to_string());
+                    }
+                    if !is_identifier(&k) {
+                        return Err(format!("invalid effect parameter key `{}`", k));
+                    }
+                    key = Some(k);
+                    value.clear();
+                    skip_spaces(&mut chars);
+                }
+                '\\' => {
+                    chars.next();
+                    match chars.next() {
+                        Some(c) => value.push(c),
+                        None => return Err("trailing backslash".to_string()),
+                    }
+                }
+                _ => {
+                    chars.next();
+                    value.push(c);
+                }
+            }
+        }
+
+        if !quoted {
+            value = value.trim().to_string();
+            if key.is_none() && value.is_empty() {
+                return Err("empty effect parameter".to_string());
+            }
+        }
+        params.push((key.unwrap_or_default(), value));
+
+        if chars.next().is_none() {
+            return Ok(params);
+        }
+    }
+}
+
+fn parse_quoted<I: Iterator<Item = char>>(
+    chars: &mut ::std::iter::Peekable<I>,
+    quote: char,
+) -> Result<String, String> {
+    let mut s = String::new();
+
+    while let Some(c) = chars.next() {
+        if c == quote {
+            if quote == '\'' && chars.peek() == Some(&'\'') {
+                chars.next();
+                s.push('\'');
+                continue;
+            }
+            return Ok(s);
+        }
+
+        if c == '\\' && quote == '"' {
+            match chars.next() {
+                Some(c) => s.push(c),
+                None => break,
+            }
+        } else {
+            s.push(c);
+        }
+    }
+
+    Err("unterminated quoted value".to_string())
+}
+
+fn skip_spaces<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) {
+    while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
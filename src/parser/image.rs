@@ -0,0 +1,112 @@
+//! Parsing of the sizing and layout hints in image alt text, for the image attributes extension.
+//!
+//! The hints are comma-separated `key:value` pairs.  They either make up the whole alt text, as
+//! in `![width:200,layout:center](a.png)`, or follow the last `|`, as in
+//! `![A diagram|width:200](a.png)`.  Pairs with unknown keys or invalid values are skipped, but
+//! unless every part is a `key:value` pair and at least one is understood, the alt text is left
+//! alone.
+
+use nodes::{ImageAttributes, ImageFilter, ImageLayout};
+
+/// Split `alt` into the human-readable text and the hints.  Returns the length of the text and
+/// the parsed hints, or `None` if `alt` has no valid hints.
+pub fn split_alt(alt: &str) -> Option<(usize, ImageAttributes)> {
+    match alt.rfind('|') {
+        Some(bar) => {
+            parse(&alt[bar + 1..]).map(|attributes| (alt[..bar].trim_end().len(), attributes))
+        }
+        None => parse(alt).map(|attributes| (0, attributes)),
+    }
+}
+
+/// Parse comma-separated hints, skipping those that are not understood.  Returns `None` if any
+/// part is not a `key:value` pair, or none is understood.
+pub fn parse(hints: &str) -> Option<ImageAttributes> {
+    let mut attributes = ImageAttributes::default();
+    let mut understood = false;
+
+    for hint in hints.split(',') {
+        let mut parts = hint.splitn(2, ':');
+        let key = parts.next()?.trim();
+        let value = parts.next()?.trim();
+        if key.is_empty() || !key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+            return None;
+        }
+
+        let applied = match key {
+            "width" | "w" => pixels(value).map(|v| attributes.width = Some(v)),
+            "height" | "h" => pixels(value).map(|v| attributes.height = Some(v)),
+            "layout" => layout(value).map(|v| attributes.layout = Some(v)),
+            "filter" => filter(value).map(|v| attributes.filters.push(v)),
+            _ => None,
+        };
+        understood |= applied.is_some();
+    }
+
+    if understood {
+        Some(attributes)
+    } else {
+        None
+    }
+}
+
+fn pixels(value: &str) -> Option<u32> {
+    let value = value.strip_suffix("px").unwrap_or(value);
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn layout(value: &str) -> Option<ImageLayout> {
+    match value {
+        "left" => Some(ImageLayout::Left),
+        "center" | "centre" => Some(ImageLayout::Center),
+        "right" => Some(ImageLayout::Right),
+        "fill" => Some(ImageLayout::Fill),
+        _ => None,
+    }
+}
+
+/// A filter written as `name`, `name(argument)`, or `name` directly followed by its argument,
+/// as in `blur.5px`.  A bare name takes a default argument.
+fn filter(value: &str) -> Option<ImageFilter> {
+    let name = value
+        .find(|c: char| !c.is_ascii_alphabetic() && c != '-')
+        .unwrap_or(value.len());
+    let (function, rest) = value.split_at(name);
+    let argument = match rest.strip_prefix('(') {
+        Some(rest) => rest.strip_suffix(')')?.trim(),
+        None => rest,
+    };
+
+    let default = match function {
+        "blur" => "10px",
+        "brightness" => "1.5",
+        "contrast" => "2",
+        "grayscale" => "1",
+        "hue-rotate" => "180deg",
+        "invert" => "1",
+        "opacity" => ".5",
+        "saturate" => "2",
+        "sepia" => "1",
+        _ => return None,
+    };
+
+    // Keep arguments to plain CSS values, as they are written into a `style` attribute.
+    if !argument
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'%' || b == b'-' || b == b'+')
+    {
+        return None;
+    }
+
+    Some(ImageFilter {
+        function: function.to_string(),
+        argument: if argument.is_empty() {
+            default.to_string()
+        } else {
+            argument.to_string()
+        },
+    })
+}
@@ -1,8 +1,10 @@
 use arena_tree::Node;
 use ctype::{ispunct, isspace};
 use entity;
-use nodes::{Ast, AstNode, EffectAttr, NodeCode, NodeLink, NodeValue};
-use parser::{unwrap_into_2, unwrap_into_copy, AutolinkType, Callback, ComrakOptions, Reference};
+use nodes::{Ast, AstNode, NodeCode, NodeLink, NodeValue};
+use parser::{effect, image};
+use parser::{line_column, unwrap_into_2, unwrap_into_copy};
+use parser::{AutolinkType, Callback, ComrakOptions, Reference};
 use scanners;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
@@ -35,6 +37,14 @@ pub struct Subject<'a: 'd, 'r, 'o, 'd, 'i, 'c: 'subj, 'subj> {
     callback: Option<&'subj mut Callback<'c>>,
 
     last_is_effect: bool,
+    /// The effects parsed, with the position in `input` of each one's arguments.
+    pub effects: Vec<(usize, &'a AstNode<'a>)>,
+
+    /// The position in `input` of the start of each line, with its byte offset in the document.
+    /// Empty when the input's position in the document is not known.
+    lines: Vec<(usize, usize)>,
+    /// The byte offset of the start of each line of the document.
+    line_starts: &'i [usize],
 }
 
 pub struct Delimiter<'a: 'd, 'd> {
@@ -82,6 +92,9 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
             smart_chars: [false; 256],
             callback,
             last_is_effect: false,
+            effects: vec![],
+            lines: vec![],
+            line_starts: &[],
         };
         for &c in &[
             b'\n', b'\r', b'_', b'*', b'"', b'`', b'\\', b'&', b'<', b'[', b']', b'!', b':',
@@ -101,12 +114,52 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         s
     }
 
+    /// Record where the input comes from, so that inlines are given source positions.
+    /// `line_offsets` are the byte offsets in the document of the input's lines, and
+    /// `line_starts` those of the start of each line of the document.
+    pub fn set_source(&mut self, line_offsets: &[usize], line_starts: &'i [usize]) {
+        let mut starts = vec![0];
+        for (ix, &c) in self.input.iter().enumerate() {
+            if c == b'\n' || (c == b'\r' && self.input.get(ix + 1) != Some(&b'\n')) {
+                starts.push(ix + 1);
+            }
+        }
+        self.lines = starts
+            .into_iter()
+            .zip(line_offsets.iter().cloned())
+            .collect();
+        self.line_starts = line_starts;
+    }
+
+    /// The line and column in the document of the byte at `pos` in the input, if known.
+    pub fn source_position(&self, pos: usize) -> Option<(u32, usize)> {
+        let ix = self.lines.partition_point(|&(start, _)| start <= pos);
+        let (start, offset) = *self.lines.get(ix.checked_sub(1)?)?;
+        Some(line_column(self.line_starts, offset + pos - start))
+    }
+
+    /// Set the position of `node` to the bytes `start..end` of the input.
+    fn set_span(&self, node: &'a AstNode<'a>, start: usize, end: usize) {
+        if let (Some(first), Some(last)) = (
+            self.source_position(start),
+            self.source_position(end.max(start + 1) - 1),
+        ) {
+            let mut ast = node.data.borrow_mut();
+            ast.start_line = first.0;
+            ast.start_column = first.1;
+            ast.end_line = last.0;
+            ast.end_column = last.1;
+        }
+    }
+
     pub fn pop_bracket(&mut self) -> bool {
         self.brackets.pop().is_some()
     }
 
     pub fn parse_inline(&mut self, node: &'a AstNode<'a>) -> bool {
         let new_inl: Option<&'a AstNode<'a>>;
+        let start = self.pos;
+        let mut end = None;
         let c = match self.peek_char() {
             None => return false,
             Some(ch) => *ch as char,
@@ -114,7 +167,10 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
 
         match c {
             '\0' => return false,
-            '\r' | '\n' => new_inl = Some(self.handle_newline()),
+            '\r' | '\n' => {
+                new_inl = Some(self.handle_newline());
+                end = Some(start + 1);
+            }
             '`' => new_inl = Some(self.handle_backticks()),
             '\\' => new_inl = Some(self.handle_backslash()),
             '&' => new_inl = Some(self.handle_entity()),
@@ -184,31 +240,32 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
                     self.skip_spaces();
                     if self.peek_char() == Some(&(b'[')) {
                         self.pos += 1;
-                        let inl = make_inline(self.arena, NodeValue::Text(b"::effect[".to_vec()));
-                        new_inl = Some(inl);
-                        // new_inl = Some(self.handle_effect())
-                        self.push_bracket(false, true, inl);
-
-                        let mut end_flag = 0;
-
-                        while end_flag < self.input.len() {
-                            let pos_char = self.peek_char_n(end_flag);
-                            if pos_char == Some(&(b']')) {
-                                break;
+                        match effect::scan(&self.input[self.pos..]) {
+                            Some(end) => {
+                                let inl =
+                                    make_inline(self.arena, NodeValue::Text(b"::effect[".to_vec()));
+                                new_inl = Some(inl);
+                                self.push_bracket(false, true, inl);
+                                self.pos += end;
                             }
-
-                            if pos_char == Some(&(b'\n')) {
-                                break;
-                            }
-
-                            if pos_char == Some(&(b'\r')) {
-                                break;
+                            None => {
+                                let end = self.input[self.pos..]
+                                    .iter()
+                                    .position(|&c| strings::is_line_end_char(c))
+                                    .map_or(self.input.len(), |ix| self.pos + ix);
+                                let literal = &self.input[self.pos..end];
+                                let inl = make_inline(
+                                    self.arena,
+                                    NodeValue::Effect(effect::malformed(
+                                        literal,
+                                        effect::UNTERMINATED,
+                                    )),
+                                );
+                                self.effects.push((self.pos, inl));
+                                new_inl = Some(inl);
+                                self.pos = end;
                             }
-
-                            end_flag = end_flag + 1;
                         }
-
-                        self.pos = self.pos + end_flag;
                     } else {
                         new_inl = Some(make_inline(
                             self.arena,
@@ -239,12 +296,14 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
                     // if self.last_is_effect {
                     //     contents = self.input[0..0].to_vec();
                     // }
+                    end = Some(start + contents.len());
                     new_inl = Some(make_inline(self.arena, NodeValue::Text(contents)));
                 }
             }
         }
 
         if let Some(inl) = new_inl {
+            self.set_span(inl, start, end.unwrap_or(self.pos));
             node.append(inl);
         }
 
@@ -892,6 +951,20 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         }
         opener.inl.insert_after(emph);
 
+        // Delimiter runs are never split across lines, so the emphasis starts after the
+        // opener's remaining delimiters and ends with the closer's used ones.
+        if opener.inl.data.borrow().start_line > 0 {
+            let mut opener_ast = opener.inl.data.borrow_mut();
+            let mut closer_ast = closer.inl.data.borrow_mut();
+            let mut emph_ast = emph.data.borrow_mut();
+            emph_ast.start_line = opener_ast.start_line;
+            emph_ast.start_column = opener_ast.start_column + opener_num_chars;
+            emph_ast.end_line = closer_ast.start_line;
+            emph_ast.end_column = closer_ast.start_column + use_delims - 1;
+            opener_ast.end_column = emph_ast.start_column.saturating_sub(1);
+            closer_ast.start_column += use_delims;
+        }
+
         // Drop the delimiters and return the next closer to process
 
         if opener_num_chars == 0 {
@@ -1033,60 +1106,9 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         let after_link_text_pos = self.pos;
 
         if is_effect {
-            // println!("self.pos :{:?}", self.pos);
-
-            let endtitle = self.pos - 1; //+ scanners::spacechars(&self.input[self.pos..]).unwrap_or(0);
-            let mut starttitle = 1;
-            while starttitle < self.input.len() {
-                let pos_char = self.un_peek_char_n(starttitle);
-
-                // match pos_char {
-                //     Some(cc) => {
-                //         let mut array = [*cc];
-                //         println!("eee:{:?}", String::from_utf8_lossy(&array));
-                //     }
-                //     None => {
-                //         println!("nil")
-                //     }
-                // }
-
-                // println!("aa starttitle: {:?}", starttitle);
-
-                if pos_char == Some(&(b'[')) {
-                    break;
-                }
-
-                if pos_char == Some(&(b'\n')) {
-                    break;
-                }
-
-                if pos_char == Some(&(b'\r')) {
-                    break;
-                }
-
-                starttitle = starttitle + 1;
-            }
-
-            /*
-            +2: '[' & ']'
-            */
-            let starttitle = endtitle - starttitle + 2;
-
-            println!(
-                "pos: {:?} starttitle: {:?} endtitle: {:?}",
-                self.pos, starttitle, endtitle
-            );
-            if endtitle < self.input.len()
-            /*&& self.input[endall] == b')'*/
-            {
-                let url = vec![];
-                let title = strings::clean_title(&self.input[starttitle..endtitle]);
-
-                // println!("title: {:?}", String::from_utf8(title.clone()));
-
-                self.close_bracket_match(false, true, url, title);
-                return None;
-            }
+            let literal = self.input[bracket.position..self.pos - 1].to_vec();
+            self.close_bracket_match(false, true, vec![], literal);
+            return None;
         }
 
         // Try to find a link destination within parenthesis
@@ -1170,6 +1192,7 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
             let text = text.unwrap();
             if text.len() > 1 && text[0] == b'^' {
                 let inl = make_inline(self.arena, NodeValue::FootnoteReference(text[1..].to_vec()));
+                self.span_bracket(inl, self.brackets[brackets_len - 1].inl_text);
                 self.brackets[brackets_len - 1].inl_text.insert_before(inl);
                 self.brackets[brackets_len - 1]
                     .inl_text
@@ -1189,6 +1212,45 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         Some(make_inline(self.arena, NodeValue::Text(b"]".to_vec())))
     }
 
+    /// Moves the hints at the end of an image's alt text into its `NodeLink`, leaving only the
+    /// human-readable text as children.
+    fn split_image_attributes(&mut self, image: &'a AstNode<'a>) {
+        // Text is split at special characters such as `:`, so gather the trailing run of text.
+        let mut run = vec![];
+        let mut child = image.last_child();
+        while let Some(c) = child {
+            if c.data.borrow().value.text().is_none() {
+                break;
+            }
+            run.push(c);
+            child = c.previous_sibling();
+        }
+        run.reverse();
+
+        let mut alt = vec![];
+        for c in &run {
+            alt.extend_from_slice(c.data.borrow().value.text().unwrap());
+        }
+        let alt = String::from_utf8_lossy(&alt).into_owned();
+
+        let (len, attributes) = match image::split_alt(&alt) {
+            // Without a `|`, the hints must be the whole alt text.
+            Some((len, attributes)) if alt.contains('|') || child.is_none() => (len, attributes),
+            _ => return,
+        };
+
+        for c in run {
+            c.detach();
+        }
+        if len > 0 {
+            let text = make_inline(self.arena, NodeValue::Text(alt.as_bytes()[..len].to_vec()));
+            image.append(text);
+        }
+        if let NodeValue::Image(ref mut nl) = image.data.borrow_mut().value {
+            nl.attributes = attributes;
+        }
+    }
+
     pub fn close_bracket_match(
         &mut self,
         is_image: bool,
@@ -1199,18 +1261,30 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         let inl = make_inline(
             self.arena,
             if is_image {
-                let nl = NodeLink { url, title };
+                let nl = NodeLink {
+                    url,
+                    title,
+                    ..NodeLink::default()
+                };
                 NodeValue::Image(nl)
             } else if is_effect {
-                let ef = EffectAttr { literal: title };
-                NodeValue::Effect(ef)
+                NodeValue::Effect(effect::parse(&title))
             } else {
-                let nl = NodeLink { url, title };
+                let nl = NodeLink {
+                    url,
+                    title,
+                    ..NodeLink::default()
+                };
                 NodeValue::Link(nl)
             },
         );
 
         let mut brackets_len = self.brackets.len();
+        self.span_bracket(inl, self.brackets[brackets_len - 1].inl_text);
+        if is_effect {
+            self.effects
+                .push((self.brackets[brackets_len - 1].position, inl));
+        }
         self.brackets[brackets_len - 1].inl_text.insert_before(inl);
         let mut tmpch = self.brackets[brackets_len - 1].inl_text.next_sibling();
         while let Some(tmp) = tmpch {
@@ -1223,6 +1297,10 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         self.brackets.pop();
         brackets_len -= 1;
 
+        if is_image && self.options.extension.image_attributes {
+            self.split_image_attributes(inl);
+        }
+
         if !is_image && !is_effect {
             let mut i = brackets_len as i32 - 1;
             while i >= 0 {
@@ -1238,6 +1316,21 @@ impl<'a, 'r, 'o, 'd, 'i, 'c, 'subj> Subject<'a, 'r, 'o, 'd, 'i, 'c, 'subj> {
         }
     }
 
+    /// Set the position of a node replacing a bracket, from the bracket's opening text to the
+    /// last byte parsed.
+    fn span_bracket(&self, node: &'a AstNode<'a>, inl_text: &'a AstNode<'a>) {
+        let end = match self.source_position(self.pos.max(1) - 1) {
+            Some(end) => end,
+            None => return,
+        };
+        let opening = inl_text.data.borrow();
+        let mut ast = node.data.borrow_mut();
+        ast.start_line = opening.start_line;
+        ast.start_column = opening.start_column;
+        ast.end_line = end.0;
+        ast.end_column = end.1;
+    }
+
     pub fn link_label(&mut self) -> Option<&[u8]> {
         let startpos = self.pos;
 
@@ -1356,7 +1449,12 @@ pub fn make_inline<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue) -> &'a A
     let ast = Ast {
         value,
         content: vec![],
+        line_offsets: vec![],
         start_line: 0,
+        start_column: 0,
+        end_line: 0,
+        end_column: 0,
+        fragment: None,
         open: false,
         last_line_blank: false,
     };
@@ -1372,7 +1470,7 @@ fn make_autolink<'a>(
         arena,
         NodeValue::Link(NodeLink {
             url: strings::clean_autolink(url, kind),
-            title: vec![],
+            ..NodeLink::default()
         }),
     );
     inl.append(make_inline(
@@ -0,0 +1,349 @@
+//! Parsing of the `key: value` lines inside a slide metadata block.
+//!
+//! Values are typed: quoted strings, numbers, `true`/`false`, flow lists (`[a, b]`) and maps
+//! (`{a: 1}`), plus block lists and maps given as more deeply indented lines; a block list may
+//! also be indented as deeply as its key.  Only the first colon of a line separates the key
+//! from the value, so values may contain colons; keys which contain colons must be quoted.  A
+//! flow map with an item that is not `key: value` is read as a string.
+//!
+//! Lines which are not entries are skipped, and reported when parsing with diagnostics.
+
+use diagnostics::{DiagnosticCode, Diagnostics};
+use nodes::{MetaValue, NodeKV};
+use regex::Regex;
+use std::str;
+
+/// Maps a line of a literal, counting from 0, and a column within it, counting from 1, to a
+/// line and column of the document.
+pub type Position<'p> = &'p dyn Fn(usize, usize) -> (u32, usize);
+
+/// A top-level entry of a metadata block, with the positions of its first and last bytes.
+pub struct Entry {
+    pub kv: NodeKV,
+    pub start: (u32, usize),
+    pub end: (u32, usize),
+}
+
+struct Line<'t> {
+    index: usize,
+    indent: usize,
+    text: &'t str,
+}
+
+struct Context<'p> {
+    position: Position<'p>,
+    diagnostics: &'p mut Diagnostics,
+}
+
+impl<'p> Context<'p> {
+    fn report(&mut self, line: &Line, code: DiagnosticCode, message: String) {
+        let (number, column) = (self.position)(line.index, line.indent + 1);
+        self.diagnostics.push(code, message, number, column);
+    }
+}
+
+/// Parse the literal contents of a metadata block into its entries, in source order.
+pub fn parse(literal: &[u8]) -> Vec<NodeKV> {
+    let position = |line: usize, column| (line as u32 + 1, column);
+    parse_with_diagnostics(literal, &position, &mut Diagnostics::new())
+        .into_iter()
+        .map(|entry| entry.kv)
+        .collect()
+}
+
+/// Parse as `parse`, adding a diagnostic for each skipped or repeated entry.  Positions in the
+/// literal are mapped to the document by `position`.
+pub fn parse_with_diagnostics(
+    literal: &[u8],
+    position: Position,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Entry> {
+    let literal = String::from_utf8_lossy(literal);
+    let lines = literal
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(index, l)| Line {
+            index,
+            indent: l.len() - l.trim_start().len(),
+            text: l.trim(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut cx = Context {
+        position,
+        diagnostics,
+    };
+    let mut entries = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let indent = lines[i].indent;
+        parse_map(&lines, &mut i, indent, &mut entries, &mut cx);
+    }
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let (first, last) = (&lines[entry.first], &lines[entry.last]);
+            Entry {
+                kv: NodeKV {
+                    key: entry.key.into_bytes(),
+                    value: entry.value,
+                },
+                start: position(first.index, first.indent + 1),
+                end: position(last.index, last.indent + last.text.len()),
+            }
+        })
+        .collect()
+}
+
+/// The body of a front matter block, between its opening and closing delimiter lines.
+pub fn front_matter_body(literal: &[u8], delimiter: &str) -> String {
+    let literal = String::from_utf8_lossy(literal);
+    literal
+        .lines()
+        .skip(1)
+        .take_while(|line| line.trim_end() != delimiter)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An entry of a map, with the indices of its first and last lines.
+struct MapEntry {
+    key: String,
+    value: MetaValue,
+    first: usize,
+    last: usize,
+}
+
+fn parse_map(
+    lines: &[Line],
+    i: &mut usize,
+    indent: usize,
+    entries: &mut Vec<MapEntry>,
+    cx: &mut Context,
+) {
+    while *i < lines.len() && lines[*i].indent == indent {
+        let first = *i;
+        let line = &lines[*i];
+        *i += 1;
+
+        let (key, rest) = match split_key(line.text) {
+            Some((key, rest)) if !key.is_empty() => (key, rest),
+            Some(_) => {
+                cx.report(
+                    line,
+                    DiagnosticCode::EmptyMetadataKey,
+                    format!("metadata entry `{}` has an empty key", line.text),
+                );
+                continue;
+            }
+            None => {
+                cx.report(
+                    line,
+                    DiagnosticCode::InvalidMetadataLine,
+                    format!("`{}` is not a `key: value` metadata entry", line.text),
+                );
+                continue;
+            }
+        };
+
+        // A block list may be indented as deeply as its key.
+        let value = if !rest.is_empty() {
+            parse_value(rest)
+        } else if *i < lines.len()
+            && (lines[*i].indent > indent
+                || (lines[*i].indent == indent && is_list_item(lines[*i].text)))
+        {
+            parse_block(lines, i, cx)
+        } else {
+            MetaValue::String(String::new())
+        };
+        if entries.iter().any(|e| e.key == key) {
+            cx.report(
+                line,
+                DiagnosticCode::DuplicateMetadataKey,
+                format!(
+                    "metadata key `{}` is given more than once; the last value is used",
+                    key
+                ),
+            );
+        }
+        entries.push(MapEntry {
+            key,
+            value,
+            first,
+            last: *i - 1,
+        });
+    }
+
+    // Lines indented deeper than their context belong to no entry.
+    while *i < lines.len() && lines[*i].indent > indent {
+        cx.report(
+            &lines[*i],
+            DiagnosticCode::InvalidMetadataLine,
+            format!("`{}` is indented under no metadata entry", lines[*i].text),
+        );
+        *i += 1;
+    }
+}
+
+/// A block list or map, starting at the current, more deeply indented line.
+fn parse_block(lines: &[Line], i: &mut usize, cx: &mut Context) -> MetaValue {
+    let indent = lines[*i].indent;
+    if !is_list_item(lines[*i].text) {
+        let mut entries = vec![];
+        parse_map(lines, i, indent, &mut entries, cx);
+        return MetaValue::Map(entries.into_iter().map(|e| (e.key, e.value)).collect());
+    }
+
+    let mut items = vec![];
+    while *i < lines.len() && lines[*i].indent == indent && is_list_item(lines[*i].text) {
+        let item = lines[*i].text[1..].trim();
+        *i += 1;
+        if !item.is_empty() {
+            items.push(parse_value(item));
+        } else if *i < lines.len() && lines[*i].indent > indent {
+            items.push(parse_block(lines, i, cx));
+        } else {
+            items.push(MetaValue::String(String::new()));
+        }
+    }
+    MetaValue::List(items)
+}
+
+fn is_list_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` at the first colon outside a quoted key.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (key, len) = parse_quoted(text)?;
+        let rest = text[len..].trim_start().strip_prefix(':')?;
+        return Some((key, rest.trim()));
+    }
+
+    let colon = text.find(':')?;
+    Some((text[..colon].trim().to_string(), text[colon + 1..].trim()))
+}
+
+/// Parse a single value written on one line.
+pub fn parse_value(text: &str) -> MetaValue {
+    lazy_static! {
+        static ref NUMBER: Regex =
+            Regex::new(r"^[-+]?([0-9]+(\.[0-9]*)?|\.[0-9]+)([eE][-+]?[0-9]+)?$").unwrap();
+    }
+
+    let text = text.trim();
+
+    if text.starts_with('"') || text.starts_with('\'') {
+        if let Some((s, len)) = parse_quoted(text) {
+            if len == text.len() {
+                return MetaValue::String(s);
+            }
+        }
+    } else if text.starts_with('[') && text.ends_with(']') {
+        if let Some(items) = split_flow(&text[1..text.len() - 1]) {
+            return MetaValue::List(items.into_iter().map(parse_value).collect());
+        }
+    } else if text.starts_with('{') && text.ends_with('}') {
+        let entries = split_flow(&text[1..text.len() - 1])
+            .and_then(|items| items.into_iter().map(split_key).collect::<Option<Vec<_>>>());
+        if let Some(entries) = entries {
+            return MetaValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, parse_value(v)))
+                    .collect(),
+            );
+        }
+    } else if text == "true" {
+        return MetaValue::Bool(true);
+    } else if text == "false" {
+        return MetaValue::Bool(false);
+    } else if NUMBER.is_match(text) {
+        if let Ok(n) = text.parse() {
+            return MetaValue::Number(n);
+        }
+    }
+
+    MetaValue::String(text.to_string())
+}
+
+/// Parse a quoted string at the start of `text`, returning the unescaped string and the number
+/// of bytes consumed.  Double-quoted strings support `\"`, `\\`, `\n` and `\t`; in
+/// single-quoted strings `''` stands for one quote.
+fn parse_quoted(text: &str) -> Option<(String, usize)> {
+    let quote = text.chars().next()?;
+    let mut s = String::new();
+    let mut chars = text.char_indices().skip(1).peekable();
+
+    while let Some((ix, c)) = chars.next() {
+        if c == quote {
+            if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') {
+                chars.next();
+                s.push('\'');
+                continue;
+            }
+            return Some((s, ix + 1));
+        }
+
+        if c == '\\' && quote == '"' {
+            match chars.next() {
+                Some((_, 'n')) => s.push('\n'),
+                Some((_, 't')) => s.push('\t'),
+                Some((_, c)) => s.push(c),
+                None => return None,
+            }
+        } else {
+            s.push(c);
+        }
+    }
+
+    None
+}
+
+/// Split the inside of a flow list or map at commas which are not nested or quoted.  Returns
+/// `None` if brackets or quotes are unbalanced.
+fn split_flow(text: &str) -> Option<Vec<&str>> {
+    let mut items = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (ix, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' && q == '"' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                items.push(text[start..ix].trim());
+                start = ix + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if quote.is_some() || depth != 0 {
+        return None;
+    }
+
+    let last = text[start..].trim();
+    if !last.is_empty() || !items.is_empty() {
+        items.push(last);
+    }
+    Some(items)
+}
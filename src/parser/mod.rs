@@ -1,9 +1,13 @@
 mod autolink;
+pub(crate) mod effect;
+mod image;
 mod inlines;
+pub(crate) mod metadata;
 mod table;
 
 use arena_tree::Node;
 use ctype::{isdigit, isspace};
+use diagnostics::{DiagnosticCode, Diagnostics};
 use entity;
 use nodes;
 use nodes::{
@@ -16,6 +20,7 @@ use std::cell::RefCell;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use std::str;
 use std::{io, mem};
 use strings;
@@ -48,10 +53,11 @@ pub fn parse_document<'a>(
 
 /// Dump Node
 ///
-/// Dump Node
-pub fn dump_node<'a>(node: &'a AstNode<'a>) -> io::Result<()> {
-    let mut output = BufWriter::new(io::stdout());
-    iter_nodes(node, &mut output, 0)
+/// Writes the tree under `node` to `output`, one block per line.
+pub fn dump_node<'a>(node: &'a AstNode<'a>, output: &mut dyn Write) -> io::Result<()> {
+    let mut output = BufWriter::new(output);
+    iter_nodes(node, &mut output, 0)?;
+    output.flush()
 }
 
 fn iter_nodes<'a, W: Write>(
@@ -170,18 +176,93 @@ pub fn parse_document_with_broken_link_callback<'a, 'c>(
     options: &ComrakOptions,
     callback: Option<Callback<'c>>,
 ) -> &'a AstNode<'a> {
-    let root: &'a AstNode<'a> = arena.alloc(Node::new(RefCell::new(Ast {
-        value: NodeValue::Document,
-        content: vec![],
-        start_line: 0,
-        open: true,
-        last_line_blank: false,
-    })));
-    let mut parser = Parser::new(arena, root, options, callback);
+    let mut parser = Parser::new(arena, document(arena), options, callback);
     parser.feed(buffer);
     parser.finish()
 }
 
+/// Parse a Markdown document to an AST, along with the problems found in it.
+///
+/// The AST is the same as `parse_document` returns; the parser recovers from every problem it
+/// reports.  Diagnostics are in source order.
+///
+/// ```
+/// # use comrak::{parse_document_with_diagnostics, Arena, ComrakOptions};
+/// use comrak::diagnostics::{DiagnosticCode, Severity};
+///
+/// let arena = Arena::new();
+/// let (_, diagnostics) = parse_document_with_diagnostics(
+///     &arena,
+///     "# Intro\n---\nlayout: title\n: centered\n",
+///     &ComrakOptions::default(),
+/// );
+///
+/// let codes = diagnostics.iter().map(|d| (d.line, d.code)).collect::<Vec<_>>();
+/// assert_eq!(codes, [
+///     (2, DiagnosticCode::UnclosedMetadataFence),
+///     (4, DiagnosticCode::EmptyMetadataKey),
+/// ]);
+/// assert_eq!(diagnostics.errors().next().unwrap().severity, Severity::Error);
+/// ```
+pub fn parse_document_with_diagnostics<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    buffer: &str,
+    options: &ComrakOptions,
+) -> (&'a AstNode<'a>, Diagnostics) {
+    let mut parser = Parser::new(arena, document(arena), options, None);
+    parser.feed(buffer);
+    let root = parser.finish();
+    let mut diagnostics = parser.diagnostics;
+    diagnostics.sort();
+    (root, diagnostics)
+}
+
+/// Parse part of a document, as `parse_document`.  With `references`, the part's links are
+/// resolved against those instead of the definitions within the part, as when the part is
+/// parsed with the rest of its document.
+///
+/// Returns the AST and the link reference definitions found in the part.
+pub(crate) fn parse_document_part<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    buffer: &str,
+    options: &ComrakOptions,
+    references: Option<HashMap<Vec<u8>, Reference>>,
+) -> (&'a AstNode<'a>, HashMap<Vec<u8>, Reference>) {
+    let mut parser = Parser::new(arena, document(arena), options, None);
+    let resolve = references.is_some();
+    parser.references = references;
+    parser.feed(buffer);
+    let root = parser.finish();
+    let defined = if resolve {
+        parser.references.take().unwrap_or_default()
+    } else {
+        parser.refmap
+    };
+    (root, defined)
+}
+
+/// The byte offset of the start of each line of `s`, followed by the length of `s`.
+pub(crate) fn line_starts(s: &[u8]) -> Vec<usize> {
+    let mut starts = vec![0];
+    for (ix, &c) in s.iter().enumerate() {
+        if c == b'\n' || (c == b'\r' && s.get(ix + 1) != Some(&b'\n')) {
+            starts.push(ix + 1);
+        }
+    }
+    if starts.last() == Some(&s.len()) && !s.is_empty() {
+        starts.pop();
+    }
+    starts.push(s.len());
+    starts
+}
+
+fn document<'a>(arena: &'a Arena<AstNode<'a>>) -> &'a AstNode<'a> {
+    let mut ast = Ast::new(NodeValue::Document);
+    ast.start_line = 1;
+    ast.start_column = 1;
+    arena.alloc(Node::new(RefCell::new(ast)))
+}
+
 type Callback<'c> = &'c mut dyn FnMut(&[u8]) -> Option<(Vec<u8>, Vec<u8>)>;
 
 pub struct Parser<'a, 'o, 'c> {
@@ -200,6 +281,17 @@ pub struct Parser<'a, 'o, 'c> {
     last_line_length: usize,
     options: &'o ComrakOptions,
     callback: Option<Callback<'c>>,
+    diagnostics: Diagnostics,
+    /// The byte offset in the input of the start of each line, followed by the input's length.
+    line_starts: Vec<usize>,
+    /// The length of the line being processed, without its line ending.
+    curline_length: Option<usize>,
+    /// Set while finalizing a block which ends on the line being processed, such as a fenced
+    /// block closed by its fence.
+    closing_line: bool,
+    /// References to resolve links against in place of the document's own definitions; once
+    /// the blocks are finalized, the document's definitions.
+    references: Option<HashMap<Vec<u8>, Reference>>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -213,6 +305,12 @@ pub struct ComrakOptions {
 
     /// Configure render-time options.
     pub render: ComrakRenderOptions,
+
+    /// Configure how documents are split into slides.
+    pub split: SlideSplitOptions,
+
+    /// Configure print output of slides.
+    pub print: SlidePrintOptions,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -389,6 +487,23 @@ pub struct ComrakExtensionOptions {
     /// assert_eq!(&String::from_utf8(buf).unwrap(), input);
     /// ```
     pub front_matter_delimiter: Option<String>,
+
+    /// Enables the image attributes extension.
+    ///
+    /// Sizing and layout hints may be given in an image's alt text as comma-separated
+    /// `key:value` pairs, either as the whole alt text or after the last `|`.  The keys are
+    /// `width` and `height` in pixels, `layout` (`left`, `center`, `right` or `fill`), and
+    /// `filter`, a CSS filter such as `blur`, `sepia(50%)` or `blur.5px`.  Pairs with unknown
+    /// keys or invalid values are skipped.
+    ///
+    /// ```
+    /// # use comrak::{markdown_to_html, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.extension.image_attributes = true;
+    /// assert_eq!(markdown_to_html("![A cat|width:200,layout:center,filter:blur](cat.png)", &options),
+    ///            "<p><img src=\"cat.png\" alt=\"A cat\" width=\"200\" class=\"layout-center\" style=\"filter: blur(10px)\" /></p>\n");
+    /// ```
+    pub image_attributes: bool,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -421,9 +536,26 @@ pub struct ComrakParseOptions {
     ///            "<pre><code class=\"language-rust\">fn hello();\n</code></pre>\n");
     /// ```
     pub default_info_string: Option<String>,
+
+    /// Names of effects accepted by `::effect[...]` directives, besides the built-in slide
+    /// transitions and reveal.js fragment styles.  Directives naming any other effect are still
+    /// parsed, but `parse_document_with_diagnostics` reports them as unknown.
+    ///
+    /// ```
+    /// # use comrak::{parse_document_with_diagnostics, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// let arena = Arena::new();
+    /// let (_, diagnostics) = parse_document_with_diagnostics(&arena, "::effect[wobble]\n", &options);
+    /// assert_eq!(diagnostics.len(), 1);
+    ///
+    /// options.parse.effects = vec!["wobble".into()];
+    /// let (_, diagnostics) = parse_document_with_diagnostics(&arena, "::effect[wobble]\n", &options);
+    /// assert!(diagnostics.is_empty());
+    /// ```
+    pub effects: Vec<String>,
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone)]
 /// Options for formatter functions.
 pub struct ComrakRenderOptions {
     /// [Soft line breaks](http://spec.commonmark.org/0.27/#soft-line-breaks) in the input
@@ -518,9 +650,191 @@ pub struct ComrakRenderOptions {
     ///            "<p>&lt;i&gt;italic text&lt;/i&gt;</p>\n");
     /// ```
     pub escape: bool,
+
+    /// What the slide JSON output carries for each slide: the Markdown `content`, the rendered
+    /// `html`, or both.
+    ///
+    /// ```
+    /// # use comrak::{format_slide, parse_document, Arena, ComrakOptions, SlideContentMode};
+    /// let mut options = ComrakOptions::default();
+    /// options.render.slide_content = SlideContentMode::Html;
+    /// let arena = Arena::new();
+    /// let root = parse_document(&arena, "# Fish & chips\n", &options);
+    /// let mut output = vec![];
+    /// format_slide(root, &options, &mut output).unwrap();
+    /// let output = String::from_utf8(output).unwrap();
+    /// assert!(output.contains(r#""html":"<h1>Fish &amp; chips</h1>\n""#));
+    /// assert!(!output.contains(r#""content":""#));
+    /// ```
+    pub slide_content: SlideContentMode,
+
+    /// The theme of standalone slide output: the name of a built-in theme (`light`, `dark` or
+    /// `high-contrast`) or of a theme directory within `theme_dirs`.  When set, this takes
+    /// precedence over the deck's `theme` metadata.
+    ///
+    /// ```
+    /// # use comrak::{format_slide_standalone, parse_document, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.render.theme = Some("dark".to_string());
+    /// let arena = Arena::new();
+    /// let root = parse_document(&arena, "# Hello\n", &options);
+    /// let mut html = vec![];
+    /// format_slide_standalone(root, &options, &mut html).unwrap();
+    /// assert!(String::from_utf8(html).unwrap().contains("<div class=\"deck theme-dark\""));
+    /// ```
+    pub theme: Option<String>,
+
+    /// Directories searched, in order, for themes which are not built in.  Each theme is a
+    /// subdirectory named after the theme; see the `themes` module.
+    pub theme_dirs: Vec<PathBuf>,
+
+    /// The directory that exporters which embed or link local images, such as `format_pptx`,
+    /// read them from.  Relative image paths are resolved against it, and files outside it are
+    /// never read.  Defaults to `None`, which reads no files, as Markdown from untrusted sources
+    /// could otherwise name any file the process can read.
+    pub base_dir: Option<PathBuf>,
+
+    /// Write slides' HTML as reveal.js expects it: fragments have the `fragment` class, and
+    /// links to slide anchors such as `#slide-2` are written as reveal.js's `#/slide-2`.
+    /// `format_reveal` sets this itself.
+    ///
+    /// ```
+    /// # use comrak::{markdown_to_html, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.render.reveal = true;
+    /// assert_eq!(markdown_to_html("[Back](#slide-1)\n", &options),
+    ///            "<p><a href=\"#/slide-1\">Back</a></p>\n");
+    /// ```
+    pub reveal: bool,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Which representations of a slide the slide JSON output carries.
+pub enum SlideContentMode {
+    /// Only the Markdown `content`.
+    Markdown,
+
+    /// Only the rendered `html`.
+    Html,
+
+    /// Both `content` and `html`.  This is the default.
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Options controlling where a document is split into slides.
+///
+/// The rules combine: a new slide starts at any block matching one of them.  Separators
+/// (thematic breaks and markers) are consumed by the split and are not part of any slide.
+pub struct SlideSplitOptions {
+    /// Headings of this level or a higher rank start a new slide; `0` disables splitting on
+    /// headings.  Defaults to `6`, i.e. every heading.
+    ///
+    /// ```
+    /// # use comrak::{parse_deck, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.split.heading_level = 2;
+    /// let arena = Arena::new();
+    /// let deck = parse_deck(&arena, "# A\n\n## B\n\n### C\n", &options);
+    /// assert_eq!(deck.slides.len(), 1);
+    /// assert_eq!(deck.slides[0].html, "<h2>B</h2>\n<h3>C</h3>\n");
+    /// ```
+    pub heading_level: u32,
+
+    /// Thematic breaks (`---`, `***`, `___`) start a new slide.  Note that a `---` following a
+    /// heading opens that slide's metadata block instead.  As a metadata block may only follow
+    /// a heading, a slide started by a thematic break has none unless it opens with a heading.
+    ///
+    /// ```
+    /// # use comrak::{parse_deck, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.split.thematic_break = true;
+    /// let arena = Arena::new();
+    /// let deck = parse_deck(&arena, "One\n\n---\n\nTwo\n", &options);
+    /// assert_eq!(deck.slides.len(), 1);
+    /// assert_eq!(deck.slides[0].html, "<p>Two</p>\n");
+    /// ```
+    pub thematic_break: bool,
+
+    /// An HTML comment block consisting of `<!-- slide -->` starts a new slide.  As with
+    /// `thematic_break`, the slide only has a metadata block if it opens with a heading.
+    ///
+    /// ```
+    /// # use comrak::{parse_deck, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.split.marker = true;
+    /// let arena = Arena::new();
+    /// let deck = parse_deck(&arena, "One\n\n<!-- slide -->\n\nTwo\n", &options);
+    /// assert_eq!(deck.slides.len(), 1);
+    /// assert_eq!(deck.slides[0].html, "<p>Two</p>\n");
+    /// ```
+    pub marker: bool,
+}
+
+impl Default for SlideSplitOptions {
+    fn default() -> Self {
+        SlideSplitOptions {
+            heading_level: 6,
+            thematic_break: false,
+            marker: false,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+/// Options for print output of slides, as by `format_slide_print`.
+pub struct SlidePrintOptions {
+    /// Print a page for every build step of a slide, revealing one more fragment on each,
+    /// instead of one page showing the whole slide.
+    ///
+    /// ```
+    /// # use comrak::{format_slide_print, parse_document, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.print.fragments = true;
+    /// let arena = Arena::new();
+    /// let root = parse_document(&arena, "# Steps\n\n<!-- fragment -->\nOne\n", &options);
+    /// let mut html = vec![];
+    /// format_slide_print(root, &options, &mut html).unwrap();
+    /// let html = String::from_utf8(html).unwrap();
+    /// assert!(html.contains("<section data-index=\"1\" data-steps=\"1\" data-step=\"1\">"));
+    /// ```
+    pub fragments: bool,
+
+    /// Print each slide's speaker notes under it.
+    ///
+    /// ```
+    /// # use comrak::{format_slide_print, parse_document, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.print.notes = true;
+    /// let arena = Arena::new();
+    /// let root = parse_document(&arena, "# Hi\n\nNote: wave\n", &options);
+    /// let mut html = vec![];
+    /// format_slide_print(root, &options, &mut html).unwrap();
+    /// let html = String::from_utf8(html).unwrap();
+    /// assert!(html.contains("<aside class=\"notes\">\n<p>wave</p>\n</aside>"));
+    /// ```
+    pub notes: bool,
+
+    /// Number each link and list its URL in footnotes at the bottom of the slide, so that
+    /// links can be followed from paper.
+    ///
+    /// ```
+    /// # use comrak::{format_slide_print, parse_document, Arena, ComrakOptions};
+    /// let mut options = ComrakOptions::default();
+    /// options.print.link_footnotes = true;
+    /// let arena = Arena::new();
+    /// let root = parse_document(&arena, "# Hi\n\nSee [docs](https://example.com).\n", &options);
+    /// let mut html = vec![];
+    /// format_slide_print(root, &options, &mut html).unwrap();
+    /// let html = String::from_utf8(html).unwrap();
+    /// assert!(html.contains("<a href=\"https://example.com\">docs</a><sup class=\"link-ref\">1</sup>"));
+    /// assert!(html.contains("<li>https://example.com</li>"));
+    /// ```
+    pub link_footnotes: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Reference {
     pub url: Vec<u8>,
     pub title: Vec<u8>,
@@ -554,6 +868,11 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
             last_line_length: 0,
             options,
             callback,
+            diagnostics: Diagnostics::new(),
+            line_starts: vec![],
+            curline_length: None,
+            closing_line: false,
+            references: None,
         }
     }
 
@@ -561,6 +880,8 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
         let mut i = 0;
         let s = s.as_bytes();
 
+        self.line_starts = line_starts(s);
+
         if let Some(ref delimiter) = self.options.extension.front_matter_delimiter {
             let front_matter_pattern = RegexBuilder::new(&format!(
                 "\\A(?:\u{feff})?{delim}\\r?\\n.*^{delim}\\r?\\n(?:\\r?\\n)?",
@@ -574,6 +895,25 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                 i += front_matter_size;
                 let node = self.add_child(self.root, NodeValue::FrontMatter(s[..i].to_vec()));
                 self.finalize(node).unwrap();
+                let last = s[..i]
+                    .iter()
+                    .rposition(|&c| !strings::is_line_end_char(c))
+                    .unwrap_or(0);
+                let (end_line, end_column) = self.line_column(last);
+                {
+                    let mut ast = node.data.borrow_mut();
+                    ast.start_line = 1;
+                    ast.start_column = 1;
+                    ast.end_line = end_line;
+                    ast.end_column = end_column;
+                }
+                // Count the front matter's lines, so that lines after it keep their numbers.
+                self.line_number = s[..i].iter().filter(|&&c| c == b'\n').count() as u32;
+                metadata::parse_with_diagnostics(
+                    metadata::front_matter_body(&s[..i], delimiter).as_bytes(),
+                    &|line, column| (line as u32 + 2, column),
+                    &mut self.diagnostics,
+                );
             }
         }
 
@@ -668,6 +1008,15 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
         self.blank = false;
         self.partially_consumed_tab = false;
 
+        let mut length = line.len();
+        if length > 0 && line[length - 1] == b'\n' {
+            length -= 1;
+        }
+        if length > 0 && line[length - 1] == b'\r' {
+            length -= 1;
+        }
+        self.curline_length = Some(length);
+
         if self.line_number == 0
             && line.len() >= 3
             && unsafe { str::from_utf8_unchecked(line) }.starts_with("\u{feff}")
@@ -689,13 +1038,8 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
             }
         }
 
-        self.last_line_length = line.len();
-        if self.last_line_length > 0 && line[self.last_line_length - 1] == b'\n' {
-            self.last_line_length -= 1;
-        }
-        if self.last_line_length > 0 && line[self.last_line_length - 1] == b'\r' {
-            self.last_line_length -= 1;
-        }
+        self.last_line_length = length;
+        self.curline_length = None;
     }
 
     fn check_open_blocks(
@@ -856,6 +1200,7 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                 *container = self.add_child(*container, NodeValue::Effect(effect));
             } */
             else if !indented
+                && follows_heading(container)
                 && unwrap_into(
                     scanners::open_slide_metadata(&line[self.first_nonspace..]),
                     &mut matched,
@@ -1043,6 +1388,7 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                 };
 
                 *container = self.add_child(*container, NodeValue::CodeBlock(ncb));
+                container.data.borrow_mut().start_column = self.offset + 1;
             } else {
                 let new_container = if !indented && self.options.extension.table {
                     table::try_opening_block(self, *container, line)
@@ -1198,7 +1544,9 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
         if matched >= fence_length {
             *should_continue = false;
             self.advance_offset(line, matched, false);
+            self.closing_line = true;
             self.current = self.finalize_borrowed(container, ast).unwrap();
+            self.closing_line = false;
             return false;
         }
 
@@ -1248,7 +1596,9 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
         if matched >= fence_length {
             *should_continue = false;
             self.advance_offset(line, matched, false);
+            self.closing_line = true;
             self.current = self.finalize_borrowed(container, ast).unwrap();
+            self.closing_line = false;
             return false;
         }
 
@@ -1318,6 +1668,7 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
 
         let mut child = Ast::new(value);
         child.start_line = self.line_number;
+        child.start_column = self.first_nonspace + 1;
         let node = self.arena.alloc(Node::new(RefCell::new(child)));
         parent.append(node);
         node
@@ -1402,7 +1753,9 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                     };
 
                     if matches_end_condition {
+                        self.closing_line = true;
                         container = self.finalize(container).unwrap();
+                        self.closing_line = false;
                     }
                 }
 
@@ -1452,13 +1805,19 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
     fn add_line(&mut self, node: &'a AstNode<'a>, line: &[u8]) {
         let mut ast = node.data.borrow_mut();
         assert!(ast.open);
+        let mut padding = 0;
         if self.partially_consumed_tab {
             self.offset += 1;
-            let chars_to_tab = TAB_STOP - (self.column % TAB_STOP);
-            for _ in 0..chars_to_tab {
+            padding = TAB_STOP - (self.column % TAB_STOP);
+            for _ in 0..padding {
                 ast.content.push(b' ');
             }
         }
+        if self.offset < line.len() || padding > 0 {
+            let line_start = self.line_starts[self.line_number as usize - 1];
+            ast.line_offsets
+                .push((line_start + self.offset).saturating_sub(padding));
+        }
         if self.offset < line.len() {
             ast.content.extend_from_slice(&line[self.offset..]);
         }
@@ -1467,15 +1826,24 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
     fn finish(&mut self) -> &'a AstNode<'a> {
         self.finalize_document();
         self.postprocess_text_nodes(self.root);
+        self.root.data.borrow_mut().line_offsets = mem::take(&mut self.line_starts);
         self.root
     }
 
+    /// The line and column of a byte offset in the input.
+    fn line_column(&self, offset: usize) -> (u32, usize) {
+        line_column(&self.line_starts, offset)
+    }
+
     fn finalize_document(&mut self) {
         while !self.current.same_node(self.root) {
             self.current = self.finalize(self.current).unwrap();
         }
 
         self.finalize(self.root);
+        if let Some(references) = self.references.take() {
+            self.references = Some(mem::replace(&mut self.refmap, references));
+        }
         self.process_inlines();
         if self.options.extension.footnotes {
             self.process_footnotes();
@@ -1548,6 +1916,22 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
         assert!(ast.open);
         ast.open = false;
 
+        let (end_line, end_column) = match self.curline_length {
+            Some(length) if self.closing_line => (self.line_number, length),
+            Some(_) => (self.line_number.saturating_sub(1), self.last_line_length),
+            None => (self.line_number, self.last_line_length),
+        };
+        ast.end_line = end_line;
+        ast.end_column = end_column;
+        if ast.value != NodeValue::Document && end_column == 0 {
+            // The block was closed by blank lines; it ends where its last child does.
+            if let Some(last) = node.last_child() {
+                let last = last.data.borrow();
+                ast.end_line = last.end_line;
+                ast.end_column = last.end_column;
+            }
+        }
+
         let content = &mut ast.content;
         let parent = node.parent();
 
@@ -1558,6 +1942,17 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                 if !has_content {
                     node.detach();
                 }
+
+                // Definitions are removed as whole lines from the start of the paragraph.
+                let lines = content.iter().filter(|&&c| c == b'\n').count()
+                    + usize::from(content.last().is_some_and(|&c| c != b'\n'));
+                let removed = ast.line_offsets.len().saturating_sub(lines);
+                if has_content && removed > 0 {
+                    ast.line_offsets.drain(..removed);
+                    let (line, column) = self.line_column(ast.line_offsets[0]);
+                    ast.start_line = line;
+                    ast.start_column = column + content.iter().take_while(|&&c| c == b' ').count();
+                }
             }
             // NodeValue::Effect(ref mut effect_attr) => {
             //     // TODO:
@@ -1616,11 +2011,60 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                     *content = content[pos..].to_vec();
                 }
                 mem::swap(&mut smd.literal, content);
+
+                if smd.fenced && !self.closing_line {
+                    self.diagnostics.push(
+                        DiagnosticCode::UnclosedMetadataFence,
+                        "metadata block is never closed; it runs to the end of its container"
+                            .to_string(),
+                        ast.start_line,
+                        ast.start_column,
+                    );
+                }
+                // A fenced block's first line is its fence, which is not part of the literal.
+                let skip = usize::from(smd.fenced);
+                let line_starts = &self.line_starts;
+                let offsets = &ast.line_offsets;
+                let position = |line: usize, column: usize| {
+                    let start = offsets
+                        .get(line + skip)
+                        .or_else(|| offsets.last())
+                        .map_or(0, |&start| start);
+                    line_column(line_starts, start + column - 1)
+                };
+                let entries = metadata::parse_with_diagnostics(
+                    &smd.literal,
+                    &position,
+                    &mut self.diagnostics,
+                );
+                smd.metadatas = entries.iter().map(|entry| entry.kv.clone()).collect();
+                for entry in entries {
+                    let mut child = Ast::new(NodeValue::KV(entry.kv));
+                    child.start_line = entry.start.0;
+                    child.start_column = entry.start.1;
+                    child.end_line = entry.end.0;
+                    child.end_column = entry.end.1;
+                    child.open = false;
+                    node.append(self.arena.alloc(Node::new(RefCell::new(child))));
+                }
             }
             NodeValue::CodeBlock(ref mut ncb) => {
                 if !ncb.fenced {
                     strings::remove_trailing_blank_lines(content);
                     content.push(b'\n');
+
+                    // The block ends at its last line which is not blank.
+                    let lines = content.iter().filter(|&&c| c == b'\n').count();
+                    if let Some(&start) = ast.line_offsets.get(lines - 1) {
+                        let length = content[..content.len() - 1]
+                            .iter()
+                            .rev()
+                            .take_while(|&&c| c != b'\n')
+                            .count();
+                        let (end_line, end_column) = self.line_column(start + length.max(1) - 1);
+                        ast.end_line = end_line;
+                        ast.end_column = end_column;
+                    }
                 } else {
                     let mut pos = 0;
                     while pos < content.len() {
@@ -1717,12 +2161,38 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
             &delimiter_arena,
             self.callback.as_mut(),
         );
+        subj.set_source(&node_data.line_offsets, &self.line_starts);
 
         while subj.parse_inline(node) {}
 
         subj.process_emphasis(None);
 
         while subj.pop_bracket() {}
+
+        let effects = subj
+            .effects
+            .iter()
+            .map(|&(pos, effect)| (subj.source_position(pos), effect))
+            .collect::<Vec<_>>();
+        for (position, effect) in effects {
+            if let NodeValue::Effect(ref effect) = effect.data.borrow().value {
+                let (code, message) = match effect.error {
+                    Some(ref error) => (DiagnosticCode::MalformedEffect, error.clone()),
+                    None if !self.is_known_effect(&effect.name) => (
+                        DiagnosticCode::UnknownEffect,
+                        format!("unknown effect `{}`", effect.name),
+                    ),
+                    None => continue,
+                };
+                let (line, column) =
+                    position.unwrap_or((node_data.start_line, node_data.start_column));
+                self.diagnostics.push(code, message, line, column);
+            }
+        }
+    }
+
+    fn is_known_effect(&self, name: &str) -> bool {
+        effect::EFFECTS.contains(&name) || self.options.parse.effects.iter().any(|e| e == name)
     }
 
     fn process_footnotes(&mut self) {
@@ -1814,7 +2284,9 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
             while let Some(n) = nch {
                 let mut this_bracket = false;
                 loop {
-                    match n.data.borrow_mut().value {
+                    let mut data = n.data.borrow_mut();
+                    let ast = &mut *data;
+                    match ast.value {
                         // Join adjacent text nodes together
                         NodeValue::Text(ref mut root) => {
                             let ns = match n.next_sibling() {
@@ -1828,9 +2300,14 @@ impl<'a, 'o, 'c> Parser<'a, 'o, 'c> {
                                 }
                             };
 
-                            match ns.data.borrow().value {
+                            let adjacent = ns.data.borrow();
+                            match adjacent.value {
                                 NodeValue::Text(ref adj) => {
                                     root.extend_from_slice(adj);
+                                    if adjacent.end_line > 0 {
+                                        ast.end_line = adjacent.end_line;
+                                        ast.end_column = adjacent.end_column;
+                                    }
                                     ns.detach();
                                 }
                                 _ => {
@@ -2056,6 +2533,16 @@ enum AddTextResult {
     Otherwise,
 }
 
+/// The line and column, counting from 1, of the byte at `offset` in an input whose lines start
+/// at `line_starts`.
+pub(crate) fn line_column(line_starts: &[usize], offset: usize) -> (u32, usize) {
+    let line = line_starts
+        .partition_point(|&start| start <= offset)
+        .clamp(1, line_starts.len().max(1));
+    let start = line_starts.get(line - 1).map_or(0, |&start| start);
+    (line as u32, offset.saturating_sub(start) + 1)
+}
+
 fn parse_list_marker(
     line: &[u8],
     mut pos: usize,
@@ -2189,6 +2676,15 @@ fn lists_match(list_data: &NodeList, item_data: &NodeList) -> bool {
         && list_data.bullet_char == item_data.bullet_char
 }
 
+/// A slide metadata fence may only open directly after a heading; anywhere else `---` is a
+/// thematic break or a setext underline.
+fn follows_heading<'a>(container: &'a AstNode<'a>) -> bool {
+    match container.last_child() {
+        Some(last) => matches!(last.data.borrow().value, NodeValue::Heading(..)),
+        None => false,
+    }
+}
+
 fn reopen_ast_nodes<'a>(mut ast: &'a AstNode<'a>) {
     loop {
         ast.data.borrow_mut().open = true;
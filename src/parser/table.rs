@@ -1,4 +1,5 @@
 use arena_tree::Node;
+use ctype::isspace;
 use nodes;
 use nodes::{Ast, AstNode, NodeValue, TableAlignment};
 use parser::Parser;
@@ -53,6 +54,22 @@ fn try_opening_header<'a, 'o, 'c>(
         );
     }
 
+    // The header row is the last line of the paragraph; find where it starts in the source.
+    let (header_start, header_end) = {
+        let ast = container.data.borrow();
+        let content = &ast.content;
+        let mut end = content.len();
+        while end > 0 && (content[end - 1] == b'\n' || content[end - 1] == b'\r') {
+            end -= 1;
+        }
+        let start = content[..end]
+            .iter()
+            .rposition(|&c| c == b'\n' || c == b'\r')
+            .map_or(0, |ix| ix + 1);
+        let source = ast.line_offsets.last().map_or(0, |&offset| offset);
+        (source, source + (end - start))
+    };
+
     let mut alignments = vec![];
     for cell in marker_row.cells {
         let left = !cell.is_empty() && cell[0] == b':';
@@ -68,15 +85,22 @@ fn try_opening_header<'a, 'o, 'c>(
         });
     }
 
+    let (start_line, start_column) = parser.line_column(header_start);
     let mut child = Ast::new(NodeValue::Table(alignments));
-    child.start_line = parser.line_number;
+    child.start_line = start_line;
+    child.start_column = start_column;
     let table = parser.arena.alloc(Node::new(RefCell::new(child)));
     container.append(table);
 
     let header = parser.add_child(table, NodeValue::TableRow(true));
-    for header_str in header_row.cells {
+    set_span(parser, header, header_start, header_end);
+    let line_start = header_row.line_start;
+    for (header_str, (start, end)) in header_row.cells.into_iter().zip(header_row.spans) {
         let header_cell = parser.add_child(header, NodeValue::TableCell);
         header_cell.data.borrow_mut().content = header_str;
+        let start = header_start + start - line_start;
+        set_span(parser, header_cell, start, header_start + end - line_start);
+        header_cell.data.borrow_mut().line_offsets = vec![start];
     }
 
     let offset = line.len() - 1 - parser.offset;
@@ -96,16 +120,24 @@ fn try_opening_row<'a, 'o, 'c>(
     }
     let this_row = row(&line[parser.first_nonspace..]).unwrap();
     let new_row = parser.add_child(container, NodeValue::TableRow(false));
+    let line_start = parser.line_starts[parser.line_number as usize - 1];
+    let row_start = line_start + parser.first_nonspace;
+    let row_end = line_start + parser.curline_length.unwrap_or(0);
+    set_span(parser, new_row, row_start, row_end);
 
     let mut i = 0;
     while i < min(alignments.len(), this_row.cells.len()) {
         let cell = parser.add_child(new_row, NodeValue::TableCell);
         cell.data.borrow_mut().content = this_row.cells[i].clone();
+        let (start, end) = this_row.spans[i];
+        set_span(parser, cell, row_start + start, row_start + end);
+        cell.data.borrow_mut().line_offsets = vec![row_start + start];
         i += 1;
     }
 
     while i < alignments.len() {
-        parser.add_child(new_row, NodeValue::TableCell);
+        let cell = parser.add_child(new_row, NodeValue::TableCell);
+        set_span(parser, cell, row_end, row_end);
         i += 1;
     }
 
@@ -117,13 +149,19 @@ fn try_opening_row<'a, 'o, 'c>(
 
 struct Row {
     paragraph_offset: usize,
+    /// Where the row's line starts in the string, after any preceding paragraph lines.
+    line_start: usize,
     cells: Vec<Vec<u8>>,
+    /// The start and end of each cell's trimmed contents in the string.
+    spans: Vec<(usize, usize)>,
 }
 
 fn row(string: &[u8]) -> Option<Row> {
     let len = string.len();
     let mut cells = vec![];
+    let mut spans = vec![];
     let mut offset = 0;
+    let mut line_start = 0;
 
     if len > 0 && string[0] == b'|' {
         offset += 1;
@@ -141,9 +179,20 @@ fn row(string: &[u8]) -> Option<Row> {
 
             if string[cell_end_offset] == b'\n' || string[cell_end_offset] == b'\r' {
                 paragraph_offset = cell_end_offset;
+                line_start = offset + cell_matched;
                 cells.clear();
+                spans.clear();
             } else {
-                let mut cell = unescape_pipes(&string[offset..offset + cell_matched]);
+                let raw = &string[offset..offset + cell_matched];
+                let leading = raw.iter().take_while(|&&c| isspace(c)).count();
+                let trailing = raw[leading..]
+                    .iter()
+                    .rev()
+                    .take_while(|&&c| isspace(c))
+                    .count();
+                spans.push((offset + leading, offset + cell_matched - trailing));
+
+                let mut cell = unescape_pipes(raw);
                 trim(&mut cell);
                 cells.push(cell);
             }
@@ -166,7 +215,9 @@ fn row(string: &[u8]) -> Option<Row> {
     } else {
         Some(Row {
             paragraph_offset: paragraph_offset,
+            line_start,
             cells: cells,
+            spans,
         })
     }
 }
@@ -188,10 +239,42 @@ fn try_inserting_table_header_paragraph<'a, 'o, 'c>(
 
     let mut paragraph = Ast::new(NodeValue::Paragraph);
     paragraph.content = paragraph_content;
+    {
+        let ast = container.data.borrow();
+        paragraph.start_line = ast.start_line;
+        paragraph.start_column = ast.start_column;
+        paragraph.line_offsets = ast.line_offsets[..ast.line_offsets.len() - 1].to_vec();
+        if let Some(&last) = paragraph.line_offsets.last() {
+            let length = parent_string[..paragraph_offset]
+                .iter()
+                .rev()
+                .take_while(|&&c| c != b'\n' && c != b'\r')
+                .count();
+            let (end_line, end_column) = parser.line_column(last + length.max(1) - 1);
+            paragraph.end_line = end_line;
+            paragraph.end_column = end_column;
+        }
+    }
     let node = parser.arena.alloc(Node::new(RefCell::new(paragraph)));
     container.insert_before(node);
 }
 
+/// Set the position of a node which is never finalized to the bytes `start..end` of the input.
+fn set_span<'a, 'o, 'c>(
+    parser: &Parser<'a, 'o, 'c>,
+    node: &'a AstNode<'a>,
+    start: usize,
+    end: usize,
+) {
+    let (start_line, start_column) = parser.line_column(start);
+    let (end_line, end_column) = parser.line_column(end.max(start + 1) - 1);
+    let mut ast = node.data.borrow_mut();
+    ast.start_line = start_line;
+    ast.start_column = start_column;
+    ast.end_line = end_line;
+    ast.end_column = end_column;
+}
+
 fn unescape_pipes(string: &[u8]) -> Vec<u8> {
     let len = string.len();
     let mut v = Vec::with_capacity(len);
@@ -0,0 +1,408 @@
+//! PowerPoint output: a deck written as an Office Open XML presentation package.
+
+mod slide;
+
+use self::slide::{xfrm, SlideWriter};
+use nodes::AstNode;
+use package::content::SlideContent;
+use package::layout::{self, Rect, NOTES_SIZE};
+use package::zip::ZipWriter;
+use package::ImageFormat;
+use package::{deck_theme, theme_color, theme_font, xml_escape};
+use parser::ComrakOptions;
+use slides::{build_deck, Deck};
+use std::io::{self, Write};
+use themes::Theme;
+
+const THEME_XML: &str = include_str!("theme.xml");
+const SLIDE_MASTER_XML: &str = include_str!("slide_master.xml");
+const SLIDE_LAYOUT_XML: &str = include_str!("slide_layout.xml");
+const NOTES_MASTER_XML: &str = include_str!("notes_master.xml");
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// The namespaces of PresentationML parts.
+const NAMESPACES: &str = "xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+                          xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+                          xmlns:p=\"http://schemas.openxmlformats.org/presentationml/2006/main\"";
+
+const CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.presentationml";
+
+/// Formats an AST as a PowerPoint presentation, with one slide per slide of the deck.
+///
+/// Each slide's first heading fills its title placeholder, and the rest of its content is
+/// laid out in the body, with a column per region.  Lists become bulleted or numbered
+/// paragraphs, tables become PowerPoint tables, and PNG, JPEG or GIF images are embedded from
+/// files within `options.render.base_dir`; other images, and all images when it is not set,
+/// are replaced by their alt text.  Speaker notes go on each slide's notes page.  Colours and
+/// fonts are taken from the deck's theme, as selected for `format_slide_standalone`.
+///
+/// The package is written as a ZIP archive to `output`, which should expect binary data.
+///
+/// ```
+/// # use comrak::{format_pptx, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Hello\n\n## World\n\n- one\n- two\n", &options);
+/// let mut pptx = vec![];
+/// format_pptx(root, &options, &mut pptx).unwrap();
+///
+/// assert!(pptx.starts_with(b"PK\x03\x04"));
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let theme = deck_theme(&deck, options)?;
+    let size = Rect::slide(deck.metadata.aspect_ratio.unwrap_or_default());
+
+    let mut package = Package::default();
+    let slides = deck
+        .cover
+        .iter()
+        .chain(deck.slides.iter())
+        .collect::<Vec<_>>();
+    let code_font = theme_font(&theme, "code").unwrap_or_else(|| "Consolas".to_string());
+    let lang = deck.metadata.language.as_deref();
+    let mut media = vec![];
+    let mut notes_count = 0;
+
+    for (ix, slide) in slides.iter().enumerate() {
+        let number = ix + 1;
+        let content = SlideContent::new(slide, &deck.agenda, options);
+        let mut writer = SlideWriter::new(&mut media, slides.len(), lang, &code_font);
+        let xml = writer.slide(&content, slide.layout, size);
+        let mut rels = writer.rels;
+
+        if !content.notes.is_empty() {
+            notes_count += 1;
+            rels.add(
+                "notesSlide",
+                format!("../notesSlides/notesSlide{}.xml", number),
+            );
+            let mut notes_rels = Relationships::default();
+            notes_rels.add(
+                "notesMaster",
+                "../notesMasters/notesMaster1.xml".to_string(),
+            );
+            notes_rels.add("slide", format!("../slides/slide{}.xml", number));
+            package.add(
+                &format!("ppt/notesSlides/notesSlide{}.xml", number),
+                Some(&format!("{}.notesSlide+xml", CONTENT_TYPE)),
+                slide::notes_slide(&content.notes, lang),
+            );
+            package.add_rels(
+                &format!("ppt/notesSlides/_rels/notesSlide{}.xml.rels", number),
+                &notes_rels,
+            );
+        }
+
+        package.add(
+            &format!("ppt/slides/slide{}.xml", number),
+            Some(&format!("{}.slide+xml", CONTENT_TYPE)),
+            xml,
+        );
+        package.add_rels(&format!("ppt/slides/_rels/slide{}.xml.rels", number), &rels);
+    }
+
+    for (name, data) in media {
+        package.add(&format!("ppt/media/{}", name), None, data);
+    }
+
+    package.add_presentation(&deck, &theme, size, slides.len(), notes_count);
+    package.write(output)
+}
+
+/// The relationships of a package part to other parts, with ids `rId1`, `rId2` and so on in
+/// the order they were added.
+#[derive(Default)]
+pub struct Relationships {
+    list: Vec<(&'static str, String, bool)>,
+}
+
+impl Relationships {
+    /// Add a relationship of type `kind` to the part at `target`, relative to the source part,
+    /// returning its id.  Adding the same relationship again returns the same id.
+    pub fn add(&mut self, kind: &'static str, target: String) -> String {
+        self.add_target(kind, target, false)
+    }
+
+    /// Add a relationship of type `kind` to a resource outside the package.
+    pub fn add_external(&mut self, kind: &'static str, target: String) -> String {
+        self.add_target(kind, target, true)
+    }
+
+    fn add_target(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        let relationship = (kind, target, external);
+        let ix = match self.list.iter().position(|r| *r == relationship) {
+            Some(ix) => ix,
+            None => {
+                self.list.push(relationship);
+                self.list.len() - 1
+            }
+        };
+        format!("rId{}", ix + 1)
+    }
+
+    fn xml(&self) -> String {
+        let mut xml = String::from(XML_DECLARATION);
+        xml += "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">";
+        for (ix, (kind, target, external)) in self.list.iter().enumerate() {
+            let namespace = match *kind {
+                "core-properties" => {
+                    "http://schemas.openxmlformats.org/package/2006/relationships/metadata"
+                }
+                _ => "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+            };
+            xml += &format!(
+                "<Relationship Id=\"rId{}\" Type=\"{}/{}\" Target=\"{}\"{}/>",
+                ix + 1,
+                namespace,
+                kind,
+                xml_escape(target),
+                if *external {
+                    " TargetMode=\"External\""
+                } else {
+                    ""
+                }
+            );
+        }
+        xml += "</Relationships>\n";
+        xml
+    }
+}
+
+/// The parts of a package, with their content types.
+#[derive(Default)]
+struct Package {
+    parts: Vec<(String, Option<String>, Vec<u8>)>,
+}
+
+impl Package {
+    /// Add a part.  Parts without a content type are typed by their extension.
+    fn add<D: Into<Vec<u8>>>(&mut self, name: &str, content_type: Option<&str>, data: D) {
+        self.parts.push((
+            name.to_string(),
+            content_type.map(str::to_string),
+            data.into(),
+        ));
+    }
+
+    fn add_rels(&mut self, name: &str, rels: &Relationships) {
+        self.add(name, None, rels.xml());
+    }
+
+    /// Add the presentation part, and the masters, theme and properties every presentation
+    /// has.
+    fn add_presentation(
+        &mut self,
+        deck: &Deck,
+        theme: &Theme,
+        size: Rect,
+        slides: usize,
+        notes: usize,
+    ) {
+        let mut rels = Relationships::default();
+        rels.add("officeDocument", "ppt/presentation.xml".to_string());
+        rels.add("core-properties", "docProps/core.xml".to_string());
+        rels.add("extended-properties", "docProps/app.xml".to_string());
+        self.add_rels("_rels/.rels", &rels);
+        self.add(
+            "docProps/core.xml",
+            Some("application/vnd.openxmlformats-package.core-properties+xml"),
+            core_properties(deck),
+        );
+        self.add(
+            "docProps/app.xml",
+            Some("application/vnd.openxmlformats-officedocument.extended-properties+xml"),
+            format!(
+                "{}<Properties xmlns=\"http://schemas.openxmlformats.org/officeDocument/2006/extended-properties\">\
+                 <Application>comrak</Application><Slides>{}</Slides><Notes>{}</Notes></Properties>\n",
+                XML_DECLARATION, slides, notes
+            ),
+        );
+
+        let mut rels = Relationships::default();
+        let master = rels.add("slideMaster", "slideMasters/slideMaster1.xml".to_string());
+        let notes_master = rels.add("notesMaster", "notesMasters/notesMaster1.xml".to_string());
+        rels.add("presProps", "presProps.xml".to_string());
+        rels.add("viewProps", "viewProps.xml".to_string());
+        rels.add("theme", "theme/theme1.xml".to_string());
+        rels.add("tableStyles", "tableStyles.xml".to_string());
+        let mut slide_ids = String::new();
+        for number in 1..=slides {
+            let id = rels.add("slide", format!("slides/slide{}.xml", number));
+            slide_ids += &format!("<p:sldId id=\"{}\" r:id=\"{}\"/>", 255 + number, id);
+        }
+        if !slide_ids.is_empty() {
+            slide_ids = format!("<p:sldIdLst>{}</p:sldIdLst>", slide_ids);
+        }
+
+        self.add(
+            "ppt/presentation.xml",
+            Some(&format!("{}.presentation.main+xml", CONTENT_TYPE)),
+            format!(
+                "{}<p:presentation {}><p:sldMasterIdLst><p:sldMasterId id=\"2147483648\" r:id=\"{}\"/></p:sldMasterIdLst>\
+                 <p:notesMasterIdLst><p:notesMasterId r:id=\"{}\"/></p:notesMasterIdLst>{}\
+                 <p:sldSz cx=\"{}\" cy=\"{}\"/><p:notesSz cx=\"{}\" cy=\"{}\"/></p:presentation>\n",
+                XML_DECLARATION,
+                NAMESPACES,
+                master,
+                notes_master,
+                slide_ids,
+                size.width,
+                size.height,
+                NOTES_SIZE.width,
+                NOTES_SIZE.height
+            ),
+        );
+        self.add_rels("ppt/_rels/presentation.xml.rels", &rels);
+        self.add(
+            "ppt/presProps.xml",
+            Some(&format!("{}.presProps+xml", CONTENT_TYPE)),
+            format!("{}<p:presentationPr {}/>\n", XML_DECLARATION, NAMESPACES),
+        );
+        self.add(
+            "ppt/viewProps.xml",
+            Some(&format!("{}.viewProps+xml", CONTENT_TYPE)),
+            format!("{}<p:viewPr {}/>\n", XML_DECLARATION, NAMESPACES),
+        );
+        self.add(
+            "ppt/tableStyles.xml",
+            Some(&format!("{}.tableStyles+xml", CONTENT_TYPE)),
+            format!(
+                "{}<a:tblStyleLst xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" def=\"{}\"/>\n",
+                XML_DECLARATION,
+                slide::TABLE_STYLE
+            ),
+        );
+
+        let title = layout::title_rect(None, size);
+        let body = layout::body_rect(None, size);
+        self.add(
+            "ppt/slideMasters/slideMaster1.xml",
+            Some(&format!("{}.slideMaster+xml", CONTENT_TYPE)),
+            SLIDE_MASTER_XML
+                .replace("{{title_xfrm}}", &xfrm(title))
+                .replace("{{body_xfrm}}", &xfrm(body)),
+        );
+        let mut rels = Relationships::default();
+        rels.add(
+            "slideLayout",
+            "../slideLayouts/slideLayout1.xml".to_string(),
+        );
+        rels.add("theme", "../theme/theme1.xml".to_string());
+        self.add_rels("ppt/slideMasters/_rels/slideMaster1.xml.rels", &rels);
+
+        self.add(
+            "ppt/slideLayouts/slideLayout1.xml",
+            Some(&format!("{}.slideLayout+xml", CONTENT_TYPE)),
+            SLIDE_LAYOUT_XML,
+        );
+        let mut rels = Relationships::default();
+        rels.add(
+            "slideMaster",
+            "../slideMasters/slideMaster1.xml".to_string(),
+        );
+        self.add_rels("ppt/slideLayouts/_rels/slideLayout1.xml.rels", &rels);
+
+        let (image, notes_text) = layout::notes_rects(size);
+        self.add(
+            "ppt/notesMasters/notesMaster1.xml",
+            Some(&format!("{}.notesMaster+xml", CONTENT_TYPE)),
+            NOTES_MASTER_XML
+                .replace("{{image_xfrm}}", &xfrm(image))
+                .replace("{{notes_xfrm}}", &xfrm(notes_text)),
+        );
+        let mut rels = Relationships::default();
+        rels.add("theme", "../theme/theme2.xml".to_string());
+        self.add_rels("ppt/notesMasters/_rels/notesMaster1.xml.rels", &rels);
+
+        let theme_xml = theme_xml(theme);
+        for number in 1..=2 {
+            self.add(
+                &format!("ppt/theme/theme{}.xml", number),
+                Some("application/vnd.openxmlformats-officedocument.theme+xml"),
+                theme_xml.clone(),
+            );
+        }
+    }
+
+    /// Write the package as a ZIP archive, starting with its content types.
+    fn write(self, output: &mut dyn Write) -> io::Result<()> {
+        let mut types = String::from(XML_DECLARATION);
+        types += "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">";
+        types += "<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>";
+        types += "<Default Extension=\"xml\" ContentType=\"application/xml\"/>";
+        for format in &[ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Gif] {
+            let extension = format!(".{}", format.extension());
+            if self
+                .parts
+                .iter()
+                .any(|(name, _, _)| name.ends_with(&extension))
+            {
+                types += &format!(
+                    "<Default Extension=\"{}\" ContentType=\"{}\"/>",
+                    format.extension(),
+                    format.mime_type()
+                );
+            }
+        }
+        for (name, content_type, _) in &self.parts {
+            if let Some(content_type) = content_type {
+                types += &format!(
+                    "<Override PartName=\"/{}\" ContentType=\"{}\"/>",
+                    name, content_type
+                );
+            }
+        }
+        types += "</Types>\n";
+
+        let mut zip = ZipWriter::new(output);
+        zip.add("[Content_Types].xml", types.as_bytes())?;
+        for (name, _, data) in &self.parts {
+            zip.add(name, data)?;
+        }
+        zip.finish()
+    }
+}
+
+fn core_properties(deck: &Deck) -> String {
+    let mut xml = format!(
+        "{}<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+        XML_DECLARATION
+    );
+    let title = deck
+        .metadata
+        .title
+        .as_deref()
+        .or_else(|| deck.cover.as_ref().map(|c| c.title.as_str()))
+        .filter(|t| !t.is_empty());
+    if let Some(title) = title {
+        xml += &format!("<dc:title>{}</dc:title>", xml_escape(title));
+    }
+    if let Some(ref author) = deck.metadata.author {
+        xml += &format!("<dc:creator>{}</dc:creator>", xml_escape(author));
+    }
+    if let Some(ref language) = deck.metadata.language {
+        xml += &format!("<dc:language>{}</dc:language>", xml_escape(language));
+    }
+    xml += "</cp:coreProperties>\n";
+    xml
+}
+
+fn theme_xml(theme: &Theme) -> String {
+    let fallback = |role| theme_font(theme, role).unwrap_or_else(|| "Calibri".to_string());
+    THEME_XML
+        .replace("{{name}}", &xml_escape(&theme.name))
+        .replace("{{dk1}}", &theme_color(theme, "foreground"))
+        .replace("{{lt1}}", &theme_color(theme, "background"))
+        .replace("{{dk2}}", &theme_color(theme, "heading"))
+        .replace("{{lt2}}", &theme_color(theme, "muted"))
+        .replace("{{accent}}", &theme_color(theme, "accent"))
+        .replace("{{heading_font}}", &xml_escape(&fallback("heading")))
+        .replace("{{body_font}}", &xml_escape(&fallback("body")))
+}
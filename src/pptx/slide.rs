@@ -0,0 +1,408 @@
+//! The XML of slides and notes pages: a slide's content laid out as shapes.
+
+use super::{Relationships, NAMESPACES, XML_DECLARATION};
+use nodes::TableAlignment;
+use package::content::{
+    Block, Marker, Paragraph, ParagraphKind, Picture, Run, SlideContent, Table,
+};
+use package::layout::{
+    picture_rect, region_rects, table_row_height, title_rect, Rect, RegionLayout,
+};
+use package::xml_escape;
+use slides::Layout;
+
+/// The built-in PowerPoint table style tables are drawn in: Medium Style 2, Accent 1.
+pub const TABLE_STYLE: &str = "{5C22544A-7EE6-4342-B048-85BDC9FD1C3A}";
+
+/// The indent of each level of list nesting, in EMUs.
+const LIST_INDENT: i64 = 457_200;
+
+/// The gap between a bullet and its text, in EMUs.
+const BULLET_INDENT: i64 = 285_750;
+
+/// A rectangle as a shape's `a:xfrm`.
+pub fn xfrm(rect: Rect) -> String {
+    format!(
+        "<a:xfrm><a:off x=\"{}\" y=\"{}\"/><a:ext cx=\"{}\" cy=\"{}\"/></a:xfrm>",
+        rect.x, rect.y, rect.width, rect.height
+    )
+}
+
+/// Writes the XML of one slide, collecting the relationships and media it needs.
+pub struct SlideWriter<'m> {
+    /// The slide's relationships: the slide layout first, then images and links.
+    pub rels: Relationships,
+
+    /// The images of the whole presentation, by file name within `ppt/media`.
+    media: &'m mut Vec<(String, Vec<u8>)>,
+
+    slides: usize,
+    lang: Option<&'m str>,
+    code_font: &'m str,
+
+    /// The id of the next shape.
+    next_id: usize,
+
+    /// Whether the content placeholder has been filled, so that further text goes in text
+    /// boxes.
+    placeholder_used: bool,
+}
+
+impl<'m> SlideWriter<'m> {
+    pub fn new(
+        media: &'m mut Vec<(String, Vec<u8>)>,
+        slides: usize,
+        lang: Option<&'m str>,
+        code_font: &'m str,
+    ) -> Self {
+        let mut rels = Relationships::default();
+        rels.add(
+            "slideLayout",
+            "../slideLayouts/slideLayout1.xml".to_string(),
+        );
+        SlideWriter {
+            rels,
+            media,
+            slides,
+            lang,
+            code_font,
+            next_id: 2,
+            placeholder_used: false,
+        }
+    }
+
+    /// The slide's XML.  Its regions are laid out side by side, or one above the other for the
+    /// quote layout; within a region, text is set beside any tables and pictures.
+    pub fn slide(&mut self, content: &SlideContent, layout: Option<Layout>, size: Rect) -> String {
+        let mut shapes = String::new();
+        if !content.title.is_empty() {
+            shapes += &self.title(&content.title, layout, size);
+        }
+
+        let frames = region_rects(layout, size, content.regions.len());
+        for (blocks, frame) in content.regions.iter().zip(frames) {
+            shapes += &self.region(blocks, frame);
+        }
+
+        format!(
+            "{}<p:sld {}><p:cSld><p:spTree>{}{}</p:spTree></p:cSld>\
+             <p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sld>\n",
+            XML_DECLARATION, NAMESPACES, GROUP_PROPERTIES, shapes
+        )
+    }
+
+    fn shape_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn title(&mut self, title: &str, layout: Option<Layout>, size: Rect) -> String {
+        let align = match layout {
+            Some(Layout::Title) | Some(Layout::Section) => "<a:pPr algn=\"ctr\"/>",
+            _ => "",
+        };
+        let id = self.shape_id();
+        format!(
+            "<p:sp><p:nvSpPr><p:cNvPr id=\"{}\" name=\"Title {}\"/><p:cNvSpPr><a:spLocks noGrp=\"1\"/></p:cNvSpPr>\
+             <p:nvPr><p:ph type=\"title\"/></p:nvPr></p:nvSpPr><p:spPr>{}</p:spPr>\
+             <p:txBody><a:bodyPr/><a:lstStyle/><a:p>{}<a:r><a:rPr{}/><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp>",
+            id,
+            id - 1,
+            xfrm(title_rect(layout, size)),
+            align,
+            self.lang_attribute(),
+            xml_escape(title)
+        )
+    }
+
+    fn region(&mut self, blocks: &[Block], frame: Rect) -> String {
+        let region = RegionLayout::new(blocks, frame);
+        let mut xml = String::new();
+        if !region.paragraphs.is_empty() {
+            xml += &self.text_box(&region.paragraphs, region.text);
+        }
+        for (block, rect) in region.objects {
+            match block {
+                Block::Table(table) => xml += &self.table(table, rect),
+                Block::Picture(picture) => xml += &self.picture(picture, rect),
+                Block::Paragraph(_) => (),
+            }
+        }
+        xml
+    }
+
+    /// A shape holding paragraphs: the content placeholder the first time, and a text box
+    /// after that.
+    fn text_box(&mut self, paragraphs: &[&Paragraph], rect: Rect) -> String {
+        let id = self.shape_id();
+        let (name, locks, placeholder, geometry) = if self.placeholder_used {
+            (
+                "TextBox",
+                "<p:cNvSpPr txBox=\"1\"/>",
+                "<p:nvPr/>",
+                "<a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom>",
+            )
+        } else {
+            self.placeholder_used = true;
+            (
+                "Content Placeholder",
+                "<p:cNvSpPr><a:spLocks noGrp=\"1\"/></p:cNvSpPr>",
+                "<p:nvPr><p:ph idx=\"1\"/></p:nvPr>",
+                "",
+            )
+        };
+
+        let mut body = String::new();
+        for paragraph in paragraphs {
+            body += &self.paragraph(paragraph);
+        }
+        format!(
+            "<p:sp><p:nvSpPr><p:cNvPr id=\"{}\" name=\"{} {}\"/>{}{}</p:nvSpPr><p:spPr>{}{}</p:spPr>\
+             <p:txBody><a:bodyPr wrap=\"square\"><a:normAutofit/></a:bodyPr><a:lstStyle/>{}</p:txBody></p:sp>",
+            id,
+            name,
+            id - 1,
+            locks,
+            placeholder,
+            xfrm(rect),
+            geometry,
+            body
+        )
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String {
+        let level = paragraph.level.saturating_sub(1).min(8);
+        let indent = LIST_INDENT * level as i64;
+        let spacing = match paragraph.kind {
+            ParagraphKind::Code => "<a:spcBef><a:spcPts val=\"0\"/></a:spcBef>",
+            _ => "",
+        };
+        let properties = match paragraph.marker {
+            Marker::Bullet => format!(
+                "<a:pPr marL=\"{}\" lvl=\"{}\" indent=\"-{}\">{}<a:buFont typeface=\"Arial\"/><a:buChar char=\"&#8226;\"/></a:pPr>",
+                indent + BULLET_INDENT,
+                level,
+                BULLET_INDENT,
+                spacing
+            ),
+            Marker::Number(number) => format!(
+                "<a:pPr marL=\"{}\" lvl=\"{}\" indent=\"-{}\">{}<a:buFont typeface=\"+mj-lt\"/><a:buAutoNum type=\"arabicPeriod\" startAt=\"{}\"/></a:pPr>",
+                indent + BULLET_INDENT,
+                level,
+                BULLET_INDENT,
+                spacing,
+                number
+            ),
+            Marker::None => {
+                let margin = if paragraph.level > 0 {
+                    indent + BULLET_INDENT
+                } else if paragraph.kind == ParagraphKind::Quote {
+                    LIST_INDENT
+                } else {
+                    0
+                };
+                format!(
+                    "<a:pPr marL=\"{}\" lvl=\"{}\" indent=\"0\">{}<a:buNone/></a:pPr>",
+                    margin, level, spacing
+                )
+            }
+        };
+
+        let size = match paragraph.kind {
+            ParagraphKind::Heading(_) => 2800,
+            ParagraphKind::Code => 1600,
+            _ if paragraph.level <= 1 => 2400,
+            _ if paragraph.level == 2 => 2000,
+            _ => 1800,
+        };
+        let code = paragraph.kind == ParagraphKind::Code;
+        format!(
+            "<a:p>{}{}</a:p>",
+            properties,
+            self.runs(&paragraph.runs, size, code, false)
+        )
+    }
+
+    /// The runs of a paragraph, `size` in hundredths of a point, ending with the paragraph's
+    /// own run properties.
+    fn runs(&mut self, runs: &[Run], size: u32, code: bool, bold: bool) -> String {
+        let mut xml = String::new();
+        for run in runs {
+            match run {
+                Run::Break => xml += "<a:br/>",
+                Run::Text(text, _) if text.is_empty() => (),
+                Run::Text(text, style) => {
+                    let mut attributes = format!("{} sz=\"{}\"", self.lang_attribute(), size);
+                    if style.bold || bold {
+                        attributes += " b=\"1\"";
+                    }
+                    if style.italic {
+                        attributes += " i=\"1\"";
+                    }
+                    if style.strike {
+                        attributes += " strike=\"sngStrike\"";
+                    }
+                    if style.superscript {
+                        attributes += " baseline=\"30000\"";
+                    }
+
+                    let mut children = String::new();
+                    if style.code || code {
+                        children +=
+                            &format!("<a:latin typeface=\"{}\"/>", xml_escape(self.code_font));
+                    }
+                    if let Some(ref link) = style.link {
+                        children += &self.hyperlink(link);
+                    }
+
+                    let properties = if children.is_empty() {
+                        format!("<a:rPr{}/>", attributes)
+                    } else {
+                        format!("<a:rPr{}>{}</a:rPr>", attributes, children)
+                    };
+                    xml += &format!("<a:r>{}<a:t>{}</a:t></a:r>", properties, xml_escape(text));
+                }
+            }
+        }
+        xml + &format!("<a:endParaRPr{} sz=\"{}\"/>", self.lang_attribute(), size)
+    }
+
+    /// The `a:hlinkClick` of a link: a jump to a slide for `#slide-N` links, and otherwise an
+    /// external hyperlink.  Other links within the deck are dropped.
+    fn hyperlink(&mut self, link: &str) -> String {
+        if let Some(anchor) = link.strip_prefix('#') {
+            let number = anchor
+                .strip_prefix("slide-")
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n >= 1 && n <= self.slides);
+            return match number {
+                Some(number) => format!(
+                    "<a:hlinkClick r:id=\"{}\" action=\"ppaction://hlinksldjump\"/>",
+                    self.rels.add("slide", format!("slide{}.xml", number))
+                ),
+                None => String::new(),
+            };
+        }
+        format!(
+            "<a:hlinkClick r:id=\"{}\"/>",
+            self.rels.add_external("hyperlink", link.to_string())
+        )
+    }
+
+    fn table(&mut self, table: &Table, rect: Rect) -> String {
+        let columns = table
+            .rows
+            .iter()
+            .map(Vec::len)
+            .chain(Some(table.alignments.len()))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let width = rect.width / columns as i64;
+        let height = table_row_height(rect, table.rows.len());
+
+        let mut grid = String::new();
+        for _ in 0..columns {
+            grid += &format!("<a:gridCol w=\"{}\"/>", width);
+        }
+        let mut rows = String::new();
+        for (ix, row) in table.rows.iter().enumerate() {
+            rows += &format!("<a:tr h=\"{}\">", height);
+            for column in 0..columns {
+                let align = match table.alignments.get(column) {
+                    Some(TableAlignment::Center) => "<a:pPr algn=\"ctr\"/>",
+                    Some(TableAlignment::Right) => "<a:pPr algn=\"r\"/>",
+                    _ => "",
+                };
+                let runs = row.get(column).map_or(&[][..], Vec::as_slice);
+                rows += &format!(
+                    "<a:tc><a:txBody><a:bodyPr/><a:lstStyle/><a:p>{}{}</a:p></a:txBody><a:tcPr/></a:tc>",
+                    align,
+                    self.runs(runs, 1600, false, ix == 0)
+                );
+            }
+            rows += "</a:tr>";
+        }
+
+        let id = self.shape_id();
+        format!(
+            "<p:graphicFrame><p:nvGraphicFramePr><p:cNvPr id=\"{}\" name=\"Table {}\"/>\
+             <p:cNvGraphicFramePr><a:graphicFrameLocks noGrp=\"1\"/></p:cNvGraphicFramePr><p:nvPr/></p:nvGraphicFramePr>\
+             <p:xfrm><a:off x=\"{}\" y=\"{}\"/><a:ext cx=\"{}\" cy=\"{}\"/></p:xfrm>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/table\">\
+             <a:tbl><a:tblPr firstRow=\"1\" bandRow=\"1\"><a:tableStyleId>{}</a:tableStyleId></a:tblPr>\
+             <a:tblGrid>{}</a:tblGrid>{}</a:tbl></a:graphicData></a:graphic></p:graphicFrame>",
+            id,
+            id - 1,
+            rect.x,
+            rect.y,
+            width * columns as i64,
+            height * table.rows.len() as i64,
+            TABLE_STYLE,
+            grid,
+            rows
+        )
+    }
+
+    /// A picture, placed within `rect`.
+    fn picture(&mut self, picture: &Picture, rect: Rect) -> String {
+        let name = format!(
+            "image{}.{}",
+            self.media.len() + 1,
+            picture.image.format.extension()
+        );
+        let rel = self.rels.add("image", format!("../media/{}", name));
+        self.media.push((name, picture.image.data.clone()));
+
+        let placed = picture_rect(picture, rect);
+
+        let id = self.shape_id();
+        format!(
+            "<p:pic><p:nvPicPr><p:cNvPr id=\"{}\" name=\"Picture {}\" descr=\"{}\"/>\
+             <p:cNvPicPr><a:picLocks noChangeAspect=\"1\"/></p:cNvPicPr><p:nvPr/></p:nvPicPr>\
+             <p:blipFill><a:blip r:embed=\"{}\"/><a:stretch><a:fillRect/></a:stretch></p:blipFill>\
+             <p:spPr>{}<a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></p:spPr></p:pic>",
+            id,
+            id - 1,
+            xml_escape(&picture.alt),
+            rel,
+            xfrm(placed)
+        )
+    }
+
+    fn lang_attribute(&self) -> String {
+        self.lang.map_or_else(String::new, |lang| {
+            format!(" lang=\"{}\"", xml_escape(lang))
+        })
+    }
+}
+
+/// The properties of a slide's shape tree, which every slide starts with.
+const GROUP_PROPERTIES: &str = "<p:nvGrpSpPr><p:cNvPr id=\"1\" name=\"\"/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr>\
+     <p:grpSpPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"0\" cy=\"0\"/><a:chOff x=\"0\" y=\"0\"/><a:chExt cx=\"0\" cy=\"0\"/></a:xfrm></p:grpSpPr>";
+
+/// The XML of a notes page: the slide image, and the notes below it, one paragraph per line.
+pub fn notes_slide(notes: &[String], lang: Option<&str>) -> String {
+    let lang = lang.map_or_else(String::new, |lang| {
+        format!(" lang=\"{}\"", xml_escape(lang))
+    });
+    let mut paragraphs = String::new();
+    for line in notes.iter().flat_map(|note| note.trim_end().lines()) {
+        paragraphs += &format!(
+            "<a:p><a:r><a:rPr{}/><a:t>{}</a:t></a:r></a:p>",
+            lang,
+            xml_escape(line)
+        );
+    }
+    format!(
+        "{}<p:notes {}><p:cSld><p:spTree>{}\
+         <p:sp><p:nvSpPr><p:cNvPr id=\"2\" name=\"Slide Image 1\"/><p:cNvSpPr><a:spLocks noGrp=\"1\" noRot=\"1\" noChangeAspect=\"1\"/></p:cNvSpPr>\
+         <p:nvPr><p:ph type=\"sldImg\" idx=\"2\"/></p:nvPr></p:nvSpPr><p:spPr/></p:sp>\
+         <p:sp><p:nvSpPr><p:cNvPr id=\"3\" name=\"Notes 2\"/><p:cNvSpPr><a:spLocks noGrp=\"1\"/></p:cNvSpPr>\
+         <p:nvPr><p:ph type=\"body\" idx=\"3\"/></p:nvPr></p:nvSpPr><p:spPr/>\
+         <p:txBody><a:bodyPr/><a:lstStyle/>{}</p:txBody></p:sp>\
+         </p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:notes>\n",
+        XML_DECLARATION, NAMESPACES, GROUP_PROPERTIES, paragraphs
+    )
+}
@@ -0,0 +1,1253 @@
+//! The slide model: a parsed document viewed as a deck of slides.
+//!
+//! ```
+//! use comrak::{parse_deck, Arena, ComrakOptions};
+//!
+//! let arena = Arena::new();
+//! let deck = parse_deck(
+//!     &arena,
+//!     "# My talk\n\n## First\n\n- one\n- two\n\n## Second\n\nBye.\n",
+//!     &ComrakOptions::default(),
+//! );
+//!
+//! assert_eq!(deck.cover.unwrap().title, "My talk");
+//! assert_eq!(deck.slides.len(), 2);
+//! assert_eq!(deck.slides[0].title, "First");
+//! assert_eq!(deck.slides[1].html, "<h2>Second</h2>\n<p>Bye.</p>\n");
+//! ```
+
+use html;
+use nodes::{AstNode, MetaValue, NodeCode, NodeKV, NodeValue};
+use parser::metadata;
+use parser::{parse_document, ComrakOptions, SlideSplitOptions};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::slice;
+use typed_arena::Arena;
+
+/// A document split into slides.
+#[derive(Debug, Clone)]
+pub struct Deck<'a> {
+    /// The cover slide, i.e. the first slide of the deck.  `None` if the document has no
+    /// slide content at all.
+    pub cover: Option<Slide<'a>>,
+
+    /// The slides following the cover, in document order.
+    pub slides: Vec<Slide<'a>>,
+
+    /// Deck-level metadata, read from the document's front matter.
+    pub metadata: DeckMetadata,
+
+    /// The deck's sections, as listed by agenda slides.
+    pub agenda: Vec<AgendaEntry>,
+}
+
+impl<'a> Deck<'a> {
+    /// The slide numbered `number`, counting the cover as 1.
+    pub fn slide(&self, number: usize) -> Option<&Slide<'a>> {
+        self.cover
+            .iter()
+            .chain(self.slides.iter())
+            .nth(number.checked_sub(1)?)
+    }
+
+    /// The number of the slide whose source contains `line`, counting the cover as 1.  `None`
+    /// for lines of the front matter, and lines past the end of the document.
+    ///
+    /// ```
+    /// # use comrak::{parse_deck, Arena, ComrakOptions};
+    /// let arena = Arena::new();
+    /// let deck = parse_deck(&arena, "# Talk\n\nHello.\n\n## Next\n", &ComrakOptions::default());
+    ///
+    /// assert_eq!(deck.slide_at_line(3), Some(1));
+    /// assert_eq!(deck.slide_at_line(5), Some(2));
+    /// assert_eq!(deck.slide_at_line(6), None);
+    /// ```
+    pub fn slide_at_line(&self, line: u32) -> Option<usize> {
+        self.cover
+            .iter()
+            .chain(self.slides.iter())
+            .position(|slide| {
+                slide
+                    .source
+                    .is_some_and(|s| s.start_line <= line && line <= s.end_line)
+            })
+            .map(|ix| ix + 1)
+    }
+
+    /// The source of the slide numbered `number`, counting the cover as 1.  `None` for a
+    /// generated slide, such as the agenda.
+    ///
+    /// ```
+    /// # use comrak::{parse_deck, Arena, ComrakOptions};
+    /// let arena = Arena::new();
+    /// let input = "# Talk\n\nHello.\n\n## Next\n";
+    /// let deck = parse_deck(&arena, input, &ComrakOptions::default());
+    /// let source = deck.source_range(2).unwrap();
+    ///
+    /// assert_eq!((source.start_line, source.end_line), (5, 5));
+    /// assert_eq!(&input[source.start_byte..source.end_byte], "## Next\n");
+    /// ```
+    pub fn source_range(&self, number: usize) -> Option<SourceRange> {
+        self.slide(number)?.source
+    }
+}
+
+/// The part of the source a slide was written in: whole lines, from the line which started the
+/// slide up to the line before the next slide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourceRange {
+    /// The first line, counting from 1.
+    pub start_line: u32,
+
+    /// The last line, inclusive.
+    pub end_line: u32,
+
+    /// The byte offset of the start of the first line.
+    pub start_byte: usize,
+
+    /// The byte offset just past the end of the last line, including its line ending.
+    pub end_byte: usize,
+}
+
+/// A section of a deck, as listed by agenda slides: a slide after the cover whose first heading
+/// is within the deck's `agenda-level` metadata (1 by default).
+///
+/// A slide lists the agenda in place of a `::toc` paragraph, or after its content if its own
+/// metadata sets `agenda: true`.  Setting `agenda: true` in the front matter instead adds a
+/// generated agenda slide after the cover, titled by the `agenda-title` metadata.
+///
+/// ```
+/// # use comrak::{parse_deck, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let deck = parse_deck(
+///     &arena,
+///     "# Talk\n\n::toc\n\n# Why\n\n## Detail\n\n# How\n",
+///     &ComrakOptions::default(),
+/// );
+///
+/// assert_eq!(deck.agenda.len(), 2);
+/// assert_eq!(deck.agenda[1].title, "How");
+/// assert_eq!(deck.agenda[1].anchor, "slide-4");
+/// assert_eq!(deck.slides[1].section, Some(0));
+/// assert!(deck.cover.unwrap().html.contains("<li class=\"level-1\"><a href=\"#slide-2\">Why</a></li>"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgendaEntry {
+    /// The section's title.
+    pub title: String,
+
+    /// The level of the heading the title was taken from.
+    pub level: u32,
+
+    /// The number of the slide starting the section, counting the cover as 1.
+    pub slide: usize,
+
+    /// The slide's anchor, such as `slide-2`, which standalone output uses as its `id`.
+    pub anchor: String,
+}
+
+/// The anchor of the slide numbered `number`, counting the cover as 1.
+pub fn slide_anchor(number: usize) -> String {
+    format!("slide-{}", number)
+}
+
+/// The start of a link to a slide anchor: `#/` in HTML for reveal.js, which routes slides by
+/// the path in the URL's hash, or else `#`.
+pub(crate) fn anchor_prefix(options: &ComrakOptions) -> &'static str {
+    if options.render.reveal {
+        "#/"
+    } else {
+        "#"
+    }
+}
+
+/// An agenda as an HTML list, marking the `current` section.  Its links start with `prefix`, as
+/// given by `anchor_prefix`.
+pub(crate) fn agenda_html(agenda: &[AgendaEntry], current: Option<usize>, prefix: &str) -> String {
+    let mut html = String::from("<ul class=\"agenda\">\n");
+    for (ix, entry) in agenda.iter().enumerate() {
+        let current = if current == Some(ix) { " current" } else { "" };
+        html += &format!(
+            "<li class=\"level-{}{}\"><a href=\"{}{}\">{}</a></li>\n",
+            entry.level,
+            current,
+            prefix,
+            entry.anchor,
+            html::escape_text(&entry.title)
+        );
+    }
+    html += "</ul>\n";
+    html
+}
+
+/// Deck-level metadata, read from the document's front matter.  Front matter is only
+/// recognised with `ComrakExtensionOptions::front_matter_delimiter` set.
+///
+/// ```
+/// # use comrak::{parse_deck, Arena, ComrakOptions};
+/// let mut options = ComrakOptions::default();
+/// options.extension.front_matter_delimiter = Some("---".to_string());
+/// let arena = Arena::new();
+/// let deck = parse_deck(
+///     &arena,
+///     "---\ntitle: Talk\ntheme: dark\n---\n\n# Cover\n\n## Light\n---\ntheme: light\n---\n",
+///     &options,
+/// );
+///
+/// assert_eq!(deck.metadata.title.as_deref(), Some("Talk"));
+/// assert_eq!(deck.cover.unwrap().metadata.get_str("theme"), Some("dark"));
+/// assert_eq!(deck.slides[0].metadata.get_str("theme"), Some("light"));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DeckMetadata {
+    /// The `title` of the deck.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// The `author` of the deck.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    /// The `date` of the deck, as written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+
+    /// The name of the deck's `theme`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+
+    /// The `aspect_ratio` (or `aspect-ratio`) of the slides, such as `16:9`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<AspectRatio>,
+
+    /// The `language` (or `lang`) of the deck, such as `en`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// The deck's default transitions.
+    #[serde(flatten)]
+    pub transitions: Transitions,
+
+    /// Every front matter entry, including the ones above.  These are the defaults each
+    /// slide's metadata starts from.
+    #[serde(rename = "meta")]
+    pub entries: Metadata,
+}
+
+impl DeckMetadata {
+    /// Read the known deck keys from `entries`.
+    pub fn from_entries(entries: Metadata) -> Self {
+        let text = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|k| entries.get(k))
+                .map(|v| v.to_string())
+                .next()
+        };
+
+        DeckMetadata {
+            title: text(&["title"]),
+            author: text(&["author"]),
+            date: text(&["date"]),
+            theme: text(&["theme"]),
+            aspect_ratio: text(&["aspect_ratio", "aspect-ratio"])
+                .and_then(|r| AspectRatio::parse(&r)),
+            language: text(&["language", "lang"]),
+            transitions: Transitions::from_metadata(&entries),
+            entries,
+        }
+    }
+}
+
+/// How a slide and its background are animated in, read from the `transition`,
+/// `transition-speed` and `background-transition` metadata keys (or their `_` spellings).
+/// Unknown values are ignored.  Slides inherit the deck's front matter values unless they set
+/// their own.
+///
+/// ```
+/// # use comrak::{parse_deck, Arena, ComrakOptions};
+/// use comrak::slides::{Transition, TransitionSpeed};
+///
+/// let mut options = ComrakOptions::default();
+/// options.extension.front_matter_delimiter = Some("---".to_string());
+/// let arena = Arena::new();
+/// let deck = parse_deck(
+///     &arena,
+///     "---\ntransition: fade\n---\n\n# Cover\n\n## Fast\n---\ntransition-speed: fast\n---\n",
+///     &options,
+/// );
+///
+/// let fast = &deck.slides[0].transitions;
+/// assert_eq!(fast.transition, Some(Transition::Fade));
+/// assert_eq!(fast.speed, Some(TransitionSpeed::Fast));
+/// assert_eq!(fast.background, None);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Transitions {
+    /// The `transition` of the slide's content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition: Option<Transition>,
+
+    /// The `transition-speed`.
+    #[serde(rename = "transition_speed", skip_serializing_if = "Option::is_none")]
+    pub speed: Option<TransitionSpeed>,
+
+    /// The `background-transition` of the slide's background.
+    #[serde(
+        rename = "background_transition",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub background: Option<Transition>,
+}
+
+impl Transitions {
+    /// Read the transition keys from `metadata`.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let value = |keys: &[&str]| keys.iter().filter_map(|k| metadata.get_str(k)).next();
+
+        Transitions {
+            transition: value(&["transition"]).and_then(Transition::parse),
+            speed: value(&["transition-speed", "transition_speed"])
+                .and_then(TransitionSpeed::parse),
+            background: value(&["background-transition", "background_transition"])
+                .and_then(Transition::parse),
+        }
+    }
+
+    /// Whether no transition key is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Transitions::default()
+    }
+}
+
+/// A slide transition style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    /// Switch instantly.
+    None,
+
+    /// Cross-fade.
+    Fade,
+
+    /// Slide in horizontally.
+    Slide,
+
+    /// Rotate in, convex side forward.
+    Convex,
+
+    /// Rotate in, concave side forward.
+    Concave,
+
+    /// Zoom in.
+    Zoom,
+}
+
+impl Transition {
+    /// Parse a transition name, such as `fade`.  Returns `None` for an unknown name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "none" => Some(Transition::None),
+            "fade" => Some(Transition::Fade),
+            "slide" => Some(Transition::Slide),
+            "convex" => Some(Transition::Convex),
+            "concave" => Some(Transition::Concave),
+            "zoom" => Some(Transition::Zoom),
+            _ => None,
+        }
+    }
+
+    /// The transition's name, as written in metadata.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Transition::None => "none",
+            Transition::Fade => "fade",
+            Transition::Slide => "slide",
+            Transition::Convex => "convex",
+            Transition::Concave => "concave",
+            Transition::Zoom => "zoom",
+        }
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The speed of a slide transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionSpeed {
+    /// The player's usual speed.
+    Default,
+
+    /// Faster than usual.
+    Fast,
+
+    /// Slower than usual.
+    Slow,
+}
+
+impl TransitionSpeed {
+    /// Parse a speed, such as `fast`.  Returns `None` for an unknown speed.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "default" => Some(TransitionSpeed::Default),
+            "fast" => Some(TransitionSpeed::Fast),
+            "slow" => Some(TransitionSpeed::Slow),
+            _ => None,
+        }
+    }
+
+    /// The speed's name, as written in metadata.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransitionSpeed::Default => "default",
+            TransitionSpeed::Fast => "fast",
+            TransitionSpeed::Slow => "slow",
+        }
+    }
+}
+
+impl fmt::Display for TransitionSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The width to height ratio of slides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectRatio {
+    /// The relative width, such as `16`.
+    pub width: u32,
+
+    /// The relative height, such as `9`.
+    pub height: u32,
+}
+
+impl AspectRatio {
+    /// Parse a ratio written as `16:9`, `16/9` or `16x9`.  Returns `None` if either side is not
+    /// a positive integer.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(2, [':', '/', 'x']);
+        let width = parts.next()?.trim().parse().ok()?;
+        let height = parts.next()?.trim().parse().ok()?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(AspectRatio { width, height })
+    }
+}
+
+impl Default for AspectRatio {
+    /// 16:9.
+    fn default() -> Self {
+        AspectRatio {
+            width: 16,
+            height: 9,
+        }
+    }
+}
+
+impl fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+impl Serialize for AspectRatio {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A single slide of a `Deck`.
+#[derive(Debug, Clone)]
+pub struct Slide<'a> {
+    /// The plain text of the slide's first heading, or an empty string if the slide has no
+    /// heading.
+    pub title: String,
+
+    /// The rule which started this slide.
+    pub split: SplitReason,
+
+    /// The slide's metadata: the deck's front matter entries, overridden by the slide's own
+    /// `---` metadata block.
+    pub metadata: Metadata,
+
+    /// The slide's speaker notes in document order, one entry per ```` ```note ```` fence,
+    /// `<!-- notes: ... -->` comment or `Note:` paragraph.
+    pub notes: Vec<String>,
+
+    /// The top-level AST nodes making up the slide, in document order.  This includes the
+    /// heading, the metadata block and any notes.
+    pub nodes: Vec<&'a AstNode<'a>>,
+
+    /// The number of build steps: fragments revealed one at a time after the slide is shown.
+    /// Each list item is a fragment when the slide's `incremental` metadata is `true`, as is
+    /// every block following a `<!-- fragment -->` marker.  The blocks after a
+    /// `<!-- fragment: rest -->` marker, up to the next one, are revealed together as a single
+    /// fragment.  The index of each fragment is stored in its node's `Ast::fragment`.
+    pub steps: usize,
+
+    /// The slide's `layout`, if its metadata names a known one.
+    pub layout: Option<Layout>,
+
+    /// The slide's transitions, from its metadata.
+    pub transitions: Transitions,
+
+    /// Whether the slide lists the deck's agenda.
+    pub agenda: bool,
+
+    /// The index in `Deck::agenda` of the section the slide belongs to, if any.
+    pub section: Option<usize>,
+
+    /// The slide's content split at `<!-- column -->` markers.  A slide without markers has a
+    /// single region.
+    pub regions: Vec<Region<'a>>,
+
+    /// The slide rendered as HTML, with the same escaping rules as `format_html`.  Metadata
+    /// blocks, notes and fragment and column markers are not rendered.  When the slide has a
+    /// layout or more than one region, each region is wrapped in a
+    /// `<div class="region region-NAME">`.
+    pub html: String,
+
+    /// Where the slide was written in the source, or `None` if it was generated.
+    pub source: Option<SourceRange>,
+}
+
+/// A named slide layout, selected by a slide's `layout` metadata.
+///
+/// ```
+/// # use comrak::{parse_deck, Arena, ComrakOptions};
+/// use comrak::slides::Layout;
+///
+/// let arena = Arena::new();
+/// let deck = parse_deck(
+///     &arena,
+///     "# Compare\n---\nlayout: two-column\n---\n\nBefore\n\n<!-- column -->\n\nAfter\n",
+///     &ComrakOptions::default(),
+/// );
+/// let cover = deck.cover.unwrap();
+///
+/// assert_eq!(cover.layout, Some(Layout::TwoColumn));
+/// assert_eq!(cover.regions[0].name, "left");
+/// assert_eq!(cover.regions[1].html, "<p>After</p>\n");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// A title slide, such as the cover.
+    Title,
+
+    /// A slide introducing a section of the deck.
+    Section,
+
+    /// Two columns side by side: `left` and `right`.
+    TwoColumn,
+
+    /// An `image` beside the `content`.
+    ImageLeft,
+
+    /// A large `quote` with its `attribution`.
+    Quote,
+}
+
+impl Layout {
+    /// Parse a layout name, such as `two-column`.  Returns `None` for an unknown name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "title" => Some(Layout::Title),
+            "section" => Some(Layout::Section),
+            "two-column" => Some(Layout::TwoColumn),
+            "image-left" => Some(Layout::ImageLeft),
+            "quote" => Some(Layout::Quote),
+            _ => None,
+        }
+    }
+
+    /// The layout's name, as written in metadata.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Layout::Title => "title",
+            Layout::Section => "section",
+            Layout::TwoColumn => "two-column",
+            Layout::ImageLeft => "image-left",
+            Layout::Quote => "quote",
+        }
+    }
+
+    /// The names of the layout's regions, in order.
+    pub fn regions(self) -> &'static [&'static str] {
+        match self {
+            Layout::Title | Layout::Section => &["main"],
+            Layout::TwoColumn => &["left", "right"],
+            Layout::ImageLeft => &["image", "content"],
+            Layout::Quote => &["quote", "attribution"],
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Part of a slide's content, between `<!-- column -->` markers.
+#[derive(Debug, Clone)]
+pub struct Region<'a> {
+    /// The region's name: from the slide's layout, or else `main` for a slide's only region
+    /// and `column-N` (counting from 1) otherwise.
+    pub name: String,
+
+    /// The region's top-level AST nodes, in document order.  Metadata blocks, notes and
+    /// markers are not included.
+    pub nodes: Vec<&'a AstNode<'a>>,
+
+    /// The region rendered as HTML.
+    pub html: String,
+}
+
+/// Ordered key/value metadata of a slide or deck.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    entries: Vec<(String, MetaValue)>,
+}
+
+impl Metadata {
+    /// Create an empty set of metadata.
+    pub fn new() -> Self {
+        Metadata::default()
+    }
+
+    /// Return the value for `key`, if present.
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Return the value for `key` if it is present and a string.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(MetaValue::as_str)
+    }
+
+    /// Set `key` to `value`.  An existing entry keeps its position; a new one is appended.
+    pub fn insert(&mut self, key: String, value: MetaValue) {
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Iterate over the entries in source order.
+    pub fn iter(&self) -> slice::Iter<'_, (String, MetaValue)> {
+        self.entries.iter()
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Serialize for Metadata {
+    /// Serializes as a map, keeping the entries in order.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_entries(&self.entries, serializer)
+    }
+}
+
+impl Serialize for MetaValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            MetaValue::String(ref s) => serializer.serialize_str(s),
+            MetaValue::Number(n) => serializer.serialize_f64(n),
+            MetaValue::Bool(b) => serializer.serialize_bool(b),
+            MetaValue::List(ref items) => items.serialize(serializer),
+            MetaValue::Map(ref entries) => serialize_entries(entries, serializer),
+        }
+    }
+}
+
+fn serialize_entries<S: Serializer>(
+    entries: &[(String, MetaValue)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(entries.len()))?;
+    for (k, v) in entries {
+        map.serialize_entry(k, v)?;
+    }
+    map.end()
+}
+
+/// Parse a Markdown document and split it into a `Deck`.
+///
+/// See the documentation of the `slides` module for an example.
+pub fn parse_deck<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    buffer: &str,
+    options: &ComrakOptions,
+) -> Deck<'a> {
+    let root = parse_document(arena, buffer, options);
+    build_deck(root, options)
+}
+
+/// Split an already parsed document into a `Deck`.
+///
+/// Where slides start is controlled by `options.split`; by default every heading starts a new
+/// slide.  Blocks preceding the first split form a slide of their own; front matter is not
+/// part of any slide, but provides the deck's metadata.
+pub fn build_deck<'a>(root: &'a AstNode<'a>, options: &ComrakOptions) -> Deck<'a> {
+    let (entries, first_line) = front_matter_entries(root, options);
+    let (end_line, line_starts) = {
+        let ast = root.data.borrow();
+        (ast.end_line, ast.line_offsets.clone())
+    };
+    let mut slides = split_slides(
+        root.children(),
+        options,
+        &entries,
+        first_line,
+        end_line,
+        &line_starts,
+    );
+    insert_agenda_slide(&mut slides, &entries);
+
+    let agenda = agenda(&slides, &entries);
+    for (ix, slide) in slides.iter_mut().enumerate() {
+        slide.finish(ix, &agenda, &entries, options);
+    }
+
+    let mut slides = slides.into_iter();
+    let cover = slides.next();
+    Deck {
+        cover,
+        slides: slides.collect(),
+        metadata: DeckMetadata::from_entries(entries),
+        agenda,
+    }
+}
+
+/// A document's deck-level metadata entries, from its front matter, and the line its first
+/// slide starts on.
+pub(crate) fn front_matter_entries<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+) -> (Metadata, u32) {
+    let mut entries = Metadata::new();
+    let mut first_line = 1;
+    for node in root.children() {
+        let ast = node.data.borrow();
+        if let NodeValue::FrontMatter(ref literal) = ast.value {
+            if let Some(ref delimiter) = options.extension.front_matter_delimiter {
+                for kv in front_matter(literal, delimiter) {
+                    entries.insert(String::from_utf8_lossy(&kv.key).into_owned(), kv.value);
+                }
+            }
+            first_line = ast.end_line + 1;
+        }
+    }
+    (entries, first_line)
+}
+
+/// Split a document's top-level nodes into slides, skipping its front matter.  The first slide
+/// starts on `first_line` and the last ends on `end_line`; `line_starts` are the byte offsets
+/// of the document's lines, as in the document node's `line_offsets`.
+pub(crate) fn split_slides<'a>(
+    nodes: impl Iterator<Item = &'a AstNode<'a>>,
+    options: &ComrakOptions,
+    entries: &Metadata,
+    first_line: u32,
+    end_line: u32,
+    line_starts: &[usize],
+) -> Vec<Slide<'a>> {
+    let mut slides: Vec<Slide<'a>> = vec![];
+    // The line each slide starts on; the first takes any lines before its first block.
+    let mut starts = vec![];
+
+    for node in nodes {
+        if let NodeValue::FrontMatter(..) = node.data.borrow().value {
+            continue;
+        }
+
+        let start_line = if slides.is_empty() {
+            first_line
+        } else {
+            node.data.borrow().start_line
+        };
+        match split_reason(node, &options.split) {
+            Some(split) if split.is_separator() => {
+                slides.push(Slide::new(split, entries));
+                starts.push(start_line);
+                continue;
+            }
+            Some(split) => {
+                // A heading directly after a separator belongs to the slide the separator opened.
+                match slides.last() {
+                    Some(slide) if slide.nodes.is_empty() => (),
+                    _ => {
+                        slides.push(Slide::new(split, entries));
+                        starts.push(start_line);
+                    }
+                }
+            }
+            None => {
+                if slides.is_empty() {
+                    slides.push(Slide::new(SplitReason::Start, entries));
+                    starts.push(start_line);
+                }
+            }
+        }
+        slides.last_mut().unwrap().push(node);
+    }
+
+    let byte = |line: u32| {
+        line_starts
+            .get(line as usize - 1)
+            .or_else(|| line_starts.last())
+            .map_or(0, |&byte| byte)
+    };
+    for (ix, slide) in slides.iter_mut().enumerate() {
+        let start_line = starts[ix];
+        let end_line = starts
+            .get(ix + 1)
+            .map_or(end_line, |&next| next - 1)
+            .max(start_line);
+        slide.source = Some(SourceRange {
+            start_line,
+            end_line,
+            start_byte: byte(start_line),
+            end_byte: byte(end_line + 1),
+        });
+    }
+    slides
+}
+
+/// Add a generated agenda slide after the cover, if the front matter sets `agenda: true`.
+pub(crate) fn insert_agenda_slide(slides: &mut Vec<Slide>, entries: &Metadata) {
+    if is_true(entries.get("agenda")) && !slides.is_empty() {
+        let mut slide = Slide::new(SplitReason::Generated, entries);
+        slide.title = entries
+            .get_str("agenda-title")
+            .unwrap_or("Agenda")
+            .to_string();
+        slides.insert(1, slide);
+    }
+}
+
+fn is_true(value: Option<&MetaValue>) -> bool {
+    value.and_then(MetaValue::as_bool) == Some(true)
+}
+
+/// The sections of a deck: the slides after the cover with a heading within `agenda-level`.
+pub(crate) fn agenda(slides: &[Slide], entries: &Metadata) -> Vec<AgendaEntry> {
+    let max_level = ["agenda-level", "agenda_level"]
+        .iter()
+        .filter_map(|k| entries.get(k).and_then(MetaValue::as_f64))
+        .next()
+        .map_or(1, |level| level as u32);
+
+    slides
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(ix, slide)| {
+            let level = slide.title_level()?;
+            if level > max_level || slide.title.is_empty() {
+                return None;
+            }
+            Some(AgendaEntry {
+                title: slide.title.clone(),
+                level,
+                slide: ix + 1,
+                anchor: slide_anchor(ix + 1),
+            })
+        })
+        .collect()
+}
+
+/// The entries of a front matter block, between its opening and closing delimiter lines.
+fn front_matter(literal: &[u8], delimiter: &str) -> Vec<NodeKV> {
+    metadata::parse(metadata::front_matter_body(literal, delimiter).as_bytes())
+}
+
+/// The split rule which started a slide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitReason {
+    /// The slide is the start of the document, before any split.
+    Start,
+
+    /// A heading within `SlideSplitOptions::heading_level`.
+    Heading,
+
+    /// A thematic break, with `SlideSplitOptions::thematic_break` set.
+    ThematicBreak,
+
+    /// A `<!-- slide -->` marker, with `SlideSplitOptions::marker` set.
+    Marker,
+
+    /// The slide was generated rather than written, such as a deck's agenda slide.
+    Generated,
+}
+
+impl SplitReason {
+    fn is_separator(self) -> bool {
+        matches!(self, SplitReason::ThematicBreak | SplitReason::Marker)
+    }
+}
+
+fn split_reason<'a>(node: &'a AstNode<'a>, options: &SlideSplitOptions) -> Option<SplitReason> {
+    match node.data.borrow().value {
+        NodeValue::Heading(ref nh) if nh.level <= options.heading_level => {
+            Some(SplitReason::Heading)
+        }
+        NodeValue::ThematicBreak if options.thematic_break => Some(SplitReason::ThematicBreak),
+        NodeValue::HtmlBlock(ref nhb) if options.marker && is_slide_marker(&nhb.literal) => {
+            Some(SplitReason::Marker)
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn is_slide_marker(literal: &[u8]) -> bool {
+    html_comment(literal).as_deref() == Some("slide")
+}
+
+fn is_fragment_marker<'a>(node: &'a AstNode<'a>) -> bool {
+    is_marker(node, "fragment")
+}
+
+/// Whether a node is a `<!-- fragment: rest -->` marker, revealing the rest of the slide in one
+/// step.
+fn is_rest_marker<'a>(node: &'a AstNode<'a>) -> bool {
+    is_marker(node, "fragment: rest")
+}
+
+fn is_column_marker<'a>(node: &'a AstNode<'a>) -> bool {
+    is_marker(node, "column")
+}
+
+/// Whether a node is a paragraph holding only the `::toc` directive.
+fn is_toc_directive<'a>(node: &'a AstNode<'a>) -> bool {
+    matches!(node.data.borrow().value, NodeValue::Paragraph) && collect_text(node).trim() == "::toc"
+}
+
+fn is_marker<'a>(node: &'a AstNode<'a>, name: &str) -> bool {
+    match node.data.borrow().value {
+        NodeValue::HtmlBlock(ref nhb) => html_comment(&nhb.literal).as_deref() == Some(name),
+        _ => false,
+    }
+}
+
+/// The trimmed text inside an HTML block consisting of a single comment.
+fn html_comment(literal: &[u8]) -> Option<String> {
+    let literal = String::from_utf8_lossy(literal);
+    let literal = literal.trim();
+    if literal.len() >= 7 && literal.starts_with("<!--") && literal.ends_with("-->") {
+        Some(literal[4..literal.len() - 3].trim().to_string())
+    } else {
+        None
+    }
+}
+
+impl<'a> Slide<'a> {
+    fn new(split: SplitReason, defaults: &Metadata) -> Self {
+        Slide {
+            title: String::new(),
+            split,
+            metadata: defaults.clone(),
+            notes: vec![],
+            nodes: vec![],
+            steps: 0,
+            layout: None,
+            transitions: Transitions::default(),
+            agenda: false,
+            section: None,
+            regions: vec![],
+            html: String::new(),
+            source: None,
+        }
+    }
+
+    fn push(&mut self, node: &'a AstNode<'a>) {
+        match node.data.borrow().value {
+            NodeValue::Heading(_) if self.title.is_empty() => {
+                self.title = collect_text(node);
+            }
+            NodeValue::SlideMetaDataBlock(_) => {
+                for child in node.children() {
+                    if let NodeValue::KV(ref kv) = child.data.borrow().value {
+                        let key = String::from_utf8_lossy(&kv.key).into_owned();
+                        self.metadata.insert(key, kv.value.clone());
+                    }
+                }
+            }
+            _ => {
+                if let Some(note) = note(node) {
+                    self.notes.push(note);
+                }
+            }
+        }
+        self.nodes.push(node);
+    }
+
+    /// Fill in what depends on the rest of the deck, such as its agenda, and render the slide
+    /// as the deck's slide at index `ix`.
+    pub(crate) fn finish(
+        &mut self,
+        ix: usize,
+        agenda: &[AgendaEntry],
+        entries: &Metadata,
+        options: &ComrakOptions,
+    ) {
+        self.agenda = self.split == SplitReason::Generated
+            || self.nodes.iter().any(|n| is_toc_directive(n))
+            || (is_true(self.metadata.get("agenda"))
+                && self.metadata.get("agenda") != entries.get("agenda"));
+        self.section = agenda.iter().rposition(|e| e.slide <= ix + 1);
+        self.transitions = Transitions::from_metadata(&self.metadata);
+        self.number_fragments();
+        self.split_regions();
+        self.render(options, agenda);
+    }
+
+    /// The level of the slide's first heading.
+    fn title_level(&self) -> Option<u32> {
+        self.nodes.iter().find_map(|n| match n.data.borrow().value {
+            NodeValue::Heading(ref nh) => Some(nh.level),
+            _ => None,
+        })
+    }
+
+    fn number_fragments(&mut self) {
+        let incremental = self
+            .metadata
+            .get("incremental")
+            .and_then(MetaValue::as_bool)
+            == Some(true);
+
+        self.steps = 0;
+        // After a `<!-- fragment: rest -->` marker, the step shared by the blocks of the rest
+        // of the slide, once one of them is found.
+        let mut rest = None;
+        for node in &self.nodes {
+            if is_rest_marker(node) {
+                rest = Some(None);
+            }
+            for (ix, descendant) in node.descendants().enumerate() {
+                let is_fragment = (incremental
+                    && matches!(descendant.data.borrow().value, NodeValue::Item(..)))
+                    || descendant
+                        .previous_sibling()
+                        .is_some_and(is_fragment_marker);
+
+                descendant.data.borrow_mut().fragment = match rest {
+                    Some(ref mut step) if ix == 0 && !is_fragment && !is_slide_only(node) => {
+                        if step.is_none() {
+                            self.steps += 1;
+                            *step = Some(self.steps - 1);
+                        }
+                        *step
+                    }
+                    _ if is_fragment => {
+                        self.steps += 1;
+                        Some(self.steps - 1)
+                    }
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    /// Split the slide's content into regions at column markers.
+    fn split_regions(&mut self) {
+        self.layout = self.metadata.get_str("layout").and_then(Layout::parse);
+
+        let mut regions = vec![vec![]];
+        for &node in &self.nodes {
+            if is_column_marker(node) {
+                regions.push(vec![]);
+            } else if !is_slide_only(node) {
+                regions.last_mut().unwrap().push(node);
+            }
+        }
+
+        let names = self.layout.map_or(&[][..], Layout::regions);
+        let count = regions.len();
+        self.regions = regions
+            .into_iter()
+            .enumerate()
+            .map(|(ix, nodes)| Region {
+                name: match names.get(ix) {
+                    Some(name) => name.to_string(),
+                    None if count == 1 => "main".to_string(),
+                    None => format!("column-{}", ix + 1),
+                },
+                nodes,
+                html: String::new(),
+            })
+            .collect();
+    }
+
+    /// Whether the slide's HTML wraps each region in its own container.
+    pub fn has_regions(&self) -> bool {
+        self.layout.is_some() || self.regions.len() > 1
+    }
+
+    fn render(&mut self, options: &ComrakOptions, agenda: &[AgendaEntry]) {
+        let regions = self.render_regions(options, agenda, None);
+        for (region, html) in self.regions.iter_mut().zip(regions) {
+            region.html = html;
+        }
+        self.html = self.join_regions(self.regions.iter().map(|r| r.html.as_str()));
+    }
+
+    /// The slide rendered as by `Slide::html`, but with each link numbered, and the links'
+    /// URLs in order.
+    pub(crate) fn html_with_links(
+        &self,
+        options: &ComrakOptions,
+        agenda: &[AgendaEntry],
+    ) -> (String, Vec<String>) {
+        let mut links = vec![];
+        let regions = self.render_regions(options, agenda, Some(&mut links));
+        (self.join_regions(regions.iter().map(String::as_str)), links)
+    }
+
+    /// The HTML of each region, listing the agenda in place of `::toc`.
+    fn render_regions(
+        &self,
+        options: &ComrakOptions,
+        agenda: &[AgendaEntry],
+        mut links: Option<&mut Vec<String>>,
+    ) -> Vec<String> {
+        fn flush<'a>(
+            chunk: &mut Vec<&'a AstNode<'a>>,
+            html: &mut String,
+            options: &ComrakOptions,
+            links: Option<&mut Vec<String>>,
+        ) {
+            let mut output = vec![];
+            html::format_nodes(chunk, options, &mut output, links).unwrap();
+            *html += &String::from_utf8(output).unwrap();
+            chunk.clear();
+        }
+
+        let list = if self.agenda {
+            agenda_html(agenda, self.section, anchor_prefix(options))
+        } else {
+            String::new()
+        };
+        let mut regions = vec![String::new()];
+        if self.split == SplitReason::Generated {
+            regions[0] = format!("<h2>{}</h2>\n", html::escape_text(&self.title));
+        }
+
+        // Walk the nodes as `split_regions` does.
+        let mut chunk = vec![];
+        let mut listed = false;
+        for &node in &self.nodes {
+            if is_column_marker(node) {
+                let html = regions.last_mut().unwrap();
+                flush(&mut chunk, html, options, links.as_deref_mut());
+                regions.push(String::new());
+            } else if is_toc_directive(node) {
+                let html = regions.last_mut().unwrap();
+                flush(&mut chunk, html, options, links.as_deref_mut());
+                *html += &list;
+                listed = true;
+            } else if !is_slide_only(node) {
+                chunk.push(node);
+            }
+        }
+        let html = regions.last_mut().unwrap();
+        flush(&mut chunk, html, options, links);
+        if self.agenda && !listed {
+            *html += &list;
+        }
+        regions
+    }
+
+    /// The slide's HTML from its regions' HTML, wrapping each if the slide `has_regions`.
+    fn join_regions<'r>(&self, regions: impl Iterator<Item = &'r str>) -> String {
+        if !self.has_regions() {
+            return regions.collect();
+        }
+        self.regions
+            .iter()
+            .zip(regions)
+            .map(|(r, html)| format!("<div class=\"region region-{}\">\n{}</div>\n", r.name, html))
+            .collect()
+    }
+}
+
+/// Whether a node only carries slide information and has no visible content.
+pub(crate) fn is_slide_only<'a>(node: &'a AstNode<'a>) -> bool {
+    match node.data.borrow().value {
+        NodeValue::SlideMetaDataBlock(..) => true,
+        _ => {
+            is_note(node)
+                || is_fragment_marker(node)
+                || is_rest_marker(node)
+                || is_column_marker(node)
+                || is_toc_directive(node)
+        }
+    }
+}
+
+/// Whether a node holds speaker notes rather than slide content.
+fn is_note<'a>(node: &'a AstNode<'a>) -> bool {
+    note(node).is_some()
+}
+
+/// The speaker notes held by a node: a ```` ```note ```` fence, a `<!-- notes: ... -->`
+/// comment, or a paragraph starting with `Note:`.
+fn note<'a>(node: &'a AstNode<'a>) -> Option<String> {
+    match node.data.borrow().value {
+        NodeValue::CodeBlock(ref ncb) => {
+            if ncb.info.split(|&c| c == b' ' || c == b'\t').next() == Some(b"note") {
+                Some(String::from_utf8_lossy(&ncb.literal).into_owned())
+            } else {
+                None
+            }
+        }
+        NodeValue::HtmlBlock(ref nhb) => html_comment(&nhb.literal)
+            .as_ref()
+            .and_then(|c| c.strip_prefix("notes:"))
+            .map(|note| note.trim().to_string()),
+        NodeValue::Paragraph => collect_text(node)
+            .strip_prefix("Note:")
+            .map(|note| note.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// The plain text of a node's inlines, with line breaks as spaces.
+pub(crate) fn collect_text<'a>(node: &'a AstNode<'a>) -> String {
+    fn collect<'a>(node: &'a AstNode<'a>, output: &mut Vec<u8>) {
+        match node.data.borrow().value {
+            NodeValue::Text(ref literal) | NodeValue::Code(NodeCode { ref literal, .. }) => {
+                output.extend_from_slice(literal)
+            }
+            NodeValue::LineBreak | NodeValue::SoftBreak => output.push(b' '),
+            _ => {
+                for n in node.children() {
+                    collect(n, output);
+                }
+            }
+        }
+    }
+
+    let mut text = vec![];
+    collect(node, &mut text);
+    String::from_utf8_lossy(&text).into_owned()
+}
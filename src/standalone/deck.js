@@ -0,0 +1,85 @@
+(function () {
+  var slides = document.querySelectorAll(".deck > section");
+  var bar = document.querySelector(".progress-bar");
+  var current = 0;
+  var step = 0;
+
+  function steps(i) {
+    return parseInt(slides[i].getAttribute("data-steps"), 10) || 0;
+  }
+
+  function show(i, s) {
+    current = Math.max(0, Math.min(slides.length - 1, i));
+    step = Math.max(0, Math.min(steps(current), s));
+
+    for (var k = 0; k < slides.length; k++) {
+      slides[k].classList.toggle("current", k === current);
+    }
+
+    var fragments = slides[current].querySelectorAll("[data-fragment-index]");
+    for (var f = 0; f < fragments.length; f++) {
+      var index = parseInt(fragments[f].getAttribute("data-fragment-index"), 10);
+      fragments[f].classList.toggle("visible", index < step);
+    }
+
+    bar.style.width = (slides.length > 1 ? (current / (slides.length - 1)) * 100 : 100) + "%";
+
+    var hash = "#" + (current + 1);
+    if (location.hash !== hash) {
+      history.replaceState(null, "", hash);
+    }
+  }
+
+  function next() {
+    if (step < steps(current)) {
+      show(current, step + 1);
+    } else if (current < slides.length - 1) {
+      show(current + 1, 0);
+    }
+  }
+
+  function previous() {
+    if (step > 0) {
+      show(current, step - 1);
+    } else if (current > 0) {
+      show(current - 1, steps(current - 1));
+    }
+  }
+
+  function fromHash() {
+    show((parseInt(location.hash.replace(/^#(slide-)?/, ""), 10) || 1) - 1, 0);
+  }
+
+  document.addEventListener("keydown", function (e) {
+    switch (e.key) {
+      case "ArrowRight":
+      case "ArrowDown":
+      case "PageDown":
+      case " ":
+      case "Enter":
+        next();
+        break;
+      case "ArrowLeft":
+      case "ArrowUp":
+      case "PageUp":
+      case "Backspace":
+        previous();
+        break;
+      case "Home":
+        show(0, 0);
+        break;
+      case "End":
+        show(slides.length - 1, steps(slides.length - 1));
+        break;
+      default:
+        return;
+    }
+    e.preventDefault();
+  });
+
+  window.addEventListener("hashchange", fromHash);
+
+  if (slides.length > 0) {
+    fromHash();
+  }
+})();
@@ -0,0 +1,264 @@
+//! A self-contained HTML presentation: one file with every slide as a `<section>`, plus
+//! embedded CSS and a navigation script.
+
+use html::escape_text;
+use nodes::{AstNode, MetaValue};
+use parser::ComrakOptions;
+use slides::{agenda_html, build_deck, slide_anchor, AspectRatio, Deck, Slide};
+use std::io::{self, Write};
+use themes::{Theme, DEFAULT_THEME};
+
+pub mod print;
+pub mod reveal;
+
+const CSS: &str = include_str!("deck.css");
+const SCRIPT: &str = include_str!("deck.js");
+
+/// Formats an AST as a single, offline HTML presentation.
+///
+/// The document is split into slides as by `build_deck`.  Slides are sized to the deck's
+/// `aspect_ratio` front matter (16:9 by default) and scaled to fit the window.  They are styled
+/// with the theme named by `options.render.theme` or the deck's `theme` metadata; a slide's own
+/// `theme` and `class` metadata apply to that slide only, and its `layout` arranges its regions.
+/// A slide's `transition` and `transition-speed` animate it in, and are also written as
+/// `data-` attributes of its `<section>` for other players.  Each `<section>` has an `id` of
+/// its slide anchor, such as `slide-2`, for agenda links; with `agenda-highlight: true` in the
+/// front matter, every slide also shows the agenda with its own section highlighted.  The
+/// embedded script moves between slides and their fragments with the arrow, space and page
+/// keys, keeps the slide number in the URL hash (`#1` is the first slide), and shows a progress
+/// bar.
+///
+/// ```
+/// # use comrak::{format_slide_standalone, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Hello\n\n## World\n", &options);
+/// let mut html = vec![];
+/// format_slide_standalone(root, &options, &mut html).unwrap();
+/// let html = String::from_utf8(html).unwrap();
+///
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(html.contains(
+///     "<section id=\"slide-2\" data-index=\"2\" data-steps=\"0\">\n<h2>World</h2>\n</section>"
+/// ));
+/// ```
+///
+/// An unknown theme is an error:
+///
+/// ```
+/// # use comrak::{format_slide_standalone, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Hello\n---\ntheme: plaid\n---\n", &options);
+/// assert!(format_slide_standalone(root, &options, &mut vec![]).is_err());
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let ratio = deck.metadata.aspect_ratio.unwrap_or_default();
+    let themes = Themes::resolve(&deck, options)?;
+
+    open_document(&deck, &format!("{}{}", CSS, themes.css()), output)?;
+    format_deck(&deck, &themes, ratio, output)?;
+    writeln!(
+        output,
+        "<div class=\"progress\"><div class=\"progress-bar\"></div></div>"
+    )?;
+    writeln!(output, "<script>\n{}</script>", SCRIPT)?;
+    writeln!(output, "</body>")?;
+    writeln!(output, "</html>")?;
+    Ok(())
+}
+
+/// Write everything up to the opening `<body>` tag, with `css` as the only style sheet.
+fn open_document(deck: &Deck, css: &str, output: &mut dyn Write) -> io::Result<()> {
+    writeln!(output, "<!DOCTYPE html>")?;
+    writeln!(
+        output,
+        "<html lang=\"{}\">",
+        escape_text(deck.metadata.language.as_deref().unwrap_or("en"))
+    )?;
+    writeln!(output, "<head>")?;
+    writeln!(output, "<meta charset=\"utf-8\">")?;
+    writeln!(
+        output,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    )?;
+    writeln!(output, "<title>{}</title>", escape_text(&title(deck)))?;
+    writeln!(output, "<style>\n{}</style>", css)?;
+    writeln!(output, "</head>")?;
+    writeln!(output, "<body>")
+}
+
+/// The deck's title: from its front matter, or else the title of the cover slide.
+fn title(deck: &Deck) -> String {
+    match (&deck.metadata.title, &deck.cover) {
+        (Some(title), _) => title.clone(),
+        (None, Some(cover)) => cover.title.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// The deck's theme, and the themes chosen by individual slides.
+struct Themes {
+    deck: Theme,
+    slides: Vec<Theme>,
+}
+
+impl Themes {
+    fn resolve(deck: &Deck, options: &ComrakOptions) -> io::Result<Themes> {
+        let dirs = &options.render.theme_dirs;
+        let name = options
+            .render
+            .theme
+            .as_deref()
+            .or(deck.metadata.theme.as_deref())
+            .unwrap_or(DEFAULT_THEME);
+        let mut themes = Themes {
+            deck: Theme::resolve(name, dirs)?,
+            slides: vec![],
+        };
+
+        for slide in deck.cover.iter().chain(deck.slides.iter()) {
+            if let Some(name) = slide_theme(deck, slide) {
+                if name != themes.deck.name && themes.slides.iter().all(|t| t.name != name) {
+                    themes.slides.push(Theme::resolve(name, dirs)?);
+                }
+            }
+        }
+
+        Ok(themes)
+    }
+
+    fn css(&self) -> String {
+        let mut css = self.deck.rule(".deck");
+        css += &self.deck.css;
+        for theme in &self.slides {
+            css += &theme.rule(&format!(".deck > section.theme-{}", theme.name));
+        }
+        css
+    }
+}
+
+/// The theme a slide sets in its own metadata, rather than inheriting from the deck.
+fn slide_theme<'s>(deck: &Deck, slide: &'s Slide) -> Option<&'s str> {
+    let theme = slide.metadata.get("theme")?;
+    if deck.metadata.entries.get("theme") == Some(theme) {
+        return None;
+    }
+    theme.as_str()
+}
+
+fn format_deck(
+    deck: &Deck,
+    themes: &Themes,
+    ratio: AspectRatio,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "<div class=\"deck theme-{}\" data-aspect-ratio=\"{}\" style=\"--aspect-width: {}; --aspect-height: {}\">",
+        escape_text(&themes.deck.name),
+        ratio,
+        ratio.width,
+        ratio.height
+    )?;
+    for (ix, slide) in deck.cover.iter().chain(deck.slides.iter()).enumerate() {
+        format_slide(deck, slide, ix + 1, output)?;
+    }
+    writeln!(output, "</div>")
+}
+
+fn format_slide(
+    deck: &Deck,
+    slide: &Slide,
+    index: usize,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    open_section(deck, slide, index, None, output)?;
+    output.write_all(slide.html.as_bytes())?;
+    format_agenda_nav(deck, slide, output)?;
+    format_notes(slide, output)?;
+    writeln!(output, "</section>")
+}
+
+/// Write the `<section>` tag opening a slide.  A slide printed as several `step`s only has its
+/// `id` on the first.
+fn open_section(
+    deck: &Deck,
+    slide: &Slide,
+    index: usize,
+    step: Option<usize>,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let classes = section_classes(deck, slide);
+    write!(output, "<section")?;
+    if !classes.is_empty() {
+        write!(output, " class=\"{}\"", escape_text(&classes.join(" ")))?;
+    }
+    if step.unwrap_or(0) == 0 {
+        write!(output, " id=\"{}\"", slide_anchor(index))?;
+    }
+    write!(
+        output,
+        " data-index=\"{}\" data-steps=\"{}\"",
+        index, slide.steps
+    )?;
+    if let Some(step) = step {
+        write!(output, " data-step=\"{}\"", step)?;
+    }
+    let transitions = &slide.transitions;
+    if let Some(transition) = transitions.transition {
+        write!(output, " data-transition=\"{}\"", transition)?;
+    }
+    if let Some(speed) = transitions.speed {
+        write!(output, " data-transition-speed=\"{}\"", speed)?;
+    }
+    if let Some(background) = transitions.background {
+        write!(output, " data-background-transition=\"{}\"", background)?;
+    }
+    writeln!(output, ">")
+}
+
+/// The classes of a slide's `<section>`: for its layout, its own theme, and its `class`
+/// metadata.
+fn section_classes(deck: &Deck, slide: &Slide) -> Vec<String> {
+    let mut classes = vec![];
+    if let Some(layout) = slide.layout {
+        classes.push(format!("layout-{}", layout));
+    }
+    if let Some(theme) = slide_theme(deck, slide) {
+        classes.push(format!("theme-{}", theme));
+    }
+    match slide.metadata.get("class") {
+        Some(MetaValue::List(items)) => classes.extend(items.iter().map(|c| c.to_string())),
+        Some(class) => classes.push(class.to_string()),
+        None => (),
+    }
+    classes
+}
+
+/// With `agenda-highlight: true`, the deck's agenda with the slide's section highlighted.
+fn format_agenda_nav(deck: &Deck, slide: &Slide, output: &mut dyn Write) -> io::Result<()> {
+    let highlight = deck.metadata.entries.get("agenda-highlight");
+    if highlight.and_then(MetaValue::as_bool) == Some(true) && !deck.agenda.is_empty() {
+        writeln!(output, "<nav class=\"agenda-nav\">")?;
+        output.write_all(agenda_html(&deck.agenda, slide.section, "#").as_bytes())?;
+        writeln!(output, "</nav>")?;
+    }
+    Ok(())
+}
+
+fn format_notes(slide: &Slide, output: &mut dyn Write) -> io::Result<()> {
+    if !slide.notes.is_empty() {
+        writeln!(output, "<aside class=\"notes\">")?;
+        for note in &slide.notes {
+            writeln!(output, "<p>{}</p>", escape_text(note.trim_end()))?;
+        }
+        writeln!(output, "</aside>")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Print output: every slide on a page of its own, sized to the deck's aspect ratio, for
+//! printing to paper or PDF from a browser.
+
+use super::{format_agenda_nav, format_notes, open_document, open_section, Themes, CSS};
+use html::escape_text;
+use nodes::AstNode;
+use parser::ComrakOptions;
+use slides::{build_deck, Deck, Slide};
+use std::io::{self, Write};
+
+const PRINT_CSS: &str = include_str!("print.css");
+
+/// The width of a printed page, in inches.
+const PAGE_WIDTH: f64 = 10.0;
+
+/// Formats an AST as HTML for printing, with one page per slide.
+///
+/// Pages are 10 inches wide, and as tall as the deck's `aspect_ratio` makes a slide; with
+/// `options.print.notes`, they are half as tall again, with the speaker notes under the slide.
+/// Slides are themed as by `format_slide_standalone`.  Fragments are all shown, unless
+/// `options.print.fragments` is set, which prints a page for each build step.  With
+/// `options.print.link_footnotes`, each link is numbered and its URL listed at the bottom of
+/// the slide.
+///
+/// ```
+/// # use comrak::{format_slide_print, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Hello\n\n## World\n", &options);
+/// let mut html = vec![];
+/// format_slide_print(root, &options, &mut html).unwrap();
+/// let html = String::from_utf8(html).unwrap();
+///
+/// assert!(html.contains("@page {\n  size: 10in 5.625in;\n  margin: 0;\n}"));
+/// assert_eq!(html.matches("<div class=\"deck theme-light page\">").count(), 2);
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let ratio = deck.metadata.aspect_ratio.unwrap_or_default();
+    let themes = Themes::resolve(&deck, options)?;
+    let slides = deck.cover.iter().chain(deck.slides.iter());
+
+    let slide_height = PAGE_WIDTH * f64::from(ratio.height) / f64::from(ratio.width);
+    let page_height = if options.print.notes {
+        slide_height * 1.5
+    } else {
+        slide_height
+    };
+
+    let mut css = format!("{}{}{}", CSS, themes.css(), PRINT_CSS);
+    css += &format!(
+        "@page {{\n  size: {}in {}in;\n  margin: 0;\n}}\n\n",
+        PAGE_WIDTH,
+        inches(page_height)
+    );
+    css += &format!(
+        ":root {{\n  --page-width: {}in;\n  --page-height: {}in;\n  --print-slide-height: {}in;\n}}\n",
+        PAGE_WIDTH,
+        inches(page_height),
+        inches(slide_height)
+    );
+    if options.print.fragments {
+        css += &fragment_css(slides.clone().map(|s| s.steps).max().unwrap_or(0));
+    }
+
+    open_document(&deck, &css, output)?;
+    for (ix, slide) in slides.enumerate() {
+        let (html, links) = if options.print.link_footnotes {
+            slide.html_with_links(options, &deck.agenda)
+        } else {
+            (slide.html.clone(), vec![])
+        };
+        let page = Page {
+            deck: &deck,
+            slide,
+            index: ix + 1,
+            html: &html,
+            links: &links,
+        };
+
+        if options.print.fragments {
+            for step in 0..=slide.steps {
+                page.format(&themes, Some(step), options, output)?;
+            }
+        } else {
+            page.format(&themes, None, options, output)?;
+        }
+    }
+    writeln!(output, "</body>")?;
+    writeln!(output, "</html>")?;
+    Ok(())
+}
+
+/// A length in inches, to at most three decimal places.
+fn inches(length: f64) -> String {
+    let s = format!("{:.3}", length);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Rules hiding, on the page for each build step, the fragments not yet revealed.
+fn fragment_css(steps: usize) -> String {
+    let mut css = String::new();
+    for step in 0..steps {
+        let selectors = (step..steps)
+            .map(|ix| {
+                format!(
+                    ".deck.page > section[data-step=\"{}\"] [data-fragment-index=\"{}\"]",
+                    step, ix
+                )
+            })
+            .collect::<Vec<_>>();
+        css += &format!("{} {{\n  visibility: hidden;\n}}\n", selectors.join(",\n"));
+    }
+    css
+}
+
+/// A slide as printed, with its links numbered if they are to be footnoted.
+struct Page<'d, 'a> {
+    deck: &'d Deck<'a>,
+    slide: &'d Slide<'a>,
+    index: usize,
+    html: &'d str,
+    links: &'d [String],
+}
+
+impl<'d, 'a> Page<'d, 'a> {
+    fn format(
+        &self,
+        themes: &Themes,
+        step: Option<usize>,
+        options: &ComrakOptions,
+        output: &mut dyn Write,
+    ) -> io::Result<()> {
+        writeln!(
+            output,
+            "<div class=\"deck theme-{} page\">",
+            escape_text(&themes.deck.name)
+        )?;
+        open_section(self.deck, self.slide, self.index, step, output)?;
+        output.write_all(self.html.as_bytes())?;
+        if !self.links.is_empty() {
+            writeln!(output, "<ol class=\"link-notes\">")?;
+            for link in self.links {
+                writeln!(output, "<li>{}</li>", escape_text(link))?;
+            }
+            writeln!(output, "</ol>")?;
+        }
+        format_agenda_nav(self.deck, self.slide, output)?;
+        writeln!(output, "</section>")?;
+        if options.print.notes {
+            format_notes(self.slide, output)?;
+        }
+        writeln!(output, "</div>")
+    }
+}
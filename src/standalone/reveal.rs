@@ -0,0 +1,132 @@
+//! reveal.js output: the deck as the DOM a reveal.js presentation expects, to be dropped into
+//! an existing reveal.js page.
+
+use super::{format_notes, section_classes};
+use html::escape_text;
+use nodes::{AstNode, MetaValue};
+use parser::ComrakOptions;
+use slides::{build_deck, slide_anchor, Deck, Slide};
+use std::io::{self, Write};
+
+/// Formats an AST as the slides of a reveal.js presentation: a `<div class="reveal">` holding
+/// a `<div class="slides">` with a `<section>` per slide.
+///
+/// Only the presentation's DOM is written, with no script or style sheet, so that it can
+/// replace the `.reveal` element of a page which already loads and initializes reveal.js.
+///
+/// Slides within a section of the deck, as listed by its agenda, are stacked vertically under
+/// the slide starting the section, as is a slide whose `vertical` metadata is `true` under the
+/// slide before it; other slides are laid out horizontally.  Each slide's
+/// `<section>` has an `id` of its slide anchor, such as `slide-2`, and links to those anchors
+/// are written as reveal.js's `#/slide-2`.  Fragments have the `fragment` class, speaker notes
+/// go in an `<aside class="notes">`, and the slide's transitions and any `background` or
+/// `background-*` metadata become `data-` attributes.
+///
+/// ```
+/// # use comrak::{format_reveal, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Talk\n\n# Why\n\n## Detail\n", &options);
+/// let mut html = vec![];
+/// format_reveal(root, &options, &mut html).unwrap();
+/// let html = String::from_utf8(html).unwrap();
+///
+/// assert!(html.starts_with("<div class=\"reveal\">\n<div class=\"slides\">\n"));
+/// assert!(html.contains(concat!(
+///     "<section>\n",
+///     "<section id=\"slide-2\">\n<h1>Why</h1>\n</section>\n",
+///     "<section id=\"slide-3\">\n<h2>Detail</h2>\n</section>\n",
+///     "</section>\n",
+/// )));
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let mut options = options.clone();
+    options.render.reveal = true;
+    let deck = build_deck(root, &options);
+
+    writeln!(output, "<div class=\"reveal\">")?;
+    writeln!(output, "<div class=\"slides\">")?;
+    for stack in stacks(&deck) {
+        let vertical = stack.len() > 1;
+        if vertical {
+            writeln!(output, "<section>")?;
+        }
+        for (index, slide) in stack {
+            format_slide(&deck, slide, index, output)?;
+        }
+        if vertical {
+            writeln!(output, "</section>")?;
+        }
+    }
+    writeln!(output, "</div>")?;
+    writeln!(output, "</div>")
+}
+
+/// The deck's slides, numbered from 1 and grouped into vertical stacks: a slide starting a
+/// section, followed by the rest of the section, or any slide followed by those marked
+/// `vertical`.
+fn stacks<'d, 'a>(deck: &'d Deck<'a>) -> Vec<Vec<(usize, &'d Slide<'a>)>> {
+    let mut stacks: Vec<Vec<(usize, &Slide)>> = vec![];
+    for (ix, slide) in deck.cover.iter().chain(deck.slides.iter()).enumerate() {
+        let index = ix + 1;
+        let within_section = slide
+            .section
+            .is_some_and(|section| deck.agenda[section].slide != index);
+        let vertical = slide.metadata.get("vertical").and_then(MetaValue::as_bool) == Some(true);
+        match stacks.last_mut() {
+            Some(stack) if within_section || vertical => stack.push((index, slide)),
+            _ => stacks.push(vec![(index, slide)]),
+        }
+    }
+    stacks
+}
+
+fn format_slide(
+    deck: &Deck,
+    slide: &Slide,
+    index: usize,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    write!(output, "<section id=\"{}\"", slide_anchor(index))?;
+    let classes = section_classes(deck, slide);
+    if !classes.is_empty() {
+        write!(output, " class=\"{}\"", escape_text(&classes.join(" ")))?;
+    }
+
+    let transitions = &slide.transitions;
+    if let Some(transition) = transitions.transition {
+        write!(output, " data-transition=\"{}\"", transition)?;
+    }
+    if let Some(speed) = transitions.speed {
+        write!(output, " data-transition-speed=\"{}\"", speed)?;
+    }
+    if let Some(background) = transitions.background {
+        write!(output, " data-background-transition=\"{}\"", background)?;
+    }
+    for (key, value) in slide.metadata.iter() {
+        let attribute = key.replace('_', "-");
+        let background = attribute == "background"
+            || (attribute.starts_with("background-") && attribute != "background-transition");
+        let valid = attribute
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !background || !valid {
+            continue;
+        }
+        write!(
+            output,
+            " data-{}=\"{}\"",
+            attribute,
+            escape_text(&value.to_string())
+        )?;
+    }
+    writeln!(output, ">")?;
+
+    output.write_all(slide.html.as_bytes())?;
+    format_notes(slide, output)?;
+    writeln!(output, "</section>")
+}
@@ -5,7 +5,7 @@ use propfuzz::prelude::*;
 use timebomb::timeout_ms;
 use {
     parse_document, Arena, ComrakExtensionOptions, ComrakOptions, ComrakParseOptions,
-    ComrakRenderOptions,
+    ComrakRenderOptions, SlideContentMode, SlidePrintOptions, SlideSplitOptions,
 };
 
 #[propfuzz]
@@ -22,10 +22,12 @@ fn fuzz_doesnt_crash(md: String) {
             footnotes: true,
             description_lists: true,
             front_matter_delimiter: None,
+            image_attributes: true,
         },
         parse: ComrakParseOptions {
             smart: true,
             default_info_string: Some("Rust".to_string()),
+            effects: vec![],
         },
         render: ComrakRenderOptions {
             hardbreaks: true,
@@ -33,6 +35,21 @@ fn fuzz_doesnt_crash(md: String) {
             width: 80,
             unsafe_: true,
             escape: false,
+            slide_content: SlideContentMode::Both,
+            theme: None,
+            theme_dirs: vec![],
+            base_dir: None,
+            reveal: false,
+        },
+        split: SlideSplitOptions {
+            heading_level: 2,
+            thematic_break: true,
+            marker: true,
+        },
+        print: SlidePrintOptions {
+            fragments: true,
+            notes: true,
+            link_footnotes: true,
         },
     };
 
@@ -1018,6 +1035,81 @@ fn nul_replacement_5() {
     html("a\r\n\0b", "<p>a\n\u{fffd}b</p>\n");
 }
 
+#[test]
+fn image_attributes() {
+    html_opts!(
+        [extension.image_attributes],
+        concat!(
+            "![width:200,height:300px,layout:center,filter:blur](a.png)\n",
+            "\n",
+            "![A *big* cat | w:20, filter:sepia(50%)](b.png \"T\")\n",
+            "\n",
+            "![Hello, world: hi](c.png)\n",
+            "\n",
+            "![x|filter:blur(1px;color:red)](d.png)\n",
+        ),
+        concat!(
+            "<p><img src=\"a.png\" alt=\"\" width=\"200\" height=\"300\" class=\"layout-center\" ",
+            "style=\"filter: blur(10px)\" /></p>\n",
+            "<p><img src=\"b.png\" alt=\"A big cat\" title=\"T\" width=\"20\" ",
+            "style=\"filter: sepia(50%)\" /></p>\n",
+            "<p><img src=\"c.png\" alt=\"Hello, world: hi\" /></p>\n",
+            "<p><img src=\"d.png\" alt=\"x|filter:blur(1px;color:red)\" /></p>\n",
+        ),
+    );
+
+    html(
+        "![width:200](a.png)\n",
+        "<p><img src=\"a.png\" alt=\"width:200\" /></p>\n",
+    );
+}
+
+#[test]
+fn image_attributes_skipped() {
+    html_opts!(
+        [extension.image_attributes],
+        concat!(
+            "![width:200,height:300,layout:center,fliter:blur.51px](./xx.png)\n",
+            "\n",
+            "![width:200,height:300,layout:center,filter:blur.51px](./xx.png)\n",
+            "\n",
+            "![Chart|width:wide,opacity:1,filter:hue-rotate90deg](e.png)\n",
+            "\n",
+            "![Note: see|layout:middle](f.png)\n",
+        ),
+        concat!(
+            "<p><img src=\"./xx.png\" alt=\"\" width=\"200\" height=\"300\" ",
+            "class=\"layout-center\" /></p>\n",
+            "<p><img src=\"./xx.png\" alt=\"\" width=\"200\" height=\"300\" ",
+            "class=\"layout-center\" style=\"filter: blur(.51px)\" /></p>\n",
+            "<p><img src=\"e.png\" alt=\"Chart\" style=\"filter: hue-rotate(90deg)\" /></p>\n",
+            "<p><img src=\"f.png\" alt=\"Note: see|layout:middle\" /></p>\n",
+        ),
+    );
+}
+
+#[test]
+fn image_attributes_node() {
+    let arena = Arena::new();
+    let mut options = ComrakOptions::default();
+    options.extension.image_attributes = true;
+    let root = parse_document(&arena, "![Chart|layout:right,h:90](c.png)\n", &options);
+
+    let image = root.first_child().unwrap().first_child().unwrap();
+    match image.data.borrow().value {
+        NodeValue::Image(ref nl) => {
+            assert_eq!(nl.attributes.height, Some(90));
+            assert_eq!(nl.attributes.layout, Some(::nodes::ImageLayout::Right));
+            assert_eq!(nl.attributes.to_string(), "height:90,layout:right");
+        }
+        ref other => panic!("expected an image, got {:?}", other),
+    }
+    assert_eq!(
+        image.first_child().unwrap().data.borrow().value,
+        NodeValue::Text(b"Chart".to_vec())
+    );
+}
+
 #[test]
 fn description_lists() {
     html_opts!(
@@ -1084,97 +1176,2335 @@ fn case_insensitive_safety() {
 }
 
 #[test]
-fn exercise_full_api() {
-    let arena = ::Arena::new();
-    let default_options = ::ComrakOptions::default();
-    let node = ::parse_document(&arena, "# My document\n", &default_options);
-    let mut buffer = vec![];
+fn deck_split() {
+    let arena = Arena::new();
+    let deck = ::parse_deck(
+        &arena,
+        "Intro text.\n\n# Talk\n\n## One\n\nHello\n\n## Two *parts*\n\nBye\n",
+        &ComrakOptions::default(),
+    );
 
-    // Use every member of the exposed API without any defaults.
-    // Not looking for specific outputs, just want to know if the API changes shape.
+    let cover = deck.cover.unwrap();
+    assert_eq!(cover.title, "");
+    assert_eq!(cover.html, "<p>Intro text.</p>\n");
 
-    let _: std::io::Result<()> = ::format_commonmark(node, &default_options, &mut buffer);
+    let titles: Vec<&str> = deck.slides.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Talk", "One", "Two parts"]);
+    assert_eq!(deck.slides[1].nodes.len(), 2);
+    assert_eq!(
+        deck.slides[2].html,
+        "<h2>Two <em>parts</em></h2>\n<p>Bye</p>\n"
+    );
+}
 
-    let _: std::io::Result<()> = ::format_html(node, &default_options, &mut buffer);
+#[test]
+fn deck_empty() {
+    let arena = Arena::new();
+    let deck = ::parse_deck(&arena, "", &ComrakOptions::default());
+    assert!(deck.cover.is_none());
+    assert!(deck.slides.is_empty());
+    assert_eq!(deck.metadata, ::slides::DeckMetadata::default());
+}
 
-    let _: String = ::Anchorizer::new().anchorize("header".to_string());
+#[test]
+fn deck_metadata_and_notes() {
+    let arena = Arena::new();
+    let deck = ::parse_deck(
+        &arena,
+        concat!(
+            "# Talk\n",
+            "---\n",
+            "author: Ann\n",
+            "---\n",
+            "\n",
+            "## One\n",
+            "---\n",
+            "layout: center\n",
+            "bg: red\n",
+            "---\n",
+            "\n",
+            "Hello\n",
+            "\n",
+            "```note\n",
+            "say hi\n",
+            "```\n",
+        ),
+        &ComrakOptions::default(),
+    );
 
-    let _: &AstNode = ::parse_document(&arena, "document", &default_options);
+    assert_eq!(
+        deck.cover.as_ref().unwrap().metadata.get_str("author"),
+        Some("Ann")
+    );
+    assert_eq!(deck.metadata.author, None);
 
-    let _: &AstNode = ::parse_document_with_broken_link_callback(
+    let slide = &deck.slides[0];
+    let meta: Vec<(&str, &str)> = slide
+        .metadata
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str().unwrap()))
+        .collect();
+    assert_eq!(meta, vec![("layout", "center"), ("bg", "red")]);
+    assert_eq!(slide.metadata.get("author"), None);
+    assert_eq!(slide.notes, vec!["say hi\n".to_string()]);
+    assert_eq!(slide.nodes.len(), 4);
+    assert_eq!(slide.html, "<h2>One</h2>\n<p>Hello</p>\n");
+}
+
+#[test]
+fn slide_metadata_typed() {
+    use nodes::{MetaValue, NodeKV};
+
+    let input = concat!(
+        "# Talk\n",
+        "---\n",
+        "title: \"Hello: world\"\n",
+        "background:http://x.png\n",
+        "count: 3\n",
+        "ratio: -1.5e2\n",
+        "draft: false\n",
+        "version: 1.2.3\n",
+        "tags: [a, \"b, c\", 2]\n",
+        "size: {w: 4, h: 3}\n",
+        "'odd:key': 'it''s'\n",
+        "font:\n",
+        "  family: Fira\n",
+        "  weights:\n",
+        "    - 400\n",
+        "    - 700\n",
+        "list:\n",
+        "- a\n",
+        "- b\n",
+        "partial: {a: 1, b}\n",
+        ": nokey\n",
+        "---\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &ComrakOptions::default());
+    let block = root.children().nth(1).unwrap();
+
+    let kvs: Vec<NodeKV> = block
+        .children()
+        .map(|c| match c.data.borrow().value {
+            NodeValue::KV(ref kv) => kv.clone(),
+            ref v => panic!("unexpected child {:?}", v),
+        })
+        .collect();
+    match block.data.borrow().value {
+        NodeValue::SlideMetaDataBlock(ref smd) => assert_eq!(smd.metadatas, kvs),
+        ref v => panic!("unexpected node {:?}", v),
+    }
+
+    let s = |s: &str| MetaValue::String(s.to_string());
+    let entries: Vec<(String, MetaValue)> = kvs
+        .into_iter()
+        .map(|kv| (String::from_utf8(kv.key).unwrap(), kv.value))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("title".to_string(), s("Hello: world")),
+            ("background".to_string(), s("http://x.png")),
+            ("count".to_string(), MetaValue::Number(3.0)),
+            ("ratio".to_string(), MetaValue::Number(-150.0)),
+            ("draft".to_string(), MetaValue::Bool(false)),
+            ("version".to_string(), s("1.2.3")),
+            (
+                "tags".to_string(),
+                MetaValue::List(vec![s("a"), s("b, c"), MetaValue::Number(2.0)])
+            ),
+            (
+                "size".to_string(),
+                MetaValue::Map(vec![
+                    ("w".to_string(), MetaValue::Number(4.0)),
+                    ("h".to_string(), MetaValue::Number(3.0)),
+                ])
+            ),
+            ("odd:key".to_string(), s("it's")),
+            (
+                "font".to_string(),
+                MetaValue::Map(vec![
+                    ("family".to_string(), s("Fira")),
+                    (
+                        "weights".to_string(),
+                        MetaValue::List(vec![MetaValue::Number(400.0), MetaValue::Number(700.0)])
+                    ),
+                ])
+            ),
+            ("list".to_string(), MetaValue::List(vec![s("a"), s("b")])),
+            ("partial".to_string(), s("{a: 1, b}")),
+        ]
+    );
+}
+
+#[test]
+fn slide_json_meta_order() {
+    let arena = Arena::new();
+    let options = ComrakOptions::default();
+    let root = parse_document(
         &arena,
-        "document",
-        &default_options,
-        Some(&mut |_: &[u8]| Some((b"abc".to_vec(), b"xyz".to_vec()))),
+        "# Talk\n---\nzeta: 1\nalpha: [true, x]\nmid: {b: 2, a: 1}\n---\n",
+        &options,
     );
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
 
-    let _ = ::ComrakOptions {
-        extension: ::ComrakExtensionOptions {
-            strikethrough: false,
-            tagfilter: false,
-            table: false,
-            autolink: false,
-            tasklist: false,
-            superscript: false,
-            header_ids: Some("abc".to_string()),
-            footnotes: false,
-            description_lists: false,
-            front_matter_delimiter: None,
-        },
-        parse: ::ComrakParseOptions {
-            smart: false,
-            default_info_string: Some("abc".to_string()),
-        },
-        render: ::ComrakRenderOptions {
-            hardbreaks: false,
-            github_pre_lang: false,
-            width: 123456,
-            unsafe_: false,
-            escape: false,
-        },
-    };
+    assert!(output.contains(r#""meta":{"zeta":1.0,"alpha":[true,"x"],"mid":{"b":2.0,"a":1.0}}"#));
+}
 
-    let _: String = ::markdown_to_html("# Yes", &default_options);
+#[test]
+fn deck_front_matter() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let arena = Arena::new();
+    let deck = ::parse_deck(
+        &arena,
+        concat!(
+            "---\n",
+            "title: \"Rust: a tour\"\n",
+            "author: Ann\n",
+            "date: 2020-05-01\n",
+            "theme: dark\n",
+            "aspect-ratio: 4x3\n",
+            "lang: en\n",
+            "---\n",
+            "\n",
+            "# Cover\n",
+            "\n",
+            "## One\n",
+            "---\n",
+            "theme: light\n",
+            "---\n",
+            "\n",
+            "## Two\n",
+        ),
+        &options,
+    );
 
-    //
+    let meta = &deck.metadata;
+    assert_eq!(meta.title.as_deref(), Some("Rust: a tour"));
+    assert_eq!(meta.author.as_deref(), Some("Ann"));
+    assert_eq!(meta.date.as_deref(), Some("2020-05-01"));
+    assert_eq!(meta.theme.as_deref(), Some("dark"));
+    assert_eq!(
+        meta.aspect_ratio,
+        Some(::slides::AspectRatio {
+            width: 4,
+            height: 3
+        })
+    );
+    assert_eq!(meta.language.as_deref(), Some("en"));
+    assert_eq!(meta.entries.len(), 6);
 
-    let ast = node.data.borrow();
-    let _ = ast.start_line;
-    match &ast.value {
-        ::nodes::NodeValue::Document => {}
-        ::nodes::NodeValue::FrontMatter(_) => {}
-        ::nodes::NodeValue::BlockQuote => {}
-        ::nodes::NodeValue::List(nl) | ::nodes::NodeValue::Item(nl) => {
-            match nl.list_type {
-                ::nodes::ListType::Bullet => {}
-                ::nodes::ListType::Ordered => {}
-            }
-            let _: usize = nl.start;
-            match nl.delimiter {
-                ::nodes::ListDelimType::Period => {}
-                ::nodes::ListDelimType::Paren => {}
-            }
-            let _: u8 = nl.bullet_char;
-            let _: bool = nl.tight;
-        }
-        ::nodes::NodeValue::DescriptionList => {}
-        ::nodes::NodeValue::DescriptionItem(_ndi) => {}
-        ::nodes::NodeValue::DescriptionTerm => {}
-        ::nodes::NodeValue::DescriptionDetails => {}
-        ::nodes::NodeValue::CodeBlock(ncb) => {
-            let _: bool = ncb.fenced;
-            let _: u8 = ncb.fence_char;
-            let _: usize = ncb.fence_length;
-            let _: Vec<u8> = ncb.info;
-            let _: Vec<u8> = ncb.literal;
+    let cover = deck.cover.as_ref().unwrap();
+    assert_eq!(cover.title, "Cover");
+    assert_eq!(cover.metadata.get_str("theme"), Some("dark"));
+    assert_eq!(deck.slides[0].metadata.get_str("theme"), Some("light"));
+    assert_eq!(deck.slides[0].metadata.get_str("author"), Some("Ann"));
+    assert_eq!(deck.slides[1].metadata.get_str("theme"), Some("dark"));
+}
+
+#[test]
+fn deck_aspect_ratio() {
+    use slides::AspectRatio;
+
+    let ratio = |width, height| Some(AspectRatio { width, height });
+    assert_eq!(AspectRatio::parse("16:9"), ratio(16, 9));
+    assert_eq!(AspectRatio::parse("16/10"), ratio(16, 10));
+    assert_eq!(AspectRatio::parse(" 4 x 3 "), ratio(4, 3));
+    assert_eq!(AspectRatio::parse("16"), None);
+    assert_eq!(AspectRatio::parse("0:9"), None);
+    assert_eq!(AspectRatio::parse("wide"), None);
+    assert_eq!(AspectRatio::default().to_string(), "16:9");
+}
+
+#[test]
+fn slide_json_deck() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let arena = Arena::new();
+    let root = parse_document(
+        &arena,
+        "---\ntitle: Talk\naspect_ratio: 16/10\n---\n\n# Cover\n",
+        &options,
+    );
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains(r#""title":"Talk","deck":{"title":"Talk","aspect_ratio":"16:10","meta":{"title":"Talk","aspect_ratio":"16/10"}}"#));
+}
+
+#[test]
+fn deck_fragments() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let arena = Arena::new();
+    let deck = ::parse_deck(
+        &arena,
+        concat!(
+            "---\n",
+            "incremental: true\n",
+            "---\n",
+            "\n",
+            "# Cover\n",
+            "\n",
+            "- a\n",
+            "  - b\n",
+            "- c\n",
+            "\n",
+            "## Two\n",
+            "---\n",
+            "incremental: false\n",
+            "---\n",
+            "\n",
+            "- x\n",
+            "\n",
+            "<!-- fragment -->\n",
+            "\n",
+            "> Later\n",
+            "\n",
+            "<!-- fragment -->\n",
+            "```\n",
+            "code\n",
+            "```\n",
+        ),
+        &options,
+    );
+
+    let cover = deck.cover.unwrap();
+    assert_eq!(cover.steps, 3);
+    assert_eq!(
+        cover.html,
+        concat!(
+            "<h1>Cover</h1>\n",
+            "<ul>\n",
+            "<li data-fragment-index=\"0\">a\n",
+            "<ul>\n",
+            "<li data-fragment-index=\"1\">b</li>\n",
+            "</ul>\n",
+            "</li>\n",
+            "<li data-fragment-index=\"2\">c</li>\n",
+            "</ul>\n",
+        )
+    );
+
+    let two = &deck.slides[0];
+    assert_eq!(two.steps, 2);
+    assert_eq!(
+        two.html,
+        concat!(
+            "<h2>Two</h2>\n",
+            "<ul>\n",
+            "<li>x</li>\n",
+            "</ul>\n",
+            "<blockquote data-fragment-index=\"0\">\n",
+            "<p>Later</p>\n",
+            "</blockquote>\n",
+            "<pre data-fragment-index=\"1\"><code>code\n",
+            "</code></pre>\n",
+        )
+    );
+}
+
+#[test]
+fn slide_json_steps() {
+    let arena = Arena::new();
+    let options = ComrakOptions::default();
+    let root = parse_document(
+        &arena,
+        "# Cover\n\n## Bullets\n---\nincremental: true\n---\n\n- a\n- b\n",
+        &options,
+    );
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains(concat!(
+        r#""split":"heading","steps":0,"#,
+        r#""source":{"start_line":1,"end_line":2,"start_byte":0,"end_byte":9}}"#
+    )));
+    assert!(output.contains(r#"<li data-fragment-index=\"1\">b</li>"#));
+    assert!(output.contains(concat!(
+        r#""split":"heading","steps":2,"#,
+        r#""source":{"start_line":3,"end_line":9,"start_byte":9,"end_byte":55}}"#
+    )));
+}
+
+#[test]
+fn deck_layouts() {
+    use slides::Layout;
+
+    let arena = Arena::new();
+    let deck = ::parse_deck(
+        &arena,
+        concat!(
+            "# Cover\n",
+            "---\n",
+            "layout: title\n",
+            "---\n",
+            "\n",
+            "## Compare\n",
+            "---\n",
+            "layout: two-column\n",
+            "---\n",
+            "\n",
+            "Before\n",
+            "\n",
+            "<!-- column -->\n",
+            "\n",
+            "After\n",
+            "\n",
+            "<!-- column -->\n",
+            "\n",
+            "Extra\n",
+            "\n",
+            "## Plain\n",
+            "\n",
+            "One\n",
+            "<!-- column -->\n",
+            "Two\n",
+            "\n",
+            "## Unknown\n",
+            "---\n",
+            "layout: diagonal\n",
+            "---\n",
+        ),
+        &ComrakOptions::default(),
+    );
+
+    let cover = deck.cover.unwrap();
+    assert_eq!(cover.layout, Some(Layout::Title));
+    assert_eq!(cover.regions.len(), 1);
+    assert_eq!(cover.regions[0].name, "main");
+    assert_eq!(
+        cover.html,
+        "<div class=\"region region-main\">\n<h1>Cover</h1>\n</div>\n"
+    );
+
+    let compare = &deck.slides[0];
+    assert_eq!(compare.layout, Some(Layout::TwoColumn));
+    let names = compare
+        .regions
+        .iter()
+        .map(|r| r.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["left", "right", "column-3"]);
+    assert_eq!(compare.regions[0].nodes.len(), 2);
+    assert_eq!(compare.regions[2].html, "<p>Extra</p>\n");
+    assert_eq!(
+        compare.html,
+        concat!(
+            "<div class=\"region region-left\">\n<h2>Compare</h2>\n<p>Before</p>\n</div>\n",
+            "<div class=\"region region-right\">\n<p>After</p>\n</div>\n",
+            "<div class=\"region region-column-3\">\n<p>Extra</p>\n</div>\n",
+        )
+    );
+
+    let plain = &deck.slides[1];
+    assert_eq!(plain.layout, None);
+    assert_eq!(plain.regions[1].name, "column-2");
+    assert!(plain
+        .html
+        .starts_with("<div class=\"region region-column-1\">\n<h2>Plain</h2>"));
+
+    let unknown = &deck.slides[2];
+    assert_eq!(unknown.layout, None);
+    assert!(!unknown.has_regions());
+    assert_eq!(unknown.html, "<h2>Unknown</h2>\n");
+    assert_eq!(
+        Layout::parse("image-left").unwrap().to_string(),
+        "image-left"
+    );
+}
+
+#[test]
+fn slide_json_regions() {
+    let arena = Arena::new();
+    let options = ComrakOptions::default();
+    let root = parse_document(
+        &arena,
+        "# Cover\n\n## Quote\n---\nlayout: quote\n---\n\nBe *brief*.\n\n<!-- column -->\n\nAnon\n",
+        &options,
+    );
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+
+    assert!(json["front"].get("layout").is_none());
+    assert!(json["front"].get("regions").is_none());
+
+    let slide = &json["content"][0];
+    assert_eq!(slide["layout"], "quote");
+    assert_eq!(slide["regions"][0]["name"], "quote");
+    assert_eq!(
+        slide["regions"][0]["html"],
+        "<h2>Quote</h2>\n<p>Be <em>brief</em>.</p>\n"
+    );
+    assert!(slide["regions"][0]["content"].is_string());
+    assert_eq!(slide["regions"][1]["name"], "attribution");
+    assert_eq!(slide["regions"][1]["html"], "<p>Anon</p>\n");
+
+    let html = standalone(
+        "# Cover\n\n## Quote\n---\nlayout: quote\n---\n\nHi\n",
+        &options,
+    )
+    .unwrap();
+    assert!(html.contains("<section class=\"layout-quote\" id=\"slide-2\" data-index=\"2\""));
+}
+
+#[test]
+fn deck_transitions() {
+    use slides::{Transition, TransitionSpeed, Transitions};
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let input = concat!(
+        "---\n",
+        "transition: slide\n",
+        "background_transition: fade\n",
+        "---\n",
+        "\n",
+        "# Cover\n",
+        "\n",
+        "## Zoom\n",
+        "---\n",
+        "transition: zoom\n",
+        "transition-speed: slow\n",
+        "---\n",
+        "\n",
+        "## Bad\n",
+        "---\n",
+        "transition: wobble\n",
+        "transition-speed: 2s\n",
+        "---\n",
+    );
+    let arena = Arena::new();
+    let deck = ::parse_deck(&arena, input, &options);
+
+    assert_eq!(
+        deck.metadata.transitions,
+        Transitions {
+            transition: Some(Transition::Slide),
+            speed: None,
+            background: Some(Transition::Fade),
         }
-        ::nodes::NodeValue::HtmlBlock(nhb) => {
-            let _: Vec<u8> = nhb.literal;
+    );
+    assert_eq!(deck.cover.unwrap().transitions, deck.metadata.transitions);
+    assert_eq!(
+        deck.slides[0].transitions,
+        Transitions {
+            transition: Some(Transition::Zoom),
+            speed: Some(TransitionSpeed::Slow),
+            background: Some(Transition::Fade),
         }
-        ::nodes::NodeValue::Paragraph => {}
-        ::nodes::NodeValue::Heading(nh) => {
-            let _: u32 = nh.level;
-            let _: bool = nh.setext;
+    );
+    assert_eq!(deck.slides[1].transitions.transition, None);
+    assert_eq!(deck.slides[1].transitions.speed, None);
+
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["deck"]["transition"], "slide");
+    assert_eq!(json["deck"]["background_transition"], "fade");
+    assert_eq!(json["content"][0]["transition"], "zoom");
+    assert_eq!(json["content"][0]["transition_speed"], "slow");
+    assert!(json["content"][1].get("transition").is_none());
+    assert_eq!(json["content"][1]["meta"]["transition"], "wobble");
+
+    let html = standalone(input, &options).unwrap();
+    assert!(html.contains(concat!(
+        "<section id=\"slide-2\" data-index=\"2\" data-steps=\"0\" data-transition=\"zoom\" ",
+        "data-transition-speed=\"slow\" data-background-transition=\"fade\">"
+    )));
+    assert!(html.contains(
+        "<section id=\"slide-3\" data-index=\"3\" data-steps=\"0\" data-background-transition=\"fade\">"
+    ));
+}
+
+#[test]
+fn deck_agenda() {
+    use slides::SplitReason;
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let input = concat!(
+        "---\n",
+        "agenda: true\n",
+        "agenda-title: Plan\n",
+        "agenda-level: 2\n",
+        "agenda-highlight: true\n",
+        "---\n",
+        "\n",
+        "# Talk\n",
+        "\n",
+        "# Why & how\n",
+        "\n",
+        "## Detail\n",
+        "\n",
+        "Before\n",
+        "\n",
+        "::toc\n",
+        "\n",
+        "After\n",
+        "\n",
+        "### Deep\n",
+        "---\n",
+        "agenda: false\n",
+        "---\n",
+    );
+    let arena = Arena::new();
+    let deck = ::parse_deck(&arena, input, &options);
+
+    let titles = deck
+        .agenda
+        .iter()
+        .map(|e| (e.title.as_str(), e.level, e.slide))
+        .collect::<Vec<_>>();
+    assert_eq!(titles, [("Why & how", 1, 3), ("Detail", 2, 4)]);
+
+    let cover = deck.cover.as_ref().unwrap();
+    assert!(!cover.agenda);
+    assert_eq!(cover.section, None);
+
+    let plan = &deck.slides[0];
+    assert_eq!(plan.split, SplitReason::Generated);
+    assert!(plan.agenda);
+    assert_eq!(
+        plan.html,
+        concat!(
+            "<h2>Plan</h2>\n",
+            "<ul class=\"agenda\">\n",
+            "<li class=\"level-1\"><a href=\"#slide-3\">Why &amp; how</a></li>\n",
+            "<li class=\"level-2\"><a href=\"#slide-4\">Detail</a></li>\n",
+            "</ul>\n",
+        )
+    );
+
+    let detail = &deck.slides[2];
+    assert!(detail.agenda);
+    assert_eq!(detail.section, Some(1));
+    assert_eq!(
+        detail.html,
+        concat!(
+            "<h2>Detail</h2>\n",
+            "<p>Before</p>\n",
+            "<ul class=\"agenda\">\n",
+            "<li class=\"level-1\"><a href=\"#slide-3\">Why &amp; how</a></li>\n",
+            "<li class=\"level-2 current\"><a href=\"#slide-4\">Detail</a></li>\n",
+            "</ul>\n",
+            "<p>After</p>\n",
+        )
+    );
+
+    let deep = &deck.slides[3];
+    assert!(!deep.agenda);
+    assert_eq!(deep.section, Some(1));
+
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["agenda"][0]["anchor"], "slide-3");
+    assert_eq!(json["content"][0]["split"], "generated");
+    assert_eq!(json["content"][0]["agenda"], true);
+    assert_eq!(json["content"][2]["section"], 1);
+    assert!(!json["content"][2]["content"]
+        .as_str()
+        .unwrap()
+        .contains("::toc"));
+
+    let html = standalone(input, &options).unwrap();
+    assert!(html.contains(concat!(
+        "<nav class=\"agenda-nav\">\n",
+        "<ul class=\"agenda\">\n",
+        "<li class=\"level-1 current\"><a href=\"#slide-3\">Why &amp; how</a></li>\n",
+    )));
+}
+
+#[test]
+fn slide_standalone() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let arena = Arena::new();
+    let root = parse_document(
+        &arena,
+        concat!(
+            "---\n",
+            "title: Fish & chips\n",
+            "aspect_ratio: 4:3\n",
+            "lang: fr\n",
+            "---\n",
+            "\n",
+            "# Cover\n",
+            "\n",
+            "Note: hi & bye\n",
+            "\n",
+            "## Two\n",
+            "---\n",
+            "incremental: true\n",
+            "---\n",
+            "\n",
+            "- a\n",
+        ),
+        &options,
+    );
+    let mut output = vec![];
+    ::format_slide_standalone(root, &options, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.starts_with("<!DOCTYPE html>\n<html lang=\"fr\">\n"));
+    assert!(output.contains("<title>Fish &amp; chips</title>"));
+    assert!(output.contains(
+        "<div class=\"deck theme-light\" data-aspect-ratio=\"4:3\" style=\"--aspect-width: 4; --aspect-height: 3\">\n"
+    ));
+    assert!(output.contains(concat!(
+        "<section id=\"slide-1\" data-index=\"1\" data-steps=\"0\">\n",
+        "<h1>Cover</h1>\n",
+        "<aside class=\"notes\">\n",
+        "<p>hi &amp; bye</p>\n",
+        "</aside>\n",
+        "</section>\n",
+        "<section id=\"slide-2\" data-index=\"2\" data-steps=\"1\">\n",
+        "<h2>Two</h2>\n",
+        "<ul>\n",
+        "<li data-fragment-index=\"0\">a</li>\n",
+        "</ul>\n",
+        "</section>\n",
+        "</div>\n",
+    )));
+    assert!(output.contains("<style>\n"));
+    assert!(output.contains("<script>\n"));
+    assert!(output.ends_with("</body>\n</html>\n"));
+}
+
+fn standalone(input: &str, options: &ComrakOptions) -> std::io::Result<String> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, options);
+    let mut output = vec![];
+    ::format_slide_standalone(root, options, &mut output)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn slide_themes() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let input = concat!(
+        "---\n",
+        "theme: dark\n",
+        "---\n",
+        "\n",
+        "# Cover\n",
+        "\n",
+        "## Loud\n",
+        "---\n",
+        "theme: high-contrast\n",
+        "class: [wide, centered]\n",
+        "---\n",
+        "\n",
+        "## Quiet\n",
+        "---\n",
+        "class: plain\n",
+        "---\n",
+    );
+
+    let output = standalone(input, &options).unwrap();
+    assert!(output.contains(".deck {\n  --background: #1e1e1e;\n"));
+    assert!(output.contains(".deck > section.theme-high-contrast {\n  --background: #000000;\n"));
+    assert!(output.contains("<div class=\"deck theme-dark\""));
+    assert!(output
+        .contains("<section id=\"slide-1\" data-index=\"1\" data-steps=\"0\">\n<h1>Cover</h1>"));
+    assert!(output.contains(
+        "<section class=\"theme-high-contrast wide centered\" id=\"slide-2\" data-index=\"2\" data-steps=\"0\">"
+    ));
+    assert!(output
+        .contains("<section class=\"plain\" id=\"slide-3\" data-index=\"3\" data-steps=\"0\">"));
+
+    options.render.theme = Some("light".to_string());
+    let output = standalone(input, &options).unwrap();
+    assert!(output.contains(".deck {\n  --background: #ffffff;\n"));
+    assert!(output.contains("<div class=\"deck theme-light\""));
+
+    options.render.theme = Some("plaid".to_string());
+    let error = standalone(input, &options).unwrap_err();
+    assert_eq!(error.to_string(), "unknown theme `plaid`");
+}
+
+#[test]
+fn slide_theme_dir() {
+    let dir = std::env::temp_dir().join(format!("comrak-themes-{}", std::process::id()));
+    let theme = dir.join("solar");
+    std::fs::create_dir_all(&theme).unwrap();
+    std::fs::write(
+        theme.join("theme.yaml"),
+        "variables:\n  background: \"#fdf6e3\"\n  glow: 2px\nfonts:\n  body: Georgia, serif\n",
+    )
+    .unwrap();
+    std::fs::write(
+        theme.join("theme.css"),
+        "h1 { text-transform: uppercase; }\n",
+    )
+    .unwrap();
+
+    let loaded = ::themes::Theme::resolve("solar", std::slice::from_ref(&dir)).unwrap();
+    assert_eq!(loaded.name, "solar");
+    assert_eq!(loaded.variable("background"), Some("#fdf6e3"));
+    assert_eq!(loaded.variable("accent"), Some("#0366d6"));
+    assert_eq!(loaded.variable("glow"), Some("2px"));
+
+    let mut options = ComrakOptions::default();
+    options.render.theme = Some("solar".to_string());
+    options.render.theme_dirs = vec![dir.clone()];
+    let output = standalone("# Hi\n", &options).unwrap();
+    assert!(output.contains("  --background: #fdf6e3;\n"));
+    assert!(output.contains("  --font-body: Georgia, serif;\n"));
+    assert!(output.contains("h1 { text-transform: uppercase; }\n"));
+
+    assert!(::themes::Theme::resolve("../solar", &[theme]).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn slide_print() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let input = concat!(
+        "---\n",
+        "aspect-ratio: 4:3\n",
+        "---\n",
+        "\n",
+        "# Cover\n",
+        "\n",
+        "See [docs](https://example.com/?a&b), [here](#top) and <https://rust-lang.org>.\n",
+        "\n",
+        "Note: smile\n",
+        "\n",
+        "## Steps\n",
+        "---\n",
+        "incremental: true\n",
+        "---\n",
+        "\n",
+        "- a\n",
+        "- b\n",
+    );
+    let print = |options: &ComrakOptions| {
+        let arena = Arena::new();
+        let root = parse_document(&arena, input, options);
+        let mut output = vec![];
+        ::format_slide_print(root, options, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let html = print(&options);
+    assert!(html.contains("@page {\n  size: 10in 7.5in;\n  margin: 0;\n}"));
+    assert_eq!(
+        html.matches("<div class=\"deck theme-light page\">")
+            .count(),
+        2
+    );
+    assert!(html.contains("<a href=\"https://example.com/?a&amp;b\">docs</a>, "));
+    assert!(!html.contains("<ol class=\"link-notes\">"));
+    assert!(!html.contains("<aside class=\"notes\">"));
+    assert!(!html.contains("<script>"));
+
+    options.print = SlidePrintOptions {
+        fragments: true,
+        notes: true,
+        link_footnotes: true,
+    };
+    let html = print(&options);
+    assert!(html.contains("size: 10in 11.25in;"));
+    assert_eq!(
+        html.matches("<div class=\"deck theme-light page\">")
+            .count(),
+        4
+    );
+    assert!(html.contains(concat!(
+        "<section id=\"slide-1\" data-index=\"1\" data-steps=\"0\" data-step=\"0\">\n",
+        "<h1>Cover</h1>\n",
+        "<p>See <a href=\"https://example.com/?a&amp;b\">docs</a><sup class=\"link-ref\">1</sup>, ",
+        "<a href=\"#top\">here</a> and ",
+        "<a href=\"https://rust-lang.org\">https://rust-lang.org</a><sup class=\"link-ref\">2</sup>.</p>\n",
+        "<ol class=\"link-notes\">\n",
+        "<li>https://example.com/?a&amp;b</li>\n",
+        "<li>https://rust-lang.org</li>\n",
+        "</ol>\n",
+        "</section>\n",
+        "<aside class=\"notes\">\n",
+        "<p>smile</p>\n",
+        "</aside>\n",
+        "</div>\n",
+    )));
+    assert!(
+        html.contains("<section id=\"slide-2\" data-index=\"2\" data-steps=\"2\" data-step=\"0\">")
+    );
+    assert!(html.contains("<section data-index=\"2\" data-steps=\"2\" data-step=\"2\">"));
+    assert!(html.contains(concat!(
+        ".deck.page > section[data-step=\"1\"] [data-fragment-index=\"1\"] {\n",
+        "  visibility: hidden;\n",
+        "}\n",
+    )));
+}
+
+/// The entries of a ZIP archive of stored entries, checking each one's CRC.
+fn unzip(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |at: usize| usize::from(u16::from_le_bytes([data[at], data[at + 1]]));
+    let u32_at =
+        |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+
+    let mut entries = vec![];
+    let mut at = 0;
+    while data[at..].starts_with(b"PK\x03\x04") {
+        assert_eq!(u16_at(at + 8), 0, "entries are stored");
+        let size = u32_at(at + 18) as usize;
+        let name_len = u16_at(at + 26);
+        let start = at + 30 + name_len + u16_at(at + 28);
+        let name = String::from_utf8(data[at + 30..at + 30 + name_len].to_vec()).unwrap();
+        let content = data[start..start + size].to_vec();
+        assert_eq!(::package::zip::crc32(&content), u32_at(at + 14), "{}", name);
+        entries.push((name, content));
+        at = start + size;
+    }
+    assert!(data[at..].starts_with(b"PK\x01\x02"));
+    entries
+}
+
+#[test]
+fn slide_pptx() {
+    let dir = std::env::temp_dir().join(format!("comrak-pptx-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("img")).unwrap();
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&[0, 0, 0, 200, 0, 0, 0, 100, 8, 6, 0, 0, 0]);
+    std::fs::write(dir.join("img/chart.png"), &png).unwrap();
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.table = true;
+    options.render.base_dir = Some(dir.clone());
+    let input = concat!(
+        "---\n",
+        "title: Q&A\n",
+        "aspect-ratio: 4:3\n",
+        "---\n",
+        "\n",
+        "# Cover\n",
+        "\n",
+        "- one **bold**\n",
+        "  - nested\n",
+        "1. first\n",
+        "\n",
+        "Note: say hello\n",
+        "\n",
+        "## Data\n",
+        "\n",
+        "| a | b |\n",
+        "|---|--:|\n",
+        "| 1 | 2 |\n",
+        "\n",
+        "![Chart](img/chart.png) ![Remote](https://example.com/x.png)\n",
+        "\n",
+        "See [back](#slide-1).\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_pptx(root, &options, &mut output).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let entries = unzip(&output);
+    let part = |name: &str| {
+        let (_, data) = entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("no {}", name));
+        String::from_utf8_lossy(data).into_owned()
+    };
+    assert_eq!(entries[0].0, "[Content_Types].xml");
+    let types = part("[Content_Types].xml");
+    assert!(types.contains("<Default Extension=\"png\" ContentType=\"image/png\"/>"));
+    assert!(types.contains("<Override PartName=\"/ppt/slides/slide2.xml\""));
+    assert!(types.contains("<Override PartName=\"/ppt/notesSlides/notesSlide1.xml\""));
+    assert!(!types.contains("/ppt/notesSlides/notesSlide2.xml"));
+
+    let presentation = part("ppt/presentation.xml");
+    assert!(presentation.contains("<p:sldSz cx=\"12192000\" cy=\"9144000\"/>"));
+    assert_eq!(presentation.matches("<p:sldId ").count(), 2);
+    assert!(part("docProps/core.xml").contains("<dc:title>Q&amp;A</dc:title>"));
+
+    let cover = part("ppt/slides/slide1.xml");
+    assert!(cover.contains("<p:ph type=\"title\"/>"));
+    assert!(cover.contains("<a:t>Cover</a:t>"));
+    assert!(cover.contains("<a:buChar char=\"&#8226;\"/>"));
+    assert!(cover.contains("<a:pPr marL=\"742950\" lvl=\"1\" indent=\"-285750\">"));
+    assert!(cover.contains("<a:r><a:rPr sz=\"2400\" b=\"1\"/><a:t>bold</a:t></a:r>"));
+    assert!(cover.contains("<a:buAutoNum type=\"arabicPeriod\" startAt=\"1\"/>"));
+    assert!(!cover.contains("say hello"));
+    assert!(part("ppt/notesSlides/notesSlide1.xml").contains("<a:t>say hello</a:t>"));
+    assert!(part("ppt/slides/_rels/slide1.xml.rels").contains("../notesSlides/notesSlide1.xml"));
+
+    let data = part("ppt/slides/slide2.xml");
+    assert!(data.contains("<a:tbl>"));
+    assert!(data.contains("<a:gridCol "));
+    assert!(data.contains("<a:pPr algn=\"r\"/>"));
+    assert!(data.contains("<a:blip r:embed=\"rId3\"/>"));
+    assert!(data.contains("descr=\"Chart\""));
+    assert!(data.contains("<a:t>Remote</a:t>"));
+    assert!(data.contains("<a:r><a:rPr sz=\"2400\"><a:hlinkClick r:id=\"rId2\" action=\"ppaction://hlinksldjump\"/></a:rPr><a:t>back</a:t></a:r>"));
+    let rels = part("ppt/slides/_rels/slide2.xml.rels");
+    assert!(rels.contains("Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide\" Target=\"slide1.xml\""));
+    assert!(rels.contains("Id=\"rId3\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/image\" Target=\"../media/image1.png\""));
+    let media = entries
+        .iter()
+        .find(|(n, _)| n == "ppt/media/image1.png")
+        .unwrap();
+    assert_eq!(media.1, png);
+}
+
+#[test]
+fn slide_odp() {
+    let dir = std::env::temp_dir().join(format!("comrak-odp-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("img")).unwrap();
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&[0, 0, 0, 200, 0, 0, 0, 100, 8, 6, 0, 0, 0]);
+    std::fs::write(dir.join("img/chart.png"), &png).unwrap();
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.table = true;
+    options.render.base_dir = Some(dir.clone());
+    let input = concat!(
+        "---\n",
+        "title: Q&A\n",
+        "aspect-ratio: 4:3\n",
+        "language: en-GB\n",
+        "---\n",
+        "\n",
+        "# Cover\n",
+        "\n",
+        "- one **bold**\n",
+        "  - nested\n",
+        "3. third\n",
+        "\n",
+        "Note: say hello\n",
+        "\n",
+        "## Data\n",
+        "\n",
+        "| a | b |\n",
+        "|---|--:|\n",
+        "| 1 | 2 |\n",
+        "\n",
+        "![Chart](img/chart.png)\n",
+        "\n",
+        "See [back](#slide-1).\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_odp(root, &options, &mut output).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let entries = unzip(&output);
+    let part = |name: &str| {
+        let (_, data) = entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("no {}", name));
+        String::from_utf8_lossy(data).into_owned()
+    };
+    assert_eq!(entries[0].0, "mimetype");
+    assert_eq!(
+        part("mimetype"),
+        "application/vnd.oasis.opendocument.presentation"
+    );
+
+    let styles = part("styles.xml");
+    assert!(styles.contains("fo:page-width=\"33.867cm\" fo:page-height=\"25.4cm\""));
+    assert!(styles.contains("fo:language=\"en\" fo:country=\"GB\""));
+    assert!(part("meta.xml").contains("<dc:title>Q&amp;A</dc:title>"));
+
+    let content = part("content.xml");
+    assert_eq!(content.matches("<draw:page ").count(), 2);
+    assert!(content.contains("<draw:page draw:name=\"slide-1\" draw:master-page-name=\"Default\">"));
+    assert!(content.contains("presentation:class=\"title\""));
+    assert!(content.contains("<text:p>Cover</text:p>"));
+    assert!(content.contains("<text:list text:style-name=\"LB\"><text:list-item>"));
+    assert!(content
+        .contains("<text:list text:style-name=\"LN\"><text:list-item text:start-value=\"3\">"));
+    assert!(content.contains("<text:span text:style-name=\"T1\">bold</text:span>"));
+    assert!(content.contains("<presentation:notes>"));
+    assert!(content.contains("say hello"));
+    assert!(content.contains("<table:table "));
+    assert!(content.contains("<text:p text:style-name=\"Ptable-right\">2</text:p>"));
+    assert!(content.contains("xlink:href=\"Pictures/image1.png\""));
+    assert!(content.contains("<svg:desc>Chart</svg:desc>"));
+    assert!(content.contains("xlink:href=\"#slide-1\""));
+
+    assert!(part("META-INF/manifest.xml")
+        .contains("manifest:full-path=\"Pictures/image1.png\" manifest:media-type=\"image/png\""));
+    let media = entries
+        .iter()
+        .find(|(n, _)| n == "Pictures/image1.png")
+        .unwrap();
+    assert_eq!(media.1, png);
+}
+
+#[test]
+fn local_images_confined() {
+    use package::LocalImage;
+    use std::path::Path;
+
+    let root = std::env::temp_dir().join(format!("comrak-images-{}", std::process::id()));
+    let dir = root.join("deck");
+    std::fs::create_dir_all(dir.join("img")).unwrap();
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&[0, 0, 0, 200, 0, 0, 0, 100, 8, 6, 0, 0, 0]);
+    std::fs::write(dir.join("img/chart.png"), &png).unwrap();
+    std::fs::write(root.join("secret.png"), &png).unwrap();
+
+    let load = |url: &str, base_dir: Option<&Path>| LocalImage::load(url, base_dir).is_some();
+    let (chart, secret) = (dir.join("img/chart.png"), root.join("secret.png"));
+    assert!(load("img/chart.png", Some(&dir)));
+    assert!(load("img/../img/chart.png", Some(&dir)));
+    assert!(load(chart.to_str().unwrap(), Some(&dir)));
+    assert!(!load("img/chart.png", None));
+    assert!(!load("../secret.png", Some(&dir)));
+    assert!(!load("img/%2E%2E/%2E%2E/secret.png", Some(&dir)));
+    assert!(!load(secret.to_str().unwrap(), Some(&dir)));
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&secret, dir.join("link.png")).unwrap();
+        assert!(!load("link.png", Some(&dir)));
+    }
+
+    let mut options = ComrakOptions::default();
+    options.render.base_dir = Some(dir.clone());
+    let beamer = |options: &ComrakOptions| {
+        let arena = Arena::new();
+        let root = parse_document(
+            &arena,
+            "![Chart](img/chart.png) ![Secret](../secret.png)\n",
+            options,
+        );
+        let mut latex = vec![];
+        ::format_beamer(root, options, &mut latex).unwrap();
+        String::from_utf8(latex).unwrap()
+    };
+    let latex = beamer(&options);
+    assert!(latex.contains(&format!("keepaspectratio]{{{}}}", chart.to_string_lossy())));
+    assert!(latex.contains(" Secret"));
+    assert!(!latex.contains("secret.png"));
+
+    options.render.base_dir = None;
+    let latex = beamer(&options);
+    assert!(!latex.contains("\\includegraphics"));
+    assert!(latex.contains("Chart Secret"));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn slide_beamer() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.footnotes = true;
+    let input = concat!(
+        "---\n",
+        "title: R&D\n",
+        "author: Ann\n",
+        "aspect-ratio: 4:3\n",
+        "---\n",
+        "\n",
+        "# Costs_1 & $benefits\n",
+        "\n",
+        "- 50% [done]\n",
+        "3. third\n",
+        "\n",
+        "Note: say ~hello~\n",
+        "\n",
+        "## Code\n",
+        "---\n",
+        "incremental: true\n",
+        "---\n",
+        "\n",
+        "- a\n",
+        "- b\n",
+        "\n",
+        "<!-- fragment -->\n",
+        "\n",
+        "```python\n",
+        "print(\"{}\" % 1)\n",
+        "```\n",
+        "\n",
+        "<!-- column -->\n",
+        "\n",
+        "See [back](#slide-1) and [docs](https://example.com/#top)[^n].\n",
+        "\n",
+        "[^n]: A *note*.\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_beamer(root, &options, &mut output).unwrap();
+    let latex = String::from_utf8(output).unwrap();
+
+    assert!(latex.starts_with("\\documentclass[aspectratio=43]{beamer}\n"));
+    assert!(latex.contains("\\title{R\\&D}\n\\author{Ann}\n"));
+    assert!(latex.contains("\\begin{frame}[plain]\n\\titlepage\n\\end{frame}\n"));
+    assert!(latex.contains(concat!(
+        "\\begin{frame}[label=slide-1]\n",
+        "\\frametitle{Costs\\_1 \\& \\$benefits}\n",
+        "\\begin{itemize}\n",
+        "\\item 50\\% {[}done{]}\n",
+        "\\end{itemize}\n",
+        "\\begin{enumerate}\n",
+        "\\setcounter{enumi}{2}\n",
+        "\\item third\n",
+        "\\end{enumerate}\n",
+        "\\note{say \\textasciitilde{}hello\\textasciitilde{}}\n",
+        "\\end{frame}\n",
+    )));
+    assert!(latex.contains(concat!(
+        "\\begin{frame}[fragile, label=slide-2]\n",
+        "\\frametitle{Code}\n",
+        "\\begin{columns}[T]\n",
+        "\\begin{column}{0.48\\textwidth}\n",
+        "\\begin{itemize}\n",
+        "\\item<2-> a\n",
+        "\\item<3-> b\n",
+        "\\end{itemize}\n",
+        "\\begin{uncoverenv}<4->\n",
+        "\\begin{lstlisting}[language=Python]\n",
+        "print(\"{}\" % 1)\n",
+        "\\end{lstlisting}\n",
+        "\\end{uncoverenv}\n",
+        "\\end{column}\n",
+    )));
+    assert!(latex.contains(
+        "See \\hyperlink{slide-1}{back} and \\href{https://example.com/\\#top}{docs}\\footnote{A \\emph{note}.}.\n"
+    ));
+    assert!(latex.ends_with("\\end{document}\n"));
+}
+
+#[test]
+fn slide_beamer_escapes() {
+    let dir = std::env::temp_dir().join(format!("comrak-beamer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("x}%.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+    std::fs::write(dir.join("x}{secret}.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+
+    let mut options = ComrakOptions::default();
+    options.render.base_dir = Some(dir.clone());
+    let input = concat!(
+        "# Escapes\n",
+        "\n",
+        "![Percent](x%7D%25.png) ![Input](x%7D%7Bsecret%7D.png)\n",
+        "\n",
+        "```\n",
+        "\\end{verbatim}\\input{/etc/passwd}\n",
+        "```\n",
+        "\n",
+        "```python\n",
+        "\\end{lstlisting}\n",
+        "```\n",
+        "\n",
+        "```latex\n",
+        "  \\end{frame}\n",
+        "```\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_beamer(root, &options, &mut output).unwrap();
+    let latex = String::from_utf8(output).unwrap();
+
+    assert!(!latex.contains("\\includegraphics"));
+    assert!(latex.contains("Percent Input"));
+    assert!(latex.contains(concat!(
+        "\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n",
+        "\\textbackslash{}end\\{verbatim\\}\\textbackslash{}input\\{/etc/passwd\\}\n",
+        "\\end{Verbatim}\n",
+        "\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n",
+        "\\textbackslash{}end\\{lstlisting\\}\n",
+        "\\end{Verbatim}\n",
+        "\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n",
+        "  \\textbackslash{}end\\{frame\\}\n",
+        "\\end{Verbatim}\n",
+    )));
+    assert!(!latex.contains("\\begin{lstlisting}"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn slide_typst() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.table = true;
+    options.extension.strikethrough = true;
+    let input = concat!(
+        "---\n",
+        "title: \"Q\\\"A\"\n",
+        "aspect-ratio: 4:3\n",
+        "language: en-GB\n",
+        "---\n",
+        "\n",
+        "# Costs_1 & $benefits\n",
+        "\n",
+        "- a *b* **c** ~~d~~ `e` #tag @ref 1 < 2 > 0 [y] // z -- ...\n",
+        "  - nested\n",
+        "3. third\n",
+        "\n",
+        "\\- 1. = not a list\n",
+        "\n",
+        "## Data\n",
+        "\n",
+        "| a | b |\n",
+        "|---|--:|\n",
+        "| 1 | 2 |\n",
+        "\n",
+        "````rust\n",
+        "let s = \"```\";\n",
+        "````\n",
+        "\n",
+        "See [back](#slide-1).\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_typst(root, &options, &mut output).unwrap();
+    let typst = String::from_utf8(output).unwrap();
+
+    assert!(typst.starts_with("#set document(title: \"Q\\\"A\")\n"));
+    assert!(typst.contains("#set page(width: 33.867cm, height: 25.4cm, "));
+    assert!(typst.contains("lang: \"en\", region: \"GB\""));
+    assert!(typst.contains(concat!(
+        "#pagebreak()\n",
+        "\n",
+        "#metadata(\"slide-1\") <slide-1>\n",
+        "= Costs\\_1 & \\$benefits\n",
+        "\n",
+        "- a #emph[b] #strong[c] #strike[d] `e` \\#tag \\@ref 1 \\< 2 \\> 0 \\[y\\] \\/\\/ z \\-- \\.\\..\n",
+        "  - nested\n",
+        "\n",
+        "3. third\n",
+        "\n",
+        "\\- 1. = not a list\n",
+    )));
+    assert!(typst.contains(concat!(
+        "#table(\n",
+        "  columns: 2,\n",
+        "  align: (auto, right),\n",
+        "  table.header([a], [b]),\n",
+        "  [1], [2],\n",
+        ")\n",
+    )));
+    assert!(typst.contains("````rust\nlet s = \"```\";\n````\n"));
+    assert!(typst.contains("See #link(<slide-1>)[back];.\n"));
+}
+
+#[test]
+fn slide_reveal() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    let input = concat!(
+        "# Talk\n",
+        "\n",
+        "# Why\n",
+        "---\n",
+        "transition: fade\n",
+        "background-color: \"#123\"\n",
+        "background_image: a.png\n",
+        "---\n",
+        "\n",
+        "<!-- fragment -->\n",
+        "\n",
+        "- one\n",
+        "\n",
+        "Note: why\n",
+        "\n",
+        "## Detail\n",
+        "\n",
+        "See [back](#slide-1).\n",
+        "\n",
+        "# How\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_reveal(root, &options, &mut output).unwrap();
+    let html = String::from_utf8(output).unwrap();
+
+    assert!(html.starts_with("<div class=\"reveal\">\n<div class=\"slides\">\n"));
+    assert!(html.ends_with("</div>\n</div>\n"));
+    assert!(html.contains(concat!(
+        "<section>\n",
+        "<section id=\"slide-2\" data-transition=\"fade\" ",
+        "data-background-color=\"#123\" data-background-image=\"a.png\">\n",
+        "<h1>Why</h1>\n",
+        "<ul class=\"fragment\" data-fragment-index=\"0\">\n",
+        "<li>one</li>\n",
+        "</ul>\n",
+        "<aside class=\"notes\">\n<p>why</p>\n</aside>\n",
+        "</section>\n",
+        "<section id=\"slide-3\">\n",
+        "<h2>Detail</h2>\n",
+        "<p>See <a href=\"#/slide-1\">back</a>.</p>\n",
+        "</section>\n",
+        "</section>\n",
+        "<section id=\"slide-4\">\n<h1>How</h1>\n</section>\n",
+    )));
+
+    let root = parse_document(&arena, "# Talk\n\n::toc\n\n# Why\n", &options);
+    let mut output = vec![];
+    ::format_reveal(root, &options, &mut output).unwrap();
+    let html = String::from_utf8(output).unwrap();
+    assert!(html.contains("<li class=\"level-1\"><a href=\"#/slide-2\">Why</a></li>\n"));
+}
+
+#[test]
+fn import_dialects() {
+    use import::{parse_dialect, Dialect};
+    use nodes::MetaValue;
+
+    let arena = Arena::new();
+    let deck = |input, dialect: Dialect| {
+        let options = dialect.options(&ComrakOptions::default());
+        let root = parse_dialect(&arena, input, dialect, &options);
+        let deck = ::build_deck(root, &options);
+        let slides = deck
+            .cover
+            .into_iter()
+            .chain(deck.slides)
+            .collect::<Vec<_>>();
+        (deck.metadata, slides)
+    };
+
+    let (metadata, slides) = deck(
+        concat!(
+            "---\n",
+            "theme: gaia\n",
+            "backgroundColor: white\n",
+            "---\n",
+            "\n",
+            "<!-- paginate: true -->\n",
+            "<!-- _class: lead -->\n",
+            "# Title\n",
+            "\n",
+            "<!-- Hello. -->\n",
+            "\n",
+            "---\n",
+            "\n",
+            "## Two\n",
+            "\n",
+            "```\n",
+            "---\n",
+            "headingDivider: 1\n",
+            "```\n",
+            "\n",
+            "## Three\n",
+            "\n",
+            "<!-- headingDivider: 2 -->\n",
+        ),
+        Dialect::Marp,
+    );
+    assert_eq!(metadata.theme.as_deref(), Some("gaia"));
+    assert_eq!(metadata.entries.get_str("background-color"), Some("white"));
+    assert_eq!(metadata.entries.get_str("heading-divider"), None);
+    assert_eq!(slides.len(), 3);
+    assert_eq!(slides[0].metadata.get_str("class"), Some("lead"));
+    assert_eq!(slides[0].notes, vec!["Hello."]);
+    assert_eq!(slides[0].html, "<h1>Title</h1>\n");
+    assert_eq!(
+        slides[1].metadata.get("paginate"),
+        Some(&MetaValue::Bool(true))
+    );
+    assert_eq!(slides[1].metadata.get("class"), None);
+    assert_eq!(
+        slides[1].html,
+        "<h2>Two</h2>\n<pre><code>---\nheadingDivider: 1\n</code></pre>\n"
+    );
+    assert_eq!(slides[2].title, "Three");
+
+    let (_, slides) = deck(
+        concat!(
+            "class: center, middle\n",
+            "\n",
+            "# Remark\n",
+            "\n",
+            "???\n",
+            "Notes\n",
+            "\n",
+            "---\n",
+            "\n",
+            "- one\n",
+            "--\n",
+            "- two\n",
+            "\n",
+            "Para one\n",
+            "\n",
+            "Para two\n",
+            "--\n",
+            "Para three\n",
+        ),
+        Dialect::Remark,
+    );
+    assert_eq!(slides.len(), 2);
+    assert_eq!(
+        slides[0].metadata.get("class"),
+        Some(&MetaValue::List(vec![
+            MetaValue::String("center".to_string()),
+            MetaValue::String("middle".to_string()),
+        ]))
+    );
+    assert_eq!(slides[0].notes, vec!["Notes\n"]);
+    assert_eq!(slides[1].steps, 2);
+    assert!(slides[1]
+        .html
+        .contains("<ul data-fragment-index=\"0\">\n<li>two</li>"));
+    assert!(slides[1].html.contains(concat!(
+        "<p data-fragment-index=\"0\">Para one</p>\n",
+        "<p data-fragment-index=\"0\">Para two</p>\n",
+        "<p data-fragment-index=\"1\">Para three</p>\n",
+    )));
+
+    let (_, slides) = deck(
+        concat!(
+            "## One\n",
+            "<!-- .slide: data-background-color=\"#f00\" data-transition='zoom' -->\n",
+            "\n",
+            "Note: Say\n",
+            "more\n",
+            "\n",
+            "---\n",
+            "\n",
+            "## Two\n",
+            "\n",
+            "--\n",
+            "\n",
+            "## Down\n",
+        ),
+        Dialect::Reveal,
+    );
+    assert_eq!(slides.len(), 3);
+    assert_eq!(slides[0].metadata.get_str("background-color"), Some("#f00"));
+    assert_eq!(
+        slides[0].transitions.transition,
+        Some(::slides::Transition::Zoom)
+    );
+    assert_eq!(slides[0].notes, vec!["Say\nmore\n"]);
+    assert_eq!(slides[0].html, "<h2>One</h2>\n");
+    assert_eq!(slides[1].metadata.get("vertical"), None);
+    assert_eq!(slides[2].title, "Down");
+    assert_eq!(
+        slides[2].metadata.get("vertical"),
+        Some(&MetaValue::Bool(true))
+    );
+
+    let options = Dialect::Reveal.options(&ComrakOptions::default());
+    let root = parse_dialect(
+        &arena,
+        "## One\n---\n## Two\n--\n## Down\n",
+        Dialect::Reveal,
+        &options,
+    );
+    let mut html = vec![];
+    ::format_reveal(root, &options, &mut html).unwrap();
+    assert!(String::from_utf8(html).unwrap().contains(concat!(
+        "<section>\n",
+        "<section id=\"slide-2\">\n<h2>Two</h2>\n</section>\n",
+        "<section id=\"slide-3\">\n<h2>Down</h2>\n</section>\n",
+        "</section>\n",
+    )));
+}
+
+/// The node values of a tree, one per line and indented by depth.
+fn ast_values<'a>(node: &'a AstNode<'a>) -> String {
+    node.traverse()
+        .filter_map(|edge| match edge {
+            ::arena_tree::NodeEdge::Start(n) => Some(n),
+            ::arena_tree::NodeEdge::End(_) => None,
+        })
+        .map(|n| {
+            let depth = n.ancestors().count() - 1;
+            format!("{}{:?}\n", "  ".repeat(depth), n.data.borrow().value)
+        })
+        .collect()
+}
+
+#[test]
+fn commonmark_round_trip_slides() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.image_attributes = true;
+    let input = concat!(
+        "---\n",
+        "title: Talk\n",
+        "---\n",
+        "\n",
+        "# Compare\n",
+        "---\n",
+        "layout: two-column\n",
+        "tags: [a, \"b c\"]\n",
+        "---\n",
+        "\n",
+        "Hello ::effect[zoom: 2, target=\"#a\"] world\n",
+        "\n",
+        "::effect[fade]\n",
+        "\n",
+        "![A chart|width:200,filter:blur](chart.png)\n",
+        "\n",
+        "<!-- column -->\n",
+        "\n",
+        "```note\n",
+        "Speak slowly.\n",
+        "```\n",
+        "\n",
+        "Note: and smile\n",
+        "\n",
+        "## Steps\n",
+        "\n",
+        "<!-- fragment -->\n",
+        "\n",
+        "- one ::effect[x: \"a\n",
+        "- two ::effect[bad name]\n",
+    );
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &options);
+    let mut md = vec![];
+    cm::format_document(root, &options, &mut md).unwrap();
+    let md = String::from_utf8(md).unwrap();
+
+    assert!(md.contains("# Compare\n---\nlayout: two-column\ntags: [a, \"b c\"]\n---\n"));
+    assert!(md.contains("Hello ::effect[zoom: 2, target=\"#a\"] world\n"));
+    assert!(md.contains("- one ::effect[x: \"a\n"));
+    let round_trip = parse_document(&arena, &md, &options);
+    compare_strs(&ast_values(round_trip), &ast_values(root), "round trip");
+}
+
+#[test]
+fn parse_diagnostics() {
+    use diagnostics::{DiagnosticCode, Severity};
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.parse.effects = vec!["wobble".to_string()];
+    let input = concat!(
+        "---\n",
+        "title: Talk\n",
+        "  stray\n",
+        "---\n",
+        "\n",
+        "# One\n",
+        "---\n",
+        "layout: title\n",
+        ": centered\n",
+        "layout: two-column\n",
+        "---\n",
+        "\n",
+        "Hello ::effect[spin] and ::effect[wobble]\n",
+        "then ::effect[fade: a=1, a=2]\n",
+        "\n",
+        "## Two\n",
+        "---\n",
+        "just words\n",
+    );
+    let arena = Arena::new();
+    let (root, diagnostics) = ::parse_document_with_diagnostics(&arena, input, &options);
+
+    let found = diagnostics
+        .iter()
+        .map(|d| (d.line, d.column, d.code))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (3, 3, DiagnosticCode::InvalidMetadataLine),
+            (9, 1, DiagnosticCode::EmptyMetadataKey),
+            (10, 1, DiagnosticCode::DuplicateMetadataKey),
+            (13, 16, DiagnosticCode::UnknownEffect),
+            (14, 15, DiagnosticCode::MalformedEffect),
+            (17, 1, DiagnosticCode::UnclosedMetadataFence),
+            (18, 1, DiagnosticCode::InvalidMetadataLine),
+        ]
+    );
+    assert_eq!(
+        diagnostics.iter().nth(3).unwrap().to_string(),
+        "13:16: warning: unknown effect `spin` [unknown-effect]"
+    );
+    assert_eq!(
+        diagnostics
+            .errors()
+            .map(|d| d.message.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "duplicate effect parameter `a`",
+            "metadata block is never closed; it runs to the end of its container",
+        ]
+    );
+    assert_eq!(diagnostics.warnings().count(), 5);
+    assert!(diagnostics.iter().all(|d| d.severity == d.code.severity()));
+    assert_eq!(
+        diagnostics.errors().next().unwrap().severity,
+        Severity::Error
+    );
+
+    // The document is parsed as without diagnostics.
+    let plain = parse_document(&arena, input, &options);
+    compare_strs(&ast_values(root), &ast_values(plain), "diagnostics");
+}
+
+#[test]
+fn source_positions() {
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.table = true;
+    let input = concat!(
+        "---\n",
+        "title: Talk\n",
+        "---\n",
+        "\n",
+        "# One *two*\n",
+        "\n",
+        "> - item ::effect[spin]\n",
+        "\n",
+        "## Next\n",
+        "---\n",
+        "layout: title\n",
+        "---\n",
+        "\n",
+        "| a | b |\n",
+        "|---|---|\n",
+    );
+    let arena = Arena::new();
+    let (root, diagnostics) = ::parse_document_with_diagnostics(&arena, input, &options);
+
+    let span = |node: &AstNode| {
+        let ast = node.data.borrow();
+        (
+            ast.start_line,
+            ast.start_column,
+            ast.end_line,
+            ast.end_column,
+        )
+    };
+    let nodes = root.descendants().collect::<Vec<_>>();
+    let find =
+        |f: &dyn Fn(&NodeValue) -> bool| *nodes.iter().find(|n| f(&n.data.borrow().value)).unwrap();
+
+    assert_eq!(span(root), (1, 1, 15, 9));
+    assert_eq!(span(root.first_child().unwrap()), (1, 1, 3, 3));
+    let heading = find(&|v| matches!(v, NodeValue::Heading(..)));
+    assert_eq!(span(heading), (5, 1, 5, 11));
+    assert_eq!(span(find(&|v| *v == NodeValue::Emph)), (5, 7, 5, 11));
+    assert_eq!(span(find(&|v| *v == NodeValue::BlockQuote)), (7, 1, 7, 23));
+    assert_eq!(
+        span(find(&|v| matches!(v, NodeValue::Item(..)))),
+        (7, 3, 7, 23)
+    );
+    assert_eq!(
+        span(find(&|v| matches!(v, NodeValue::Effect(..)))),
+        (7, 10, 7, 23)
+    );
+    assert_eq!(
+        span(find(&|v| matches!(v, NodeValue::KV(..)))),
+        (11, 1, 11, 13)
+    );
+    assert_eq!(
+        span(find(&|v| matches!(v, NodeValue::Table(..)))),
+        (14, 1, 15, 9)
+    );
+    let cell = find(&|v| *v == NodeValue::TableCell)
+        .next_sibling()
+        .unwrap();
+    assert_eq!(span(cell), (14, 7, 14, 7));
+
+    let found = diagnostics
+        .iter()
+        .map(|d| (d.line, d.column))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(7, 19)]);
+
+    let deck = ::build_deck(root, &options);
+    let cover = deck.source_range(1).unwrap();
+    assert_eq!((cover.start_line, cover.end_line), (4, 8));
+    let next = deck.source_range(2).unwrap();
+    assert_eq!((next.start_line, next.end_line), (9, 15));
+    assert_eq!(
+        &input[next.start_byte..next.end_byte],
+        "## Next\n---\nlayout: title\n---\n\n| a | b |\n|---|---|\n"
+    );
+    assert_eq!(cover.end_byte, next.start_byte);
+    assert_eq!(deck.slide_at_line(2), None);
+    assert_eq!(deck.slide_at_line(7), Some(1));
+    assert_eq!(deck.slide_at_line(12), Some(2));
+    assert_eq!(deck.slide_at_line(16), None);
+}
+
+#[test]
+fn incremental_edit() {
+    use incremental::{LiveDeck, TextEdit};
+
+    let mut options = ComrakOptions::default();
+    options.extension.front_matter_delimiter = Some("---".to_string());
+    options.extension.footnotes = true;
+    let input = concat!(
+        "---\n",
+        "agenda: true\n",
+        "---\n",
+        "# Talk\n",
+        "\n",
+        "Hello.\n",
+        "\n",
+        "# One\n",
+        "\n",
+        "First [link].\n",
+        "\n",
+        "# Two\n",
+        "\n",
+        "Second.\n",
+        "\n",
+        "[link]: /url\n",
+    );
+    let arena = Arena::new();
+    let mut live = LiveDeck::new(&arena, input, &options);
+
+    let mut edit = |find: &str, text: &str| {
+        let start = live.source().find(find).unwrap();
+        let update = live.edit(&TextEdit::new(start, start + find.len(), text));
+
+        let arena = Arena::new();
+        let fresh = ::parse_deck(&arena, live.source(), &options);
+        let slides = |deck: &::slides::Deck| {
+            deck.cover
+                .iter()
+                .chain(deck.slides.iter())
+                .map(|s| (s.html.clone(), s.source, s.section))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(slides(live.deck()), slides(&fresh));
+        assert_eq!(live.deck().agenda, fresh.agenda);
+        update
+    };
+
+    let update = edit("Second", "Last");
+    assert!(!update.full);
+    assert_eq!((update.removed, update.inserted), (4..5, 4..5));
+    assert_eq!(update.changed, [4]);
+
+    let update = edit("\n[link]", "\n# Three\n\nMore.\n\n[link]");
+    assert!(!update.full);
+    assert_eq!((update.removed, update.inserted), (4..5, 4..6));
+    assert_eq!(update.changed, [2, 4, 5]);
+
+    assert!(edit("/url", "/other").full);
+    assert!(edit("Hello", "Hello[^1]").full);
+}
+
+#[test]
+fn incremental_edit_at_end() {
+    use incremental::{LiveDeck, TextEdit};
+
+    let options = ComrakOptions::default();
+    let arena = Arena::new();
+    let mut live = LiveDeck::new(&arena, "# A\n\n#", &options);
+    for &(start, end, text) in &[
+        (6, 6, "x"),
+        (7, 7, "\n\n# B"),
+        (12, 12, "\n==="),
+        (6, 7, ""),
+    ] {
+        live.edit(&TextEdit::new(start, end, text));
+
+        let arena = Arena::new();
+        let fresh = ::parse_deck(&arena, live.source(), &options);
+        let slides = |deck: &::slides::Deck| {
+            deck.cover
+                .iter()
+                .chain(deck.slides.iter())
+                .map(|s| (s.html.clone(), s.source))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(slides(live.deck()), slides(&fresh), "{:?}", live.source());
+    }
+}
+
+fn effects(input: &str) -> Vec<::nodes::EffectAttr> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, input, &ComrakOptions::default());
+    root.descendants()
+        .filter_map(|n| match n.data.borrow().value {
+            NodeValue::Effect(ref effect) => Some(effect.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn effect_params() {
+    let effects = effects(concat!(
+        "::effect[fade]\n",
+        "\n",
+        "::effect[zoom: 2, target = \"#a\", label='it''s, ok]', note=a\\,b]\n",
+    ));
+
+    assert_eq!(effects[0].name, "fade");
+    assert!(effects[0].params.is_empty());
+    assert_eq!(effects[0].target, None);
+
+    let zoom = &effects[1];
+    assert_eq!(zoom.error, None);
+    assert_eq!(zoom.name, "zoom");
+    assert_eq!(zoom.target.as_deref(), Some("#a"));
+    assert_eq!(
+        zoom.params,
+        vec![
+            ("".to_string(), "2".to_string()),
+            ("label".to_string(), "it's, ok]".to_string()),
+            ("note".to_string(), "a,b".to_string()),
+        ]
+    );
+    assert_eq!(zoom.param("label"), Some("it's, ok]"));
+}
+
+#[test]
+fn effect_malformed() {
+    let errors = effects(concat!(
+        "::effect[]\n",
+        "\n",
+        "::effect[bad name]\n",
+        "\n",
+        "::effect[x: =1]\n",
+        "\n",
+        "::effect[x: a,, b]\n",
+        "\n",
+        "::effect[x: target=a, target=b]\n",
+        "\n",
+        "::effect[x: \"a\" b]\n",
+        "\n",
+        "::effect[x: \"a]\n",
+    ))
+    .into_iter()
+    .map(|e| e.error.unwrap())
+    .collect::<Vec<_>>();
+
+    assert_eq!(
+        errors,
+        vec![
+            "empty effect directive",
+            "invalid effect name `bad name`",
+            "empty effect parameter key",
+            "empty effect parameter",
+            "duplicate effect target",
+            "unexpected text after quoted value",
+            "unterminated effect directive",
+        ]
+    );
+}
+
+#[test]
+fn effect_html() {
+    let arena = Arena::new();
+    let options = ComrakOptions::default();
+    let root = parse_document(
+        &arena,
+        "::effect[fade: in, speed=\"<1s>\", target=#a]\n\n::effect[bad name]\n",
+        &options,
+    );
+    let mut output = vec![];
+    html::format_document(root, &options, &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        concat!(
+            "<p>\n",
+            "<effect name=\"fade\" target=\"#a\" data-arg-0=\"in\" data-speed=\"&lt;1s&gt;\">\n",
+            "\n",
+            "</effect>\n",
+            "</p>\n",
+            "<p></p>\n",
+        )
+    );
+}
+
+#[test]
+fn deck_notes() {
+    let input = concat!(
+        "# Talk\n",
+        "\n",
+        "## One\n",
+        "\n",
+        "Hello\n",
+        "\n",
+        "```note\n",
+        "first\n",
+        "```\n",
+        "\n",
+        "<!-- notes: second\n",
+        "line -->\n",
+        "\n",
+        "Note: third *one*\n",
+        "\n",
+        "```note\n",
+        "fourth\n",
+        "```\n",
+        "\n",
+        "<!-- not a note -->\n",
+    );
+    let arena = Arena::new();
+    let mut options = ComrakOptions::default();
+    options.render.unsafe_ = true;
+    let deck = ::parse_deck(&arena, input, &options);
+
+    let slide = &deck.slides[0];
+    assert_eq!(
+        slide.notes,
+        vec![
+            "first\n".to_string(),
+            "second\nline".to_string(),
+            "third one".to_string(),
+            "fourth\n".to_string(),
+        ]
+    );
+    assert_eq!(
+        slide.html,
+        "<h2>One</h2>\n<p>Hello</p>\n<!-- not a note -->\n"
+    );
+
+    let root = parse_document(&arena, input, &options);
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+    let slide = &json["content"][0];
+    assert_eq!(slide["notes"].as_array().unwrap().len(), 4);
+    assert_eq!(slide["notes"][2], "third one");
+    let content = slide["content"].as_str().unwrap();
+    assert!(!content.contains("first"));
+    assert!(!content.contains("third"));
+    assert!(!content.contains("_1001110001000Notes_1001110001000_"));
+}
+
+#[test]
+fn deck_split_rules() {
+    use slides::SplitReason;
+
+    let input = concat!(
+        "# Talk\n",
+        "\n",
+        "## One\n",
+        "\n",
+        "### Detail\n",
+        "\n",
+        "***\n",
+        "\n",
+        "Loose\n",
+        "\n",
+        "<!-- slide -->\n",
+        "\n",
+        "## Two\n",
+    );
+    let arena = Arena::new();
+    let mut options = ComrakOptions {
+        split: SlideSplitOptions {
+            heading_level: 2,
+            thematic_break: true,
+            marker: true,
+        },
+        ..ComrakOptions::default()
+    };
+    let deck = ::parse_deck(&arena, input, &options);
+
+    let splits: Vec<(&str, SplitReason)> = deck
+        .slides
+        .iter()
+        .map(|s| (s.title.as_str(), s.split))
+        .collect();
+    assert_eq!(
+        splits,
+        vec![
+            ("One", SplitReason::Heading),
+            ("", SplitReason::ThematicBreak),
+            ("Two", SplitReason::Marker),
+        ]
+    );
+    assert_eq!(deck.slides[0].html, "<h2>One</h2>\n<h3>Detail</h3>\n");
+    assert_eq!(deck.slides[1].html, "<p>Loose</p>\n");
+
+    options.split = SlideSplitOptions {
+        heading_level: 0,
+        thematic_break: false,
+        marker: false,
+    };
+    let deck = ::parse_deck(&arena, input, &options);
+    assert_eq!(deck.cover.unwrap().split, SplitReason::Start);
+    assert!(deck.slides.is_empty());
+}
+
+#[test]
+fn slide_metadata_only_after_heading() {
+    html("Hi\n---\n", "<h2>Hi</h2>\n");
+    html("---\nk: v\n---\n", "<hr />\n<h2>k: v</h2>\n");
+    html("# Hi\n---\nk: v\n---\n", "<h1>Hi</h1>\n");
+}
+
+#[test]
+fn slide_json_split() {
+    let arena = Arena::new();
+    let mut options = ComrakOptions::default();
+    options.split.thematic_break = true;
+    let root = parse_document(&arena, "# Talk\n\nHi\n\n---\n\nBye\n", &options);
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+
+    assert_eq!(json["front"]["split"], "heading");
+    assert_eq!(json["content"][0]["split"], "thematic_break");
+    assert_eq!(json["content"][0]["title"], "");
+    assert_eq!(json["title"], "Talk");
+}
+
+#[test]
+fn slide_json_heading_within_slide() {
+    let arena = Arena::new();
+    let mut options = ComrakOptions::default();
+    options.split.heading_level = 1;
+    let root = parse_document(&arena, "# A\n\nfirst para\n\n## B\n\nmore\n", &options);
+    let mut output = vec![];
+    ::format_slide(root, &options, &mut output).unwrap();
+    let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+
+    assert_eq!(json["front"]["content"], "# A\nfirst para\n\n## B\nmore");
+}
+
+#[test]
+fn slide_json_html() {
+    let input = "# Talk\n\n## <b>One</b>\n\n[x](javascript:alert(1)) *y*\n";
+    let slide_json = |mode| {
+        let arena = Arena::new();
+        let mut options = ComrakOptions::default();
+        options.render.slide_content = mode;
+        let root = parse_document(&arena, input, &options);
+        let mut output = vec![];
+        ::format_slide(root, &options, &mut output).unwrap();
+        let json: ::serde_json::Value = ::serde_json::from_slice(&output).unwrap();
+        json["content"][0].clone()
+    };
+
+    let slide = slide_json(SlideContentMode::Both);
+    assert_eq!(
+        slide["html"],
+        concat!(
+            "<h2><!-- raw HTML omitted -->One<!-- raw HTML omitted --></h2>\n",
+            "<p><a href=\"\">x</a> <em>y</em></p>\n"
+        )
+    );
+    assert!(slide["content"].is_string());
+
+    let slide = slide_json(SlideContentMode::Html);
+    assert!(slide["html"].is_string());
+    assert!(slide.get("content").is_none());
+
+    let slide = slide_json(SlideContentMode::Markdown);
+    assert!(slide.get("html").is_none());
+    assert!(slide["content"].is_string());
+}
+
+#[test]
+fn exercise_full_api() {
+    let arena = ::Arena::new();
+    let default_options = ::ComrakOptions::default();
+    let node = ::parse_document(&arena, "# My document\n", &default_options);
+    let mut buffer = vec![];
+
+    // Use every member of the exposed API without any defaults.
+    // Not looking for specific outputs, just want to know if the API changes shape.
+
+    let _: std::io::Result<()> = ::format_commonmark(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_slide_standalone(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_slide_print(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_pptx(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_odp(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_beamer(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_typst(node, &default_options, &mut buffer);
+    let _: std::io::Result<()> = ::format_reveal(node, &default_options, &mut buffer);
+
+    let _: std::io::Result<()> = ::format_html(node, &default_options, &mut buffer);
+
+    let _: String = ::Anchorizer::new().anchorize("header".to_string());
+
+    let _: &AstNode = ::parse_document(&arena, "document", &default_options);
+
+    let _: (&AstNode, ::diagnostics::Diagnostics) =
+        ::parse_document_with_diagnostics(&arena, "document", &default_options);
+
+    let _: &AstNode = ::parse_document_with_broken_link_callback(
+        &arena,
+        "document",
+        &default_options,
+        Some(&mut |_: &[u8]| Some((b"abc".to_vec(), b"xyz".to_vec()))),
+    );
+
+    let _ = ::ComrakOptions {
+        extension: ::ComrakExtensionOptions {
+            strikethrough: false,
+            tagfilter: false,
+            table: false,
+            autolink: false,
+            tasklist: false,
+            superscript: false,
+            header_ids: Some("abc".to_string()),
+            footnotes: false,
+            description_lists: false,
+            front_matter_delimiter: None,
+            image_attributes: true,
+        },
+        parse: ::ComrakParseOptions {
+            smart: false,
+            default_info_string: Some("abc".to_string()),
+            effects: vec![],
+        },
+        render: ::ComrakRenderOptions {
+            hardbreaks: false,
+            github_pre_lang: false,
+            width: 123456,
+            unsafe_: false,
+            escape: false,
+            slide_content: ::SlideContentMode::Markdown,
+            theme: Some("dark".to_string()),
+            theme_dirs: vec![std::path::PathBuf::from("themes")],
+            base_dir: Some(std::path::PathBuf::from("slides")),
+            reveal: false,
+        },
+        split: ::SlideSplitOptions {
+            heading_level: 6,
+            thematic_break: false,
+            marker: false,
+        },
+        print: ::SlidePrintOptions {
+            fragments: false,
+            notes: false,
+            link_footnotes: false,
+        },
+    };
+
+    let _: String = ::markdown_to_html("# Yes", &default_options);
+
+    let deck: ::slides::Deck = ::parse_deck(&arena, "# Yes", &default_options);
+    let _: ::slides::Deck = ::build_deck(node, &default_options);
+    let _: Option<usize> = node.data.borrow().fragment;
+    let _: usize = deck.cover.as_ref().unwrap().steps;
+    let deck_metadata: &::slides::DeckMetadata = &deck.metadata;
+    let _: &Option<String> = &deck_metadata.title;
+    let _: &Option<String> = &deck_metadata.author;
+    let _: &Option<String> = &deck_metadata.date;
+    let _: &Option<String> = &deck_metadata.theme;
+    let _: &Option<String> = &deck_metadata.language;
+    let _: &Option<::slides::AspectRatio> = &deck_metadata.aspect_ratio;
+    let _: Option<::slides::AspectRatio> = ::slides::AspectRatio::parse("16:9");
+    let _: &::slides::Metadata = &deck_metadata.entries;
+    let _: ::slides::DeckMetadata =
+        ::slides::DeckMetadata::from_entries(deck_metadata.entries.clone());
+    let _: Option<&::nodes::MetaValue> = deck_metadata.entries.get("key");
+    let _: Option<&str> = deck_metadata.entries.get_str("key");
+    let _: usize = deck_metadata.entries.len();
+    let _: bool = deck_metadata.entries.is_empty();
+    let _ = deck_metadata.entries.iter();
+    let mut metadata = ::slides::Metadata::new();
+    metadata.insert("key".to_string(), ::nodes::MetaValue::Bool(true));
+    let attributes = ::nodes::ImageAttributes::default();
+    let _: Option<u32> = attributes.width;
+    let _: Option<u32> = attributes.height;
+    let _: Option<::nodes::ImageLayout> = attributes.layout;
+    let _: &Vec<::nodes::ImageFilter> = &attributes.filters;
+    let _: bool = attributes.is_empty();
+    let effect = ::nodes::EffectAttr::default();
+    let _: &String = &effect.name;
+    let _: &Vec<(String, String)> = &effect.params;
+    let _: &Option<String> = &effect.target;
+    let _: &Option<String> = &effect.error;
+    let _: Option<&str> = effect.param("key");
+    let value = ::nodes::MetaValue::String("value".to_string());
+    let _: Option<&str> = value.as_str();
+    let _: Option<f64> = value.as_f64();
+    let _: Option<bool> = value.as_bool();
+    let _: Option<&[::nodes::MetaValue]> = value.as_list();
+    let _: Option<&[(String, ::nodes::MetaValue)]> = value.as_map();
+    let _: String = value.to_string();
+    for slide in deck.cover.iter().chain(deck.slides.iter()) {
+        let _: &String = &slide.title;
+        match slide.split {
+            ::slides::SplitReason::Start => {}
+            ::slides::SplitReason::Heading => {}
+            ::slides::SplitReason::ThematicBreak => {}
+            ::slides::SplitReason::Marker => {}
+            ::slides::SplitReason::Generated => {}
+        }
+        let _: &::slides::Metadata = &slide.metadata;
+        let _: &Vec<String> = &slide.notes;
+        let _: &Vec<&AstNode> = &slide.nodes;
+        let _: Option<::slides::Layout> = slide.layout;
+        let _: &Vec<::slides::Region> = &slide.regions;
+        let _: bool = slide.has_regions();
+        let _: ::slides::Transitions = slide.transitions;
+        let _: bool = slide.agenda;
+        let _: Option<usize> = slide.section;
+        let _: &String = &slide.html;
+    }
+    for entry in &deck.agenda {
+        let _: &String = &entry.title;
+        let _: u32 = entry.level;
+        let _: usize = entry.slide;
+        let _: &String = &entry.anchor;
+    }
+    let _: String = ::slides::slide_anchor(1);
+    match default_options.render.slide_content {
+        ::SlideContentMode::Markdown => {}
+        ::SlideContentMode::Html => {}
+        ::SlideContentMode::Both => {}
+    }
+
+    //
+
+    let ast = node.data.borrow();
+    let _: (u32, usize) = (ast.start_line, ast.start_column);
+    let _: (u32, usize) = (ast.end_line, ast.end_column);
+    match &ast.value {
+        ::nodes::NodeValue::Document => {}
+        ::nodes::NodeValue::FrontMatter(_) => {}
+        ::nodes::NodeValue::BlockQuote => {}
+        ::nodes::NodeValue::List(nl) | ::nodes::NodeValue::Item(nl) => {
+            match nl.list_type {
+                ::nodes::ListType::Bullet => {}
+                ::nodes::ListType::Ordered => {}
+            }
+            let _: usize = nl.start;
+            match nl.delimiter {
+                ::nodes::ListDelimType::Period => {}
+                ::nodes::ListDelimType::Paren => {}
+            }
+            let _: u8 = nl.bullet_char;
+            let _: bool = nl.tight;
+        }
+        ::nodes::NodeValue::DescriptionList => {}
+        ::nodes::NodeValue::DescriptionItem(_ndi) => {}
+        ::nodes::NodeValue::DescriptionTerm => {}
+        ::nodes::NodeValue::DescriptionDetails => {}
+        ::nodes::NodeValue::CodeBlock(ncb) => {
+            let _: bool = ncb.fenced;
+            let _: u8 = ncb.fence_char;
+            let _: usize = ncb.fence_length;
+            let _: Vec<u8> = ncb.info;
+            let _: Vec<u8> = ncb.literal;
+        }
+        ::nodes::NodeValue::HtmlBlock(nhb) => {
+            let _: Vec<u8> = nhb.literal;
+        }
+        ::nodes::NodeValue::Paragraph => {}
+        ::nodes::NodeValue::Heading(nh) => {
+            let _: u32 = nh.level;
+            let _: bool = nh.setext;
         }
         ::nodes::NodeValue::ThematicBreak => {}
         ::nodes::NodeValue::FootnoteDefinition(name) => {
@@ -1220,40 +3550,16 @@ fn exercise_full_api() {
             let _: &Vec<u8> = name;
         }
         ::nodes::NodeValue::SlideMetaDataBlock(ref _smd) => {}
-        ::nodes::NodeValue::KV(ref _kv) => {}
-        NodeValue::Document
-        | NodeValue::FrontMatter(_)
-        | NodeValue::BlockQuote
-        | NodeValue::List(_)
-        | NodeValue::Item(_)
-        | NodeValue::DescriptionList
-        | NodeValue::DescriptionItem(_)
-        | NodeValue::DescriptionTerm
-        | NodeValue::DescriptionDetails
-        | NodeValue::SlideMetaDataBlock(_)
-        | NodeValue::KV(_)
-        | NodeValue::CodeBlock(_)
-        | NodeValue::HtmlBlock(_)
-        | NodeValue::Paragraph
-        | NodeValue::Heading(_)
-        | NodeValue::ThematicBreak
-        | NodeValue::FootnoteDefinition(_)
-        | NodeValue::Table(_)
-        | NodeValue::TableRow(_)
-        | NodeValue::TableCell
-        | NodeValue::Text(_)
-        | NodeValue::TaskItem(_)
-        | NodeValue::SoftBreak
-        | NodeValue::LineBreak
-        | NodeValue::Code(_)
-        | NodeValue::HtmlInline(_)
-        | NodeValue::Emph
-        | NodeValue::Strong
-        | NodeValue::Strikethrough
-        | NodeValue::Superscript
-        | NodeValue::Link(_)
-        | NodeValue::Image(_)
-        | NodeValue::FootnoteReference(_)
-        | NodeValue::Effect(_) => todo!(),
+        ::nodes::NodeValue::KV(ref kv) => {
+            let _: &Vec<u8> = &kv.key;
+            match kv.value {
+                ::nodes::MetaValue::String(ref _s) => {}
+                ::nodes::MetaValue::Number(_n) => {}
+                ::nodes::MetaValue::Bool(_b) => {}
+                ::nodes::MetaValue::List(ref _items) => {}
+                ::nodes::MetaValue::Map(ref _entries) => {}
+            }
+        }
+        NodeValue::Effect(_) => todo!(),
     }
 }
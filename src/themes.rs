@@ -0,0 +1,203 @@
+//! Themes for standalone slide output.
+//!
+//! A theme is a named set of CSS custom properties (colours and spacing), fonts and extra CSS.
+//! The built-in themes are `light` (the default), `dark` and `high-contrast`.  Other themes are
+//! loaded from a directory named after the theme, which may contain:
+//!
+//! * `theme.yaml`, with `variables` and `fonts` maps in slide metadata syntax:
+//!
+//!   ```yaml
+//!   variables:
+//!     background: "#fdf6e3"
+//!     accent: "#268bd2"
+//!   fonts:
+//!     body: Georgia, serif
+//!   ```
+//!
+//! * `theme.css`, with any further CSS, such as `@font-face` rules.
+//!
+//! Missing variables and fonts are taken from the `light` theme.
+
+use parser::metadata;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the theme used when none is selected.
+pub const DEFAULT_THEME: &str = "light";
+
+/// The names of the built-in themes.
+pub const BUILTIN_THEMES: [&str; 3] = ["light", "dark", "high-contrast"];
+
+/// A named bundle of CSS variables, fonts and CSS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// The theme's name.
+    pub name: String,
+
+    /// CSS custom properties, written as `--name: value`, in order.
+    pub variables: Vec<(String, String)>,
+
+    /// Font stacks by role (`body`, `heading` and `code`), written as `--font-role: value`.
+    pub fonts: Vec<(String, String)>,
+
+    /// Further CSS included after the variables.
+    pub css: String,
+}
+
+impl Theme {
+    /// The built-in theme with the given name.
+    ///
+    /// ```
+    /// # use comrak::themes::Theme;
+    /// assert_eq!(Theme::builtin("dark").unwrap().variable("background"), Some("#1e1e1e"));
+    /// assert!(Theme::builtin("plaid").is_none());
+    /// ```
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let variables: &[(&str, &str)] = match name {
+            "light" => &[
+                ("background", "#ffffff"),
+                ("foreground", "#222222"),
+                ("heading", "#111111"),
+                ("accent", "#0366d6"),
+                ("muted", "#f4f4f4"),
+            ],
+            "dark" => &[
+                ("background", "#1e1e1e"),
+                ("foreground", "#e0e0e0"),
+                ("heading", "#ffffff"),
+                ("accent", "#58a6ff"),
+                ("muted", "#2d2d2d"),
+            ],
+            "high-contrast" => &[
+                ("background", "#000000"),
+                ("foreground", "#ffffff"),
+                ("heading", "#ffff00"),
+                ("accent", "#00ffff"),
+                ("muted", "#1a1a1a"),
+            ],
+            _ => return None,
+        };
+
+        let css = if name == "high-contrast" {
+            ".deck > section { font-weight: 600; }\n.deck > section a { text-decoration: underline; }\n"
+        } else {
+            ""
+        };
+
+        Some(Theme {
+            name: name.to_string(),
+            variables: pairs(variables),
+            fonts: pairs(&[
+                (
+                    "body",
+                    "-apple-system, \"Segoe UI\", Helvetica, Arial, sans-serif",
+                ),
+                (
+                    "heading",
+                    "-apple-system, \"Segoe UI\", Helvetica, Arial, sans-serif",
+                ),
+                ("code", "SFMono-Regular, Consolas, Menlo, monospace"),
+            ]),
+            css: css.to_string(),
+        })
+    }
+
+    /// Load a theme from a directory, named after the directory.
+    pub fn load(dir: &Path) -> io::Result<Theme> {
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("theme directory {} not found", dir.display()),
+            ));
+        }
+
+        let mut theme = Theme::builtin(DEFAULT_THEME).unwrap();
+        theme.name = dir
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        theme.css.clear();
+
+        let yaml = dir.join("theme.yaml");
+        if yaml.exists() {
+            for kv in metadata::parse(&fs::read(yaml)?) {
+                let target = match &kv.key[..] {
+                    b"variables" => &mut theme.variables,
+                    b"fonts" => &mut theme.fonts,
+                    _ => continue,
+                };
+                for (key, value) in kv.value.as_map().unwrap_or(&[]) {
+                    set(target, key, value.to_string());
+                }
+            }
+        }
+
+        let css = dir.join("theme.css");
+        if css.exists() {
+            theme.css = fs::read_to_string(css)?;
+        }
+
+        Ok(theme)
+    }
+
+    /// Find a theme by name: a built-in theme, or else a directory of that name in one of
+    /// `dirs`, searched in order.
+    pub fn resolve(name: &str, dirs: &[PathBuf]) -> io::Result<Theme> {
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            for dir in dirs {
+                let dir = dir.join(name);
+                if dir.is_dir() {
+                    return Theme::load(&dir);
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("unknown theme `{}`", name),
+        ))
+    }
+
+    /// The value of a variable.
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// A CSS rule setting the theme's variables and fonts on `selector`.
+    pub fn rule(&self, selector: &str) -> String {
+        let mut css = format!("{} {{\n", selector);
+        for (name, value) in &self.variables {
+            css += &format!("  --{}: {};\n", name, value);
+        }
+        for (role, value) in &self.fonts {
+            css += &format!("  --font-{}: {};\n", role, value);
+        }
+        css += "}\n";
+        css
+    }
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn set(pairs: &mut Vec<(String, String)>, key: &str, value: String) {
+    match pairs.iter_mut().find(|(k, _)| k == key) {
+        Some(pair) => pair.1 = value,
+        None => pairs.push((key.to_string(), value)),
+    }
+}
@@ -0,0 +1,605 @@
+//! Typst output: a deck written as a Typst document, with a page per slide.
+
+use export::{self, footnote_blocks, Footnotes, LinkTarget};
+use nodes::{AstNode, ListType, NodeCode, NodeValue, TableAlignment};
+use package::layout::{centimetres, Rect, MARGIN};
+use package::{deck_theme, theme_color, theme_font};
+use parser::ComrakOptions;
+use slides::{build_deck, collect_text, slide_anchor, AgendaEntry, Deck, Slide, SplitReason};
+use std::io::{self, Write};
+use std::ptr;
+use themes::Theme;
+
+/// Formats an AST as a Typst document, with one page per slide of the deck.
+///
+/// Each slide's first heading becomes the heading at the top of its page, and lists, tables,
+/// images from files within `options.render.base_dir`, code and emphasis are translated to
+/// Typst markup, with Typst's special characters escaped.  Pages are sized by the deck's aspect
+/// ratio, coloured and set in the fonts of the deck's theme, and each is labelled with the
+/// slide's anchor, such as `<slide-2>`, so that links to slides of the deck keep working.  A
+/// slide with more than one region lays them out in a `grid`.  When the deck's metadata has a
+/// title, a title page comes before the first slide.
+///
+/// ```
+/// # use comrak::{format_typst, parse_document, Arena, ComrakOptions};
+/// let arena = Arena::new();
+/// let options = ComrakOptions::default();
+/// let root = parse_document(&arena, "# Price: $5\n\n- *one* #1\n", &options);
+/// let mut typst = vec![];
+/// format_typst(root, &options, &mut typst).unwrap();
+/// let typst = String::from_utf8(typst).unwrap();
+///
+/// assert!(typst.contains("#metadata(\"slide-1\") <slide-1>\n= Price: \\$5\n"));
+/// assert!(typst.contains("- #emph[one] \\#1\n"));
+/// ```
+pub fn format_document<'a>(
+    root: &'a AstNode<'a>,
+    options: &ComrakOptions,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let deck = build_deck(root, options);
+    let theme = deck_theme(&deck, options)?;
+    let mut f = TypstFormatter {
+        options,
+        output: String::new(),
+        prefix: String::new(),
+        line_start: true,
+        after_expression: false,
+        footnotes: Footnotes::new(root),
+        slides: deck.slides.len() + deck.cover.iter().len(),
+    };
+
+    f.preamble(&deck, &theme);
+    for (ix, slide) in deck.cover.iter().chain(deck.slides.iter()).enumerate() {
+        if ix > 0 || deck.metadata.title.is_some() {
+            f.blankline();
+            f.write("#pagebreak()");
+            f.blankline();
+        }
+        f.page(slide, ix + 1, &deck.agenda);
+    }
+    f.cr();
+    output.write_all(f.output.as_bytes())
+}
+
+/// Escape text for Typst markup.  Characters which only have a meaning at the start of a
+/// line, such as list markers, are escaped there, as are the dashes and dots making up
+/// shorthands such as `--` and `...`.
+fn escape(s: &str, mut line_start: bool) -> String {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut escaped = String::with_capacity(s.len());
+    for (ix, &c) in chars.iter().enumerate() {
+        let next = chars.get(ix + 1).cloned();
+        let special = match c {
+            '\\' | '*' | '_' | '`' | '$' | '#' | '<' | '>' | '@' | '[' | ']' | '~' | '/' => true,
+            '-' => line_start || next == Some('-') || next == Some('?'),
+            '.' => next == Some('.') || (line_start && ix > 0 && chars[ix - 1].is_ascii_digit()),
+            '+' | '=' => line_start,
+            _ => false,
+        };
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        line_start = line_start && (c.is_ascii_digit() || (c == ' ' && escaped.trim().is_empty()));
+    }
+    escaped
+}
+
+/// Escape a string for a Typst string literal.
+fn escape_string(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The text of `lang` and `region` for a language tag such as `en-GB`, if it is one Typst
+/// understands.
+fn language(tag: &str) -> Option<(String, Option<String>)> {
+    let mut parts = tag.splitn(2, ['-', '_']);
+    let lang = parts.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&lang.len()) || !lang.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let region = parts
+        .next()
+        .map(str::to_ascii_uppercase)
+        .filter(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()));
+    Some((lang, region))
+}
+
+struct TypstFormatter<'a, 'o> {
+    options: &'o ComrakOptions,
+    output: String,
+
+    /// The indentation continuing the current list item.
+    prefix: String,
+
+    /// Whether the next text starts a line, or follows a list marker.
+    line_start: bool,
+
+    /// Whether the last thing written was an embedded expression, which text starting with
+    /// `.` or `(` would otherwise continue.
+    after_expression: bool,
+
+    /// Footnote definitions by name, written as `#footnote`s where they are referenced.
+    footnotes: Footnotes<'a>,
+
+    slides: usize,
+}
+
+impl<'a, 'o> TypstFormatter<'a, 'o> {
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if self.output.ends_with('\n') {
+            self.output += &self.prefix;
+        }
+        if self.after_expression && (s.starts_with('.') || s.starts_with('(')) {
+            self.output.push(';');
+        }
+        self.output += s;
+        self.line_start = false;
+        self.after_expression = false;
+    }
+
+    /// Write the closing bracket of an embedded expression.
+    fn close(&mut self) {
+        self.write("]");
+        self.after_expression = true;
+    }
+
+    fn cr(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+        self.line_start = true;
+        self.after_expression = false;
+    }
+
+    fn blankline(&mut self) {
+        self.cr();
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        let escaped = escape(s, self.line_start);
+        self.write(&escaped);
+    }
+
+    fn preamble(&mut self, deck: &Deck, theme: &Theme) {
+        let metadata = &deck.metadata;
+        let size = Rect::slide(metadata.aspect_ratio.unwrap_or_default());
+
+        let mut document = vec![];
+        if let Some(ref title) = metadata.title {
+            document.push(format!("title: \"{}\"", escape_string(title)));
+        }
+        if let Some(ref author) = metadata.author {
+            document.push(format!("author: \"{}\"", escape_string(author)));
+        }
+        if !document.is_empty() {
+            self.output += &format!("#set document({})\n", document.join(", "));
+        }
+        self.output += &format!(
+            "#set page(width: {}, height: {}, margin: {}, fill: rgb(\"#{}\"))\n",
+            centimetres(size.width),
+            centimetres(size.height),
+            centimetres(MARGIN),
+            theme_color(theme, "background")
+        );
+
+        let mut text = vec![];
+        if let Some(font) = theme_font(theme, "body") {
+            text.push(format!("font: \"{}\"", escape_string(&font)));
+        }
+        text.push("size: 24pt".to_string());
+        text.push(format!(
+            "fill: rgb(\"#{}\")",
+            theme_color(theme, "foreground")
+        ));
+        if let Some((lang, region)) = metadata.language.as_deref().and_then(language) {
+            text.push(format!("lang: \"{}\"", lang));
+            if let Some(region) = region {
+                text.push(format!("region: \"{}\"", region));
+            }
+        }
+        self.output += &format!("#set text({})\n", text.join(", "));
+
+        let mut heading = vec![format!("fill: rgb(\"#{}\")", theme_color(theme, "heading"))];
+        if let Some(font) = theme_font(theme, "heading") {
+            heading.insert(0, format!("font: \"{}\"", escape_string(&font)));
+        }
+        self.output += &format!("#show heading: set text({})\n", heading.join(", "));
+        self.output += "#show heading.where(level: 1): set text(size: 36pt)\n";
+        if let Some(font) = theme_font(theme, "code") {
+            self.output += &format!("#show raw: set text(font: \"{}\")\n", escape_string(&font));
+        }
+        self.output += "#show table.cell.where(y: 0): strong\n";
+        self.output += &format!(
+            "#show link: set text(fill: rgb(\"#{}\"))\n",
+            theme_color(theme, "accent")
+        );
+
+        if let Some(ref title) = metadata.title {
+            self.blankline();
+            self.write("#align(center + horizon)[");
+            self.cr();
+            self.write("#text(size: 40pt, weight: \"bold\")[");
+            self.text(title);
+            self.close();
+            for line in metadata.author.iter().chain(metadata.date.iter()) {
+                self.blankline();
+                self.text(line);
+            }
+            self.cr();
+            self.write("]");
+            self.cr();
+        }
+    }
+
+    fn page(&mut self, slide: &Slide<'a>, number: usize, agenda: &[AgendaEntry]) {
+        let anchor = slide_anchor(number);
+        let title_node = slide
+            .nodes
+            .iter()
+            .cloned()
+            .find(|n| matches!(n.data.borrow().value, NodeValue::Heading(..)));
+
+        self.write(&format!("#metadata(\"{}\") <{}>", anchor, anchor));
+        self.cr();
+        match title_node {
+            Some(heading) => {
+                self.write("= ");
+                self.line_start = true;
+                for child in heading.children() {
+                    self.format(child);
+                }
+                self.blankline();
+            }
+            None if slide.split == SplitReason::Generated => {
+                self.write("= ");
+                self.text(&slide.title);
+                self.blankline();
+            }
+            None => (),
+        }
+
+        let grid = slide.regions.len() > 1;
+        if grid {
+            let columns = vec!["1fr"; slide.regions.len()].join(", ");
+            self.write(&format!("#grid(columns: ({}), gutter: 1cm,", columns));
+            self.cr();
+        }
+        for (ix, region) in slide.regions.iter().enumerate() {
+            if grid {
+                self.write("[");
+                self.cr();
+            }
+            for &node in &region.nodes {
+                if !title_node.is_some_and(|t| ptr::eq(t, node)) {
+                    self.format(node);
+                }
+            }
+            if slide.agenda && ix == slide.regions.len() - 1 {
+                self.agenda(agenda, slide.section);
+            }
+            if grid {
+                self.cr();
+                self.write("],");
+                self.cr();
+            }
+        }
+        if grid {
+            self.write(")");
+            self.cr();
+        }
+    }
+
+    /// The deck's agenda as a nested list, with the current section in bold.
+    fn agenda(&mut self, agenda: &[AgendaEntry], current: Option<usize>) {
+        let base = agenda.iter().map(|e| e.level).min().unwrap_or(1);
+        let mut depth = 0;
+        self.blankline();
+        for (ix, entry) in agenda.iter().enumerate() {
+            depth = (entry.level - base).min(if ix == 0 { 0 } else { depth + 1 });
+            self.cr();
+            self.write(&"  ".repeat(depth as usize));
+            self.write(&format!("- #link(<{}>)[", entry.anchor));
+            if current == Some(ix) {
+                self.write("#strong[");
+                self.text(&entry.title);
+                self.close();
+            } else {
+                self.text(&entry.title);
+            }
+            self.close();
+        }
+        self.blankline();
+    }
+
+    fn format(&mut self, node: &'a AstNode<'a>) {
+        export::walk(node, |node, entering| self.format_node(node, entering));
+    }
+
+    /// The `#link` opening a link to `url`, if it can be followed.
+    fn link(&self, url: &[u8]) -> Option<String> {
+        Some(match export::link_target(url, self.options, self.slides)? {
+            LinkTarget::Slide(anchor) => format!("#link(<{}>)[", anchor),
+            LinkTarget::Url(url) => format!("#link(\"{}\")[", escape_string(&url)),
+        })
+    }
+
+    /// A footnote's paragraphs, as the content of `#footnote`.  The definition is set aside
+    /// while it is written, so that a footnote referring to itself is not expanded again.
+    fn footnote(&mut self, name: &[u8]) {
+        let definition = match self.footnotes.take(name) {
+            Some(definition) => definition,
+            None => return,
+        };
+        self.write("#footnote[");
+        for (ix, nodes) in footnote_blocks(definition).into_iter().enumerate() {
+            if ix > 0 {
+                self.write(" #parbreak() ");
+            }
+            for node in nodes {
+                self.format(node);
+            }
+        }
+        self.output = self.output.trim_end().to_string();
+        self.close();
+        self.footnotes.restore(name, definition);
+    }
+
+    fn format_node(&mut self, node: &'a AstNode<'a>, entering: bool) -> bool {
+        match node.data.borrow().value {
+            NodeValue::Document => (),
+            NodeValue::FrontMatter(_)
+            | NodeValue::SlideMetaDataBlock(_)
+            | NodeValue::KV(_)
+            | NodeValue::HtmlBlock(_)
+            | NodeValue::HtmlInline(_)
+            | NodeValue::Effect(_)
+            | NodeValue::FootnoteDefinition(_) => return false,
+            NodeValue::BlockQuote => {
+                if entering {
+                    self.blankline();
+                    self.write("#quote(block: true)[");
+                    self.cr();
+                } else {
+                    self.cr();
+                    self.write("]");
+                    self.blankline();
+                }
+            }
+            NodeValue::List(..) => {
+                let nested = node
+                    .parent()
+                    .is_some_and(|p| matches!(p.data.borrow().value, NodeValue::Item(..)));
+                if nested {
+                    self.cr();
+                } else {
+                    self.blankline();
+                }
+            }
+            NodeValue::Item(ref nl) => {
+                if entering {
+                    self.cr();
+                    match nl.list_type {
+                        ListType::Bullet => self.write("- "),
+                        ListType::Ordered => {
+                            let index = node.preceding_siblings().count() - 1;
+                            self.write(&format!("{}. ", nl.start + index));
+                        }
+                    }
+                    self.line_start = true;
+                    self.prefix += "  ";
+                } else {
+                    let len = self.prefix.len() - 2;
+                    self.prefix.truncate(len);
+                }
+            }
+            NodeValue::DescriptionList => self.blankline(),
+            NodeValue::DescriptionItem(..) => (),
+            NodeValue::DescriptionTerm => {
+                if entering {
+                    self.cr();
+                    self.write("/ ");
+                    self.line_start = true;
+                } else {
+                    self.output = self.output.trim_end().to_string();
+                    self.write(": ");
+                }
+            }
+            NodeValue::DescriptionDetails => {
+                if entering {
+                    self.prefix += "  ";
+                } else {
+                    let len = self.prefix.len() - 2;
+                    self.prefix.truncate(len);
+                }
+            }
+            NodeValue::Heading(ref nh) => {
+                if entering {
+                    self.blankline();
+                    self.write(&format!("{} ", "=".repeat(nh.level.max(2) as usize)));
+                    self.line_start = true;
+                } else {
+                    self.blankline();
+                }
+            }
+            NodeValue::CodeBlock(ref ncb) => {
+                if entering {
+                    let literal = String::from_utf8_lossy(&ncb.literal);
+                    let info = String::from_utf8_lossy(&ncb.info);
+                    let lang = info.split_whitespace().next().unwrap_or("");
+                    let lang = if lang
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+#_-".contains(c))
+                    {
+                        lang
+                    } else {
+                        ""
+                    };
+                    let mut ticks = 0;
+                    let mut longest = 0;
+                    for c in literal.chars() {
+                        ticks = if c == '`' { ticks + 1 } else { 0 };
+                        longest = longest.max(ticks);
+                    }
+                    let fence = "`".repeat((longest + 1).max(3));
+
+                    self.blankline();
+                    self.write(&fence);
+                    self.write(lang);
+                    for line in literal.lines() {
+                        self.output.push('\n');
+                        self.write(line);
+                    }
+                    self.output.push('\n');
+                    self.write(&fence);
+                    self.blankline();
+                }
+            }
+            NodeValue::ThematicBreak => {
+                if entering {
+                    self.blankline();
+                    self.write("#line(length: 100%)");
+                    self.blankline();
+                }
+            }
+            NodeValue::Paragraph => {
+                if !entering {
+                    if export::tight(node) {
+                        self.cr();
+                    } else {
+                        self.blankline();
+                    }
+                }
+            }
+            NodeValue::Text(ref literal) => {
+                if entering {
+                    self.text(&String::from_utf8_lossy(literal));
+                }
+            }
+            NodeValue::LineBreak => {
+                if entering {
+                    self.write("\\");
+                    self.cr();
+                }
+            }
+            NodeValue::SoftBreak => {
+                if entering {
+                    self.cr();
+                }
+            }
+            NodeValue::Code(NodeCode { ref literal, .. }) => {
+                if entering {
+                    let literal = String::from_utf8_lossy(literal);
+                    if literal.contains('`') || literal.is_empty() {
+                        self.write(&format!("#raw(\"{}\")", escape_string(&literal)));
+                        self.after_expression = true;
+                    } else {
+                        self.write(&format!("`{}`", literal));
+                    }
+                }
+            }
+            NodeValue::Strong => self.wrap("#strong[", entering),
+            NodeValue::Emph => self.wrap("#emph[", entering),
+            NodeValue::Strikethrough => self.wrap("#strike[", entering),
+            NodeValue::Superscript => self.wrap("#super[", entering),
+            NodeValue::Link(ref nl) => {
+                if let Some(command) = self.link(&nl.url) {
+                    self.wrap(&command, entering);
+                }
+            }
+            NodeValue::Image(ref nl) => {
+                if let Some(path) = export::image_path(&nl.url, self.options) {
+                    if entering {
+                        let alt = collect_text(node);
+                        self.write(&format!(
+                            "#image(\"{}\", alt: \"{}\", width: 100%, height: 60%, fit: \"contain\")",
+                            escape_string(&path),
+                            escape_string(&alt)
+                        ));
+                        self.after_expression = true;
+                    }
+                    return false;
+                }
+            }
+            NodeValue::Table(ref alignments) => {
+                if entering {
+                    let align = alignments
+                        .iter()
+                        .map(|alignment| match *alignment {
+                            TableAlignment::None => "auto",
+                            TableAlignment::Left => "left",
+                            TableAlignment::Center => "center",
+                            TableAlignment::Right => "right",
+                        })
+                        .collect::<Vec<_>>();
+                    self.blankline();
+                    self.write("#table(");
+                    self.cr();
+                    self.prefix += "  ";
+                    self.write(&format!("columns: {},", alignments.len()));
+                    self.cr();
+                    self.write(&format!(
+                        "align: ({}{}),",
+                        align.join(", "),
+                        if align.len() == 1 { "," } else { "" }
+                    ));
+                    self.cr();
+                } else {
+                    let len = self.prefix.len() - 2;
+                    self.prefix.truncate(len);
+                    self.write(")");
+                    self.blankline();
+                }
+            }
+            NodeValue::TableRow(header) => {
+                if entering {
+                    if header {
+                        self.write("table.header(");
+                    }
+                } else {
+                    self.write(if header { ")," } else { "," });
+                    self.cr();
+                }
+            }
+            NodeValue::TableCell => {
+                if entering {
+                    if node.previous_sibling().is_some() {
+                        self.write(", ");
+                    }
+                    self.write("[");
+                    self.line_start = true;
+                } else {
+                    self.write("]");
+                }
+            }
+            NodeValue::FootnoteReference(ref name) => {
+                if entering {
+                    self.footnote(name);
+                }
+            }
+            NodeValue::TaskItem(checked) => {
+                if entering {
+                    self.write(if checked { "\u{2612} " } else { "\u{2610} " });
+                }
+            }
+        }
+        true
+    }
+
+    /// Open or close a function call taking the node's content.
+    fn wrap(&mut self, open: &str, entering: bool) {
+        if entering {
+            self.write(open);
+            self.line_start = true;
+        } else {
+            self.close();
+        }
+    }
+}